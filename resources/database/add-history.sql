@@ -0,0 +1,5 @@
+insert into play_history (
+	guild_id, queued_at, source_url, title
+) values (
+	$1, $2, $3, $4
+);
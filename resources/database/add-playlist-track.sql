@@ -0,0 +1,5 @@
+insert into playlist_track (
+	guild_id, name, track_index, source_url, title
+) values (
+	$1, $2, $3, $4, $5
+);
@@ -0,0 +1,5 @@
+insert into playlist (
+	guild_id, name
+) values (
+	$1, $2
+);
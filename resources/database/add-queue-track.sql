@@ -0,0 +1,5 @@
+insert into guild_queue (
+	guild_id, queue_index, source_url, title, requested_by, position_ms, loops
+) values (
+	$1, $2, $3, $4, $5, $6, $7
+);
@@ -0,0 +1,2 @@
+delete from guild_queue
+where guild_id = $1;
@@ -10,3 +10,47 @@ create table if not exists guild_config (
 	volume_clip real check (volume_clip >= 0.0 and volume_clip <= 1.0),
 	volume_play real check (volume_play >= 0.0 and volume_play <= 1.0)
 );
+
+-- loops is null when the track loops forever
+create table if not exists guild_queue (
+	guild_id bigint not null,
+	queue_index bigint not null,
+	source_url text not null,
+	title text,
+	position_ms bigint not null default 0,
+	loops bigint,
+	primary key (guild_id, queue_index)
+);
+
+-- queued_at is in microseconds since the unix epoch
+create table if not exists play_history (
+	guild_id bigint not null,
+	queued_at bigint not null,
+	source_url text not null,
+	title text
+);
+
+create index if not exists play_history_guild on play_history (guild_id, queued_at);
+
+create table if not exists playlist (
+	guild_id bigint not null,
+	name text not null,
+	primary key (guild_id, name)
+);
+
+create table if not exists playlist_track (
+	guild_id bigint not null,
+	name text not null,
+	track_index bigint not null,
+	source_url text not null,
+	title text,
+	primary key (guild_id, name, track_index)
+);
+
+-- loudness is null when the clip is silent, and modified_at is in microseconds
+-- since the unix epoch
+create table if not exists clip_loudness (
+	clip_path text primary key,
+	loudness double precision,
+	modified_at bigint not null
+);
@@ -0,0 +1,2 @@
+delete from playlist_track
+where guild_id = $1 and name = $2;
@@ -0,0 +1,2 @@
+delete from playlist
+where guild_id = $1 and name = $2;
@@ -0,0 +1,3 @@
+select loudness, modified_at
+from clip_loudness
+where clip_path = $1;
@@ -0,0 +1,3 @@
+select crossfade_ms
+from guild_config
+where guild_id = $1;
@@ -0,0 +1,3 @@
+select dj_role
+from guild_config
+where guild_id = $1;
@@ -0,0 +1,3 @@
+select duck_level
+from guild_config
+where guild_id = $1;
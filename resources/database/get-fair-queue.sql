@@ -0,0 +1,5 @@
+-- the any driver cannot decode sqlite booleans, and postgres can only cast
+-- booleans to integer rather than bigint
+select cast(fair_queue as integer)
+from guild_config
+where guild_id = $1;
@@ -0,0 +1,5 @@
+-- the any driver cannot decode sqlite booleans, and postgres can only cast
+-- booleans to integer rather than bigint
+select filter_bass_boost, filter_rate, filter_speed, filter_pitch, cast(filter_normalize as integer)
+from guild_config
+where guild_id = $1;
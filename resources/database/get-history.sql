@@ -0,0 +1,5 @@
+select source_url, title, queued_at
+from play_history
+where guild_id = $1
+order by queued_at desc
+limit $2;
@@ -0,0 +1,4 @@
+select source_url, title
+from playlist_track
+where guild_id = $1 and name = $2
+order by track_index;
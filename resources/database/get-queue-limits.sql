@@ -0,0 +1,3 @@
+select user_track_limit, user_duration_limit_ms
+from guild_config
+where guild_id = $1;
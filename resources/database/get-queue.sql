@@ -0,0 +1,4 @@
+select source_url, title, requested_by, position_ms, loops
+from guild_queue
+where guild_id = $1
+order by queue_index;
@@ -0,0 +1,3 @@
+select skip_threshold
+from guild_config
+where guild_id = $1;
@@ -0,0 +1,7 @@
+select playlist.name, count(playlist_track.track_index)
+from playlist
+left join playlist_track
+	on playlist_track.guild_id = playlist.guild_id and playlist_track.name = playlist.name
+where playlist.guild_id = $1
+group by playlist.name
+order by playlist.name;
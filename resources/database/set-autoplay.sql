@@ -0,0 +1,7 @@
+insert into guild_config (
+	guild_id, autoplay
+) values (
+	$1, $2
+) on conflict (guild_id) do
+	update set autoplay = excluded.autoplay
+;
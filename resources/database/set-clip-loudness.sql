@@ -0,0 +1,9 @@
+insert into clip_loudness (
+	clip_path, loudness, modified_at
+) values (
+	$1, $2, $3
+) on conflict (clip_path) do
+	update set
+		loudness = excluded.loudness,
+		modified_at = excluded.modified_at
+;
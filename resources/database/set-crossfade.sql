@@ -0,0 +1,7 @@
+insert into guild_config (
+	guild_id, crossfade_ms
+) values (
+	$1, $2
+) on conflict (guild_id) do
+	update set crossfade_ms = excluded.crossfade_ms
+;
@@ -0,0 +1,7 @@
+insert into guild_config (
+	guild_id, dj_role
+) values (
+	$1, $2
+) on conflict (guild_id) do
+	update set dj_role = excluded.dj_role
+;
@@ -0,0 +1,7 @@
+insert into guild_config (
+	guild_id, duck_level
+) values (
+	$1, $2
+) on conflict (guild_id) do
+	update set duck_level = excluded.duck_level
+;
@@ -0,0 +1,7 @@
+insert into guild_config (
+	guild_id, fair_queue
+) values (
+	$1, $2
+) on conflict (guild_id) do
+	update set fair_queue = excluded.fair_queue
+;
@@ -0,0 +1,12 @@
+insert into guild_config (
+	guild_id, filter_bass_boost, filter_rate, filter_speed, filter_pitch, filter_normalize
+) values (
+	$1, $2, $3, $4, $5, $6
+) on conflict (guild_id) do
+	update set
+		filter_bass_boost = excluded.filter_bass_boost,
+		filter_rate = excluded.filter_rate,
+		filter_speed = excluded.filter_speed,
+		filter_pitch = excluded.filter_pitch,
+		filter_normalize = excluded.filter_normalize
+;
@@ -0,0 +1,9 @@
+insert into guild_config (
+	guild_id, user_track_limit, user_duration_limit_ms
+) values (
+	$1, $2, $3
+) on conflict (guild_id) do
+	update set
+		user_track_limit = excluded.user_track_limit,
+		user_duration_limit_ms = excluded.user_duration_limit_ms
+;
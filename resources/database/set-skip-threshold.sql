@@ -0,0 +1,7 @@
+insert into guild_config (
+	guild_id, skip_threshold
+) values (
+	$1, $2
+) on conflict (guild_id) do
+	update set skip_threshold = excluded.skip_threshold
+;
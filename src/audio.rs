@@ -1,41 +1,41 @@
-//! Fetch Spotify, YouTube, online audio files, and locally stored clips for
-//! playing.
+//! Fetch Spotify, YouTube, SoundCloud, Bandcamp, online audio files, and
+//! locally stored clips for playing.
 //!
 //! Ultimately all audio sources that are streamed are from youtube, but the
 //! metadata from Spotify is parsed in order to create a search on youtube
 //! which will likely return the source being searched for.
 //!
+//! Each kind of source is resolved by a [`crate::resolver::SourceResolver`].
+//!
 //! Clip searches are done using levenshtein distance in order to fuzzily
 //! match making it easier to use without knowing exact clip names.
 
-use futures::TryStreamExt;
-
 use itertools::Itertools;
 
 use songbird::Call;
 use songbird::error::TrackResult;
-use songbird::input::{AudioStream, AudioStreamError, AuxMetadata, Compose};
-use songbird::tracks::PlayMode;
+use songbird::input::{AudioStream, AudioStreamError, AuxMetadata, Compose, File};
+use songbird::tracks::{LoopState, PlayMode, TrackQueue};
 
 use symphonia::core::io::MediaSource;
 
 use tracing::{debug, error, info, warn};
 
-use rand::seq::IteratorRandom;
-
 use regex::Regex;
 
 use reqwest::Url;
 
 use serenity::async_trait;
+use serenity::model::id::GuildId;
 
 use songbird::input::Input;
 
 use thiserror::Error;
 
+use uuid::Uuid;
+
 use walkdir::WalkDir;
 
-use std::borrow::Cow;
 use std::cmp::min;
 use std::collections::HashSet;
 use std::ffi::{OsStr, OsString};
@@ -43,25 +43,29 @@ use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
 use crate::RESOURCE_PATH;
-use crate::data::{ArcRw, Keys};
+use crate::audio_cache::AUDIO_CACHE;
+use crate::data::{ArcRw, Keys, TrackData};
 use crate::parser::Selection;
+use crate::persistence::{QueuedTrack, Storage};
+use crate::prefetch::ReadAhead;
+use crate::resolver::{self, RESOLVERS, SourceQuery};
 use crate::util::*;
-use crate::youtube::{self, compose_yt_search};
-use crate::{REQWEST_CLIENT, spotify};
+use crate::youtube;
+use crate::ytdl;
 
 /// Path to shared directory for clips.
 pub static CLIP_PATH: LazyLock<PathBuf> = LazyLock::new(|| RESOURCE_PATH.join("clips/"));
 
-/// Regular expression which matches valid http or https urls.
-static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new("^https?://").unwrap());
+/// Path to the directories of clips for single guilds, see
+/// [`guild_clip_path`].
+pub static GUILD_CLIP_PATH: LazyLock<PathBuf> =
+	LazyLock::new(|| RESOURCE_PATH.join("guild_clips/"));
 
-/// Regular expression which matches the host portion of a url if the host is youtube.
-static YOUTUBE_HOST: LazyLock<Regex> =
-	LazyLock::new(|| Regex::new("^([^.]*\\.)?(youtube\\.com|youtu.be)").unwrap());
+/// Extensions of files which are found as clips, in the order they are tried.
+pub const CLIP_EXTENSIONS: [&str; 2] = ["mp3", "wav"];
 
-/// Regular expression which matches the host portion of a url if the host is spotify.
-static SPOTIFY_HOST: LazyLock<Regex> =
-	LazyLock::new(|| Regex::new("^open\\.spotify\\.com").unwrap());
+/// Regular expression which matches valid http or https urls.
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new("^https?://").unwrap());
 
 /// Enum for the two styles of audio source.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -79,6 +83,8 @@ pub enum SearchSource {
 	Youtube,
 	/// Search through local clip files.
 	Local,
+	/// Search through the music library, then on youtube if nothing matched.
+	Library,
 }
 
 impl std::str::FromStr for PlayStyle {
@@ -113,10 +119,36 @@ impl<C: Compose> Compose for ComposeWithMetadata<C> {
 		self.compose.create()
 	}
 
+	/// Streams from the web are read ahead, see [`ReadAhead`], and played from
+	/// the [`AUDIO_CACHE`] once they have been streamed, if it is enabled.
 	async fn create_async(
 		&mut self,
 	) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
-		self.compose.create_async().await
+		let web_url = self
+			.aux_metadata
+			.source_url
+			.as_deref()
+			.filter(|url| Url::parse(url).is_ok_and(|url| url.scheme().starts_with("http")));
+
+		let Some(url) = web_url else {
+			return self.compose.create_async().await;
+		};
+
+		let cache = AUDIO_CACHE.as_ref();
+
+		if let Some(path) = cache.and_then(|cache| cache.get(url)) {
+			return File::new(path).create_async().await;
+		}
+
+		let mut stream = self.compose.create_async().await?;
+
+		if let Some(cache) = cache {
+			stream.input = cache.write_through(url, stream.input);
+		}
+
+		stream.input = Box::new(ReadAhead::new(stream.input));
+
+		Ok(stream)
 	}
 
 	fn should_create_async(&self) -> bool {
@@ -150,6 +182,9 @@ pub enum AudioError {
 	#[error("error while fetching from youtube api: {0}")]
 	YoutubeApi(youtube::Error),
 
+	#[error("error while reading with yt-dlp: {0}")]
+	Ytdl(ytdl::Error),
+
 	/// Error indicating the context does not allow playlists.
 	#[error("playlists are not allowed in this context")]
 	PlaylistNotAllowed,
@@ -186,6 +221,9 @@ pub struct SourceInfo {
 	/// duration of all sources added up
 	pub duration: Option<std::time::Duration>,
 
+	/// Path of the local clip, if the source is one
+	pub clip: Option<PathBuf>,
+
 	/// Iterator for the inputs
 	pub inputs: Box<dyn Iterator<Item = Input> + Send + Sync>,
 }
@@ -194,345 +232,31 @@ pub struct SourceInfo {
 /// Information about the audio inputs is included with an iterator over the
 /// inputs.
 ///
-/// If `loc` is a URL, as matched by the [`URL`] regular expression, it will try
-/// to match either a youtube or spotify URL, based on the host names in the
-/// [`YOUTUBE_HOST`] and [`SPOTIFY_HOST`] regular expressions. Any other URL
-/// will be streamed as an audio file, and if that fails, it returns an
-/// [`AudioError::UnsupportedUrl`] error.
-///
-/// If `loc` is not a URL then it will instead do a search on youtube and grab
-/// the first match.
+/// `loc` is resolved by the first of the [`RESOLVERS`] which recognises it,
+/// whether it is a url for a site like youtube or spotify, any other audio
+/// file url, or a search in `search_location`. A url no resolver recognises
+/// returns an [`AudioError::UnsupportedUrl`] error.
 ///
 /// Certain contexts may wish to exclude playlists, so `allow_playlist` can be
 /// set to false return an [`AudioError::PlaylistNotAllowed`] instead.
+///
+/// Clips are found in the clips of `guild_id` before the shared clips.
 pub async fn get_inputs(
 	keys: ArcRw<Keys>,
+	guild_id: Option<GuildId>,
 	loc: &str,
 	allow_playlist: bool,
 	search_location: Option<SearchSource>,
 ) -> Result<SourceInfo, AudioError> {
-	if URL.is_match(loc) {
-		let url = Url::parse(loc).map_err(|_| AudioError::UnsupportedUrl)?;
-		let host = url.host_str().ok_or(AudioError::UnsupportedUrl)?;
-
-		if YOUTUBE_HOST.is_match(host) {
-			let path = url.path();
-
-			let youtube_api = keys
-				.read()
-				.await
-				.youtube
-				.clone()
-				.ok_or(AudioError::YoutubeApiKey)?;
-
-			if path == "/playlist" {
-				// youtube playlist
-				if !allow_playlist {
-					return Err(AudioError::PlaylistNotAllowed);
-				}
-
-				let id = url
-					.query_pairs()
-					.filter(|(key, _)| key == "list")
-					.map(|(_, value)| value)
-					.next()
-					.ok_or_else(|| {
-						debug!("Missing \"list\" in query parameters: {}", url);
-						AudioError::UnsupportedUrl
-					})?;
-
-				let playlist = youtube::playlist(&youtube_api, &id)
-					.await
-					.inspect_err(|e| error!("Error in youtube data api for playlists: {:?}", e))
-					.map_err(AudioError::YoutubeApi)?
-					.ok_or_else(|| {
-						error!("No playlist found");
-						AudioError::NotFound
-					})?;
-
-				let videos = youtube::playlist_items(&youtube_api, &id)
-					.map_ok(|items| {
-						youtube::videos(
-							&youtube_api,
-							items.into_iter().map(|item| item.content_details.video_id),
-						)
-					})
-					// concurrently hit videos api after paged playlist items
-					// no gains were seen after 3 concurrent, 4 is just in case
-					.try_buffered(4)
-					.try_concat()
-					.await
-					.inspect_err(|e| {
-						error!("Error in youtube data api for playlist items: {:?}", e)
-					})
-					.map_err(AudioError::YoutubeApi)?;
-
-				let count = videos.len();
-
-				Ok(SourceInfo {
-					title: Some(playlist.snippet.title),
-					url: Some(loc.to_string()),
-					count,
-					duration: videos
-						.iter()
-						.map(|video| video.content_details.duration.to_std())
-						.try_fold(std::time::Duration::ZERO, |acc, opt| opt.map(|x| acc + x)),
-					inputs: Box::new(
-						videos
-							.into_iter()
-							.map(|item| Input::from(ComposeWithMetadata::from(item))),
-					),
-				})
-			} else {
-				// single youtube video
-				let id = if path == "/watch" {
-					url.query_pairs()
-						.filter(|(key, _)| key == "v")
-						.map(|(_, value)| value)
-						.next()
-						.ok_or_else(|| {
-							debug!("Missing \"v\" in query parameters: {}", url);
-							AudioError::UnsupportedUrl
-						})?
-				} else {
-					Cow::Borrowed(&path[1..])
-				};
-
-				let video = youtube::video(&youtube_api, &id)
-					.await
-					.inspect_err(|e| error!("Youtube video error: {:?}", e))
-					.map_err(AudioError::YoutubeApi)?
-					.ok_or_else(|| {
-						error!("No video found with id {:?}", id);
-						AudioError::NotFound
-					})?;
-
-				let duration = video.content_details.duration.to_std();
-				let title = video.snippet.title.clone();
-
-				let compose = ComposeWithMetadata::from(video);
-
-				Ok(SourceInfo {
-					title: Some(title),
-					url: Some(loc.to_string()),
-					count: 1,
-					duration,
-					inputs: Box::new(std::iter::once_with(|| compose.into())),
-				})
-			}
-		} else if SPOTIFY_HOST.is_match(host) {
-			let mut path_segments = url.path_segments().ok_or(AudioError::UnsupportedUrl)?;
+	let query = SourceQuery {
+		keys,
+		guild_id,
+		loc,
+		allow_playlist,
+		search_location,
+	};
 
-			let token = keys
-				.write()
-				.await
-				.spotify
-				.as_mut()
-				.ok_or(AudioError::Spotify)?
-				.get_token()
-				.await
-				.map_err(|_| AudioError::Spotify)?
-				.access_token
-				.clone();
-
-			match path_segments.next().ok_or(AudioError::UnsupportedUrl)? {
-				"track" => {
-					// spotify single song
-					let track_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
-
-					let track = spotify::track(&token, track_id)
-						.await
-						.inspect_err(|e| error!("Error reading spotify track: {:?}", e))
-						.map_err(|_| AudioError::Spotify)?;
-
-					let compose = ComposeWithMetadata::from(&track);
-
-					Ok(SourceInfo {
-						title: Some(track.name),
-						url: Some(loc.to_string()),
-						count: 1,
-						duration: None,
-						inputs: Box::new(std::iter::once_with(|| compose.into())),
-					})
-				}
-				"playlist" => {
-					// spotify playlist
-					if !allow_playlist {
-						return Err(AudioError::PlaylistNotAllowed);
-					}
-
-					let playlist_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
-
-					// First try the api
-					let playlist = match spotify::playlist(&token, playlist_id).await {
-						Ok(v) => v,
-						Err(e) => {
-							// Fall back to parsing an embed in case spotify does not display that
-							// playlist on the api
-							spotify::scrape::playlist(playlist_id)
-								.await
-								.inspect_err(|e_scrape| {
-									error!("Error reading spotify playlist: {:?}", e);
-									error!("Error scraping spotify playlist: {:?}", e_scrape);
-								})
-								.map_err(|_| AudioError::Spotify)?
-						}
-					};
-
-					let items = spotify::into_stream(&token, playlist.tracks)
-						.map_ok(|playlist_tracks| playlist_tracks.items)
-						.try_concat()
-						.await
-						.inspect_err(|e| {
-							error!("Error in spotify data api for playlist items: {:?}", e)
-						})
-						.map_err(|_| AudioError::Spotify)?;
-
-					Ok(SourceInfo {
-						title: Some(playlist.name),
-						url: Some(loc.to_string()),
-						count: items.len(),
-						duration: None,
-						inputs: Box::new(
-							items
-								.into_iter()
-								.map(|t| Input::from(ComposeWithMetadata::from(&t.track))),
-						),
-					})
-				}
-				"album" => {
-					// spotify playlist
-					if !allow_playlist {
-						return Err(AudioError::PlaylistNotAllowed);
-					}
-
-					let album_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
-
-					let album = spotify::album(&token, album_id)
-						.await
-						.inspect_err(|e| error!("Error reading spotify album: {:?}", e))
-						.map_err(|_| AudioError::Spotify)?;
-
-					let items = spotify::into_stream(&token, album.tracks)
-						.map_ok(|album_tracks| album_tracks.items)
-						.try_concat()
-						.await
-						.inspect_err(|e| {
-							error!("Error in spotify data api for album items: {:?}", e)
-						})
-						.map_err(|_| AudioError::Spotify)?;
-
-					Ok(SourceInfo {
-						title: Some(album.name),
-						url: Some(loc.to_string()),
-						count: items.len(),
-						duration: None,
-						inputs: Box::new(
-							items
-								.into_iter()
-								.map(|track| Input::from(ComposeWithMetadata::from(&track))),
-						),
-					})
-				}
-				_ => Err(AudioError::UnsupportedUrl),
-			}
-		} else {
-			// arbitrary audio file url
-			let title = url
-				.path_segments()
-				.map(|mut p| p.nth_back(0).unwrap().to_string());
-
-			let mut compose = ComposeWithMetadata::new(
-				songbird::input::HttpRequest::new(REQWEST_CLIENT.clone(), loc.to_string()),
-				AuxMetadata {
-					title: title.clone(),
-					source_url: Some(loc.to_string()),
-					..Default::default()
-				},
-			);
-
-			let aux_metadata = compose
-				.aux_metadata()
-				.await
-				.inspect_err(|e| error!("Error getting metadata: {:?}", e))?;
-
-			Ok(SourceInfo {
-				title,
-				url: Some(loc.to_string()),
-				count: 1,
-				duration: aux_metadata.duration,
-				inputs: Box::new(std::iter::once_with(|| compose.into())),
-			})
-		}
-	} else {
-		// arbitrary search term
-		match search_location {
-			Some(SearchSource::Youtube) => {
-				let loc_string = loc.to_string();
-
-				let mut compose = compose_yt_search(loc_string);
-
-				let aux_metadata = compose
-					.aux_metadata()
-					.await
-					.inspect_err(|e| error!("Error getting metadata: {:?}", e))?;
-
-				Ok(SourceInfo {
-					title: aux_metadata.title,
-					url: aux_metadata.source_url,
-					count: 1,
-					duration: aux_metadata.duration,
-					inputs: Box::new(std::iter::once_with(|| compose.into())),
-				})
-			}
-			Some(SearchSource::Local) => {
-				let clips = search_clips(loc.as_ref());
-
-				let clip_name = clips
-					.into_iter()
-					.choose(&mut rand::rng())
-					.ok_or(AudioError::NotFound)?;
-
-				let clip = get_clip(&clip_name).ok_or(AudioError::NotFound)?;
-
-				let aux_metadata = AuxMetadata {
-					title: Some(clip_name.to_string_lossy().into_owned()),
-					..Default::default()
-				};
-
-				let compose =
-					ComposeWithMetadata::new(songbird::input::File::new(clip), aux_metadata);
-
-				Ok(SourceInfo {
-					title: Some(clip_name.to_string_lossy().into_owned()),
-					url: None,
-					count: 1,
-					duration: None,
-					inputs: Box::new(std::iter::once_with(|| compose.into())),
-				})
-			}
-			// try to get an exact match on the clip, else fail
-			None => {
-				let clip = get_clip(loc.as_ref()).ok_or(AudioError::NotFound)?;
-
-				let aux_metadata = AuxMetadata {
-					title: Some(loc.to_owned()),
-					..Default::default()
-				};
-
-				let compose =
-					ComposeWithMetadata::new(songbird::input::File::new(clip), aux_metadata);
-
-				Ok(SourceInfo {
-					title: Some(loc.to_owned()),
-					url: None,
-					count: 1,
-					duration: None,
-					inputs: Box::new(std::iter::once_with(|| compose.into())),
-				})
-			}
-		}
-	}
+	resolver::resolve(&RESOLVERS, &query).await
 }
 
 #[tracing::instrument(level = "info", ret, skip(call))]
@@ -615,6 +339,68 @@ pub async fn move_queue(
 	Ok(moved)
 }
 
+/// Take a snapshot of the tracks in `queue`, in order, so that they can be
+/// saved with [`Storage::set_queue`].
+///
+/// Tracks with a uuid in `exclude` are skipped, which is useful for tracks
+/// that have ended but not yet been removed from the queue. Tracks without a
+/// source url are also skipped, as they cannot be fetched again.
+pub async fn snapshot_queue(queue: &TrackQueue, exclude: &[Uuid]) -> Vec<QueuedTrack> {
+	let mut snapshot = Vec::new();
+
+	for handle in queue.current_queue() {
+		if exclude.contains(&handle.uuid()) {
+			continue;
+		}
+
+		let data = handle.data::<TrackData>();
+
+		let Some((source_url, title)) = data
+			.metadata
+			.as_ref()
+			.and_then(|meta| Some((meta.source_url.clone()?, meta.title.clone())))
+		else {
+			continue;
+		};
+
+		let state = handle.get_info().await.ok();
+
+		snapshot.push(QueuedTrack {
+			source_url,
+			title,
+			requester: data.requester,
+			position: state.as_ref().map(|s| s.position).unwrap_or_default(),
+			loops: match state.map(|s| s.loops) {
+				Some(LoopState::Infinite) => None,
+				Some(LoopState::Finite(n)) => Some(n),
+				None => Some(0),
+			},
+		});
+	}
+
+	snapshot
+}
+
+/// Save a snapshot of `queue` for the guild using [`snapshot_queue`], so it
+/// can be restored later.
+///
+/// Failures are only logged, as losing the saved queue should not interrupt
+/// playing audio.
+pub async fn persist_queue(
+	storage: &(dyn Storage + Send + Sync),
+	guild_id: GuildId,
+	queue: &TrackQueue,
+	exclude: &[Uuid],
+) {
+	let snapshot = snapshot_queue(queue, exclude).await;
+
+	debug!("Saving queue of {} tracks for {}", snapshot.len(), guild_id);
+
+	if let Err(e) = storage.set_queue(guild_id, &snapshot).await {
+		error!("Unable to save queue: {:?}", e);
+	}
+}
+
 /// Log a warning if any clips have the same file stem.
 ///
 /// Different extensions are ignored, the comparison is done using
@@ -648,7 +434,7 @@ pub fn warn_exact_name_finds_different_clip() {
 		.filter(|f| {
 			let path = f.path();
 
-			match &search_clips(path.file_stem().unwrap())[..] {
+			match &search_clips(None, path.file_stem().unwrap())[..] {
 				[p] => p != &path.strip_prefix(&*CLIP_PATH).unwrap().with_extension(""),
 				_ => true,
 			}
@@ -661,7 +447,41 @@ pub fn warn_exact_name_finds_different_clip() {
 		});
 }
 
-/// Try to find a clip based on the search `loc`.
+/// Directory of the clips only available in `guild_id`, which are found
+/// before the shared clips in [`CLIP_PATH`].
+pub fn guild_clip_path(guild_id: GuildId) -> PathBuf {
+	GUILD_CLIP_PATH.join(guild_id.to_string())
+}
+
+/// Directories clips are found in for `guild_id`, in the order they are
+/// searched. Clips of the guild hide shared clips with the same name.
+pub fn clip_paths(guild_id: Option<GuildId>) -> Vec<PathBuf> {
+	guild_id
+		.map(guild_clip_path)
+		.filter(|path| path.is_dir())
+		.into_iter()
+		.chain(std::iter::once(CLIP_PATH.clone()))
+		.collect()
+}
+
+/// Every clip file in `dirs`, as the path relative to its clip directory and
+/// the full path.
+fn clip_files(dirs: Vec<PathBuf>) -> impl Iterator<Item = (PathBuf, PathBuf)> {
+	dirs.into_iter().flat_map(|dir| {
+		WalkDir::new(&dir)
+			.into_iter()
+			.filter_map(|f| f.inspect_err(|e| error!("{:?}", e)).ok())
+			.filter(|f| f.file_type().is_file())
+			.map(move |f| {
+				(
+					f.path().strip_prefix(&dir).unwrap().to_owned(),
+					f.into_path(),
+				)
+			})
+	})
+}
+
+/// Try to find a clip available in `guild_id` based on the search `loc`.
 ///
 /// If the clip matches a URL, it is just returned.
 ///
@@ -676,83 +496,108 @@ pub fn warn_exact_name_finds_different_clip() {
 /// bytes of the search have to be found in the clip, or else it is possible
 /// for no clips to be returned.
 #[tracing::instrument(level = "info", ret)]
-pub fn search_clips(loc: &OsStr) -> Vec<OsString> {
+pub fn search_clips(guild_id: Option<GuildId>, loc: &OsStr) -> Vec<OsString> {
+	search_clips_in(clip_paths(guild_id), loc)
+}
+
+/// [`search_clips`] in the clip directories `dirs`, in the order they are
+/// searched.
+fn search_clips_in(dirs: Vec<PathBuf>, loc: &OsStr) -> Vec<OsString> {
 	if URL.is_match(&loc.to_string_lossy()) {
 		return vec![loc.to_owned()];
 	}
 
 	// short circuit exact match
-	if let Some(path) = get_clip(loc) {
-		return vec![path];
+	if get_clip_in(&dirs, loc).is_some() {
+		return vec![loc.to_owned()];
 	}
 
-	WalkDir::new(&*CLIP_PATH)
-		.into_iter()
-		.filter_map(|f| f.inspect_err(|e| error!("{:?}", e)).ok())
-		.filter(|f| f.file_type().is_file())
+	clip_files(dirs)
 		// calculate the levenshtein distance of each file
 		// break ties by prioritizing longest length of match
 		// followed by shortest length of clip path
-		.min_set_by_key(|f| {
-			let path = f.path().to_string_lossy();
-
-			let leven = triple_accel::levenshtein::levenshtein_search(
+		.min_set_by_key(|(relative, _)| {
+			levenshtein_rank(
 				loc.to_string_lossy().as_bytes(),
-				path.as_bytes(),
+				relative.to_string_lossy().as_bytes(),
 			)
-			.next();
-
-			match leven {
-				Some(leven) => (leven.k, -((leven.end - leven.start) as isize), path.len()),
-				None => (u32::MAX, isize::MAX, usize::MAX),
-			}
 		})
 		.into_iter()
-		.map(|f| {
-			f.path()
-				.strip_prefix(&*CLIP_PATH)
-				.unwrap()
-				.with_extension("")
-				.into()
-		})
+		.map(|(relative, _)| relative.with_extension("").into())
+		// a clip of the guild and a shared clip may have the same name
+		.unique()
 		.collect_vec()
 }
 
-pub fn get_clip(loc: &OsStr) -> Option<OsString> {
+/// Rank how well `search` matches `target`, where lower is better.
+///
+/// Ranks by the lowest levenshtein distance, breaking ties by the longest
+/// match, followed by the shortest `target`.
+pub fn levenshtein_rank(search: &[u8], target: &[u8]) -> (u32, isize, usize) {
+	let leven = triple_accel::levenshtein::levenshtein_search(search, target).next();
+
+	match leven {
+		Some(leven) => (leven.k, -((leven.end - leven.start) as isize), target.len()),
+		None => (u32::MAX, isize::MAX, usize::MAX),
+	}
+}
+
+/// Check if `loc` is a http or https url, as matched by [`URL`].
+pub fn is_url(loc: &str) -> bool {
+	URL.is_match(loc)
+}
+
+/// Find the file of the clip named exactly `loc` in `guild_id`, checking the
+/// guild's clips before the shared clips.
+pub fn get_clip(guild_id: Option<GuildId>, loc: &OsStr) -> Option<OsString> {
+	get_clip_in(&clip_paths(guild_id), loc)
+}
+
+/// [`get_clip`] in the clip directories `dirs`, in the order they are checked.
+fn get_clip_in(dirs: &[PathBuf], loc: &OsStr) -> Option<OsString> {
 	if URL.is_match(&loc.to_string_lossy()) {
 		return Some(loc.to_os_string());
 	}
 
 	let mut play_path = Path::new(loc).to_path_buf();
 
-	for ext in &["mp3", "wav"] {
-		play_path.set_extension(ext);
+	for dir in dirs {
+		for ext in CLIP_EXTENSIONS {
+			play_path.set_extension(ext);
 
-		if valid_clip(&play_path) {
-			return Some(CLIP_PATH.join(play_path).into());
+			if valid_clip(dir, &play_path) {
+				return Some(dir.join(&play_path).into());
+			}
 		}
 	}
 
 	None
 }
 
-pub fn clip_iter() -> impl Iterator<Item = OsString> {
-	WalkDir::new(&*CLIP_PATH)
-		.into_iter()
-		.filter_map(|f| f.inspect_err(|e| error!("{:?}", e)).ok())
-		.filter(|f| f.file_type().is_file())
-		.map(|f| f.path().strip_prefix(&*CLIP_PATH).unwrap().into())
+/// Names of every clip available in `guild_id`, relative to their clip
+/// directory and with their extension.
+pub fn clip_iter(guild_id: Option<GuildId>) -> impl Iterator<Item = OsString> {
+	clip_iter_in(clip_paths(guild_id))
 }
 
-/// Verify that the clip exists within the clip path directory.
-pub fn valid_clip(path: &Path) -> bool {
-	sandboxed_join(&CLIP_PATH, path).is_some()
+/// [`clip_iter`] in the clip directories `dirs`.
+fn clip_iter_in(dirs: Vec<PathBuf>) -> impl Iterator<Item = OsString> {
+	clip_files(dirs)
+		.map(|(relative, _)| relative.into_os_string())
+		.unique()
+}
+
+/// Verify that the clip exists within the clip directory `dir`.
+pub fn valid_clip(dir: &Path, path: &Path) -> bool {
+	sandboxed_join(dir, path).is_some()
 }
 
 #[cfg(test)]
 mod tests {
 	use super::*;
 
+	use crate::util::mock::{MockServer, mock_keys};
+
 	const URLS: &[&str] = &[
 		// youtube single video
 		"https://www.youtube.com/watch?v=k2mFvwDTTt0",
@@ -764,6 +609,16 @@ mod tests {
 		"https://open.spotify.com/album/0G2RxSCixG5Nl6jpjwiw2g",
 		// spotify playlist
 		"https://open.spotify.com/playlist/2O18dCV9uoGTyxN5HLJkTo",
+		// spotify artist
+		"https://open.spotify.com/artist/0gxyHStUsqpMadRV0Di1Qt",
+		// soundcloud single track
+		"https://soundcloud.com/forss/flickermood",
+		// soundcloud set
+		"https://soundcloud.com/forss/sets/soulhack",
+		// bandcamp single track
+		"https://macklemore.bandcamp.com/track/downtown",
+		// bandcamp album
+		"https://macklemore.bandcamp.com/album/the-heist",
 	];
 
 	/// test to make sure inputs equal count
@@ -775,9 +630,127 @@ mod tests {
 		let keys: ArcRw<_> = std::sync::Arc::new(read_toml::<Keys, _>("keys.toml").unwrap().into());
 
 		for url in URLS {
-			let sources = get_inputs(keys.clone(), url, true, None).await.unwrap();
+			let sources = get_inputs(keys.clone(), None, url, true, None)
+				.await
+				.unwrap();
 
 			assert_eq!(sources.count, sources.inputs.count())
 		}
 	}
+
+	/// same as [`play_sources_count`], but against fixtures rather than the
+	/// real apis
+	#[tokio::test]
+	async fn mock_sources_count() {
+		let base_url = MockServer::new()
+			.fixture("/youtube/v3/playlists", "youtube/playlists.json")
+			.fixture_with_query(
+				"/youtube/v3/playlistItems",
+				"pageToken=mockpage2",
+				"youtube/playlist-items-2.json",
+			)
+			.fixture("/youtube/v3/playlistItems", "youtube/playlist-items-1.json")
+			.fixture("/youtube/v3/videos", "youtube/videos.json")
+			.fixture("/api/token", "spotify/token.json")
+			.fixture("/v1/tracks/mocktrack01", "spotify/track.json")
+			.fixture(
+				"/v1/artists/mockartist/top-tracks",
+				"spotify/artist-top-tracks.json",
+			)
+			.fixture("/v1/shows/mockshow", "spotify/show.json")
+			.fixture("/v1/shows/mockshow/episodes", "spotify/show-episodes.json")
+			.fixture("/v1/episodes/mockepisode01", "spotify/episode.json")
+			// the api does not show every playlist, which are scraped instead
+			.error("/v1/playlists/mockplaylist", 404, "spotify/not-found.json")
+			.fixture(
+				"/embed/playlist/mockplaylist",
+				"spotify/embed-playlist.html",
+			)
+			.start()
+			.await;
+
+		let keys = mock_keys(&base_url);
+
+		let sources = [
+			(
+				"https://www.youtube.com/watch?v=mockvideo01",
+				"Mock Video 1",
+				1,
+			),
+			("https://youtu.be/mockvideo01", "Mock Video 1", 1),
+			(
+				"https://www.youtube.com/playlist?list=PLmockplaylist",
+				"Mock Playlist",
+				3,
+			),
+			(
+				"https://open.spotify.com/track/mocktrack01",
+				"Mock Track 1",
+				1,
+			),
+			(
+				"https://open.spotify.com/playlist/mockplaylist",
+				"Mock Mix",
+				2,
+			),
+			(
+				"https://open.spotify.com/artist/mockartist",
+				"Top tracks by Mock Artist",
+				2,
+			),
+			("https://open.spotify.com/show/mockshow", "Mock Show", 3),
+			(
+				"https://open.spotify.com/episode/mockepisode01",
+				"Mock Episode 1",
+				1,
+			),
+		];
+
+		for (url, title, count) in sources {
+			let sources = get_inputs(keys.clone(), None, url, true, None)
+				.await
+				.unwrap();
+
+			assert_eq!(sources.title.as_deref(), Some(title), "{}", url);
+			assert_eq!(sources.count, count, "{}", url);
+			assert_eq!(sources.inputs.count(), count, "{}", url);
+		}
+	}
+
+	#[test]
+	fn guild_clips_first() {
+		let shared = clip_iter(None)
+			.map(PathBuf::from)
+			.find(|path| path.extension().is_some_and(|e| e == "mp3"))
+			.expect("No mp3 clips found");
+
+		// kept out of the real guild clips, so nothing is left behind there
+		let dir = std::env::temp_dir().join(format!("utf-nate-guild-clips-{}", Uuid::new_v4()));
+		let dirs = vec![dir.clone(), CLIP_PATH.clone()];
+		let guild_clip = dir.join(&shared);
+
+		std::fs::create_dir_all(guild_clip.parent().unwrap()).unwrap();
+		std::fs::copy(CLIP_PATH.join(&shared), &guild_clip).unwrap();
+		std::fs::copy(CLIP_PATH.join(&shared), dir.join("guild only.mp3")).unwrap();
+
+		let name = shared.with_extension("");
+
+		let guild_found = get_clip_in(&dirs, name.as_os_str());
+		let shared_found = get_clip(None, name.as_os_str());
+		let guild_only = search_clips_in(dirs.clone(), OsStr::new("guild only"));
+		let guild_only_shared = get_clip(None, OsStr::new("guild only"));
+		let guild_count = clip_iter_in(dirs).count();
+
+		// clean up before asserting, so nothing is left behind on failure
+		std::fs::remove_dir_all(&dir).unwrap();
+
+		assert_eq!(guild_found, Some(guild_clip.into_os_string()));
+		assert_eq!(shared_found, Some(CLIP_PATH.join(&shared).into_os_string()));
+
+		assert_eq!(guild_only, ["guild only"]);
+		assert!(guild_only_shared.is_none());
+
+		// clips in both are only listed once
+		assert_eq!(guild_count, clip_iter(None).count() + 1);
+	}
 }
@@ -0,0 +1,569 @@
+//! Keep audio streamed from the web on disk, so that playing the same track
+//! again reads it from disk instead of streaming it again.
+//!
+//! Audio is written to the cache as it is first streamed, see
+//! [`AudioCache::write_through`], and only kept if it is streamed to the end.
+//! Cached tracks are keyed by their source url, and the least recently played
+//! are removed once the cache grows past its size. The cache is only used if
+//! [`crate::configuration::Config::audio_cache`] is set.
+
+use symphonia::core::io::MediaSource;
+
+use tracing::{debug, error, info};
+
+use uuid::Uuid;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::CONFIG;
+
+/// Extension of files still being written to the cache.
+const PART_EXTENSION: &str = "part";
+
+/// Cache of streamed audio, if one is configured.
+pub static AUDIO_CACHE: LazyLock<Option<Arc<AudioCache>>> = LazyLock::new(|| {
+	let config = CONFIG.audio_cache.as_ref()?;
+
+	AudioCache::open(config.path.clone(), config.size * 1024 * 1024)
+		.inspect(|_| info!("Opened audio cache in {:?}", config.path))
+		.inspect_err(|e| error!("Unable to open audio cache: {:?}", e))
+		.ok()
+		.map(Arc::new)
+});
+
+/// Cached track, named by the key of its source url.
+#[derive(Debug)]
+struct Entry {
+	len: u64,
+
+	/// When the track was cached or last played, to remove the least recently
+	/// played first.
+	used: SystemTime,
+}
+
+#[derive(Debug, Default)]
+struct Index {
+	entries: HashMap<String, Entry>,
+
+	/// Bytes of all entries together.
+	len: u64,
+
+	hits: u64,
+	misses: u64,
+}
+
+/// Audio files on disk keyed by source url, which are kept under a size.
+#[derive(Debug)]
+pub struct AudioCache {
+	dir: PathBuf,
+
+	/// Most bytes kept in the cache.
+	capacity: u64,
+
+	index: Mutex<Index>,
+}
+
+/// Statistics of an [`AudioCache`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AudioCacheStats {
+	pub hits: u64,
+	pub misses: u64,
+	pub entries: usize,
+	pub len: u64,
+	pub capacity: u64,
+}
+
+impl fmt::Display for AudioCacheStats {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		const MIB: f64 = 1024.0 * 1024.0;
+
+		let total = self.hits + self.misses;
+
+		write!(f, "Audio: {} hits, {} misses", self.hits, self.misses)?;
+
+		if total > 0 {
+			write!(f, " ({:.0}% hit)", self.hits as f64 / total as f64 * 100.0)?;
+		}
+
+		write!(
+			f,
+			", {} cached using {:.1} of {:.0} MiB",
+			self.entries,
+			self.len as f64 / MIB,
+			self.capacity as f64 / MIB
+		)
+	}
+}
+
+/// What [`AudioCache::clear`] removed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cleared {
+	pub entries: usize,
+	pub len: u64,
+
+	/// Tracks which could not be removed, and are still cached.
+	pub failed: usize,
+}
+
+/// Name of the file the audio of `url` is cached in.
+fn key(url: &str) -> String {
+	format!("{:016x}", fxhash::hash64(url))
+}
+
+impl AudioCache {
+	/// Open the cache in `dir`, creating it if needed, and keep at most
+	/// `capacity` bytes in it.
+	///
+	/// Files left part written are removed, and the rest are cached tracks.
+	pub fn open(dir: PathBuf, capacity: u64) -> io::Result<Self> {
+		fs::create_dir_all(&dir)?;
+
+		let mut index = Index::default();
+
+		for file in fs::read_dir(&dir)? {
+			let file = file?;
+			let path = file.path();
+
+			if path.extension().is_some_and(|e| e == PART_EXTENSION) {
+				let _ = fs::remove_file(&path)
+					.inspect_err(|e| error!("Unable to remove {:?}: {:?}", path, e));
+				continue;
+			}
+
+			let metadata = file.metadata()?;
+
+			if !metadata.is_file() {
+				continue;
+			}
+
+			let Some(key) = path.file_name().and_then(|n| n.to_str()) else {
+				continue;
+			};
+
+			index.len += metadata.len();
+			index.entries.insert(
+				key.to_owned(),
+				Entry {
+					len: metadata.len(),
+					used: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+				},
+			);
+		}
+
+		let cache = Self {
+			dir,
+			capacity,
+			index: Mutex::new(index),
+		};
+
+		cache.evict(&mut cache.index());
+
+		Ok(cache)
+	}
+
+	fn index(&self) -> MutexGuard<'_, Index> {
+		self.index.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Path of the file cached for `url`, if it has been cached.
+	pub fn get(&self, url: &str) -> Option<PathBuf> {
+		let key = key(url);
+		let path = self.dir.join(&key);
+
+		let mut index = self.index();
+
+		let cached = match index.entries.get_mut(&key) {
+			Some(entry) if path.is_file() => {
+				entry.used = SystemTime::now();
+
+				// keep the order it was played in if the bot restarts
+				let _ = File::options()
+					.write(true)
+					.open(&path)
+					.and_then(|file| file.set_modified(entry.used))
+					.inspect_err(|e| error!("Unable to mark {:?} as played: {:?}", path, e));
+
+				true
+			}
+			Some(_) => {
+				// removed from outside the bot
+				if let Some(entry) = index.entries.remove(&key) {
+					index.len -= entry.len;
+				}
+
+				false
+			}
+			None => false,
+		};
+
+		match cached {
+			true => index.hits += 1,
+			false => index.misses += 1,
+		}
+
+		debug!(
+			"Audio cache {} for {}",
+			if cached { "hit" } else { "miss" },
+			url
+		);
+
+		cached.then_some(path)
+	}
+
+	/// Wrap `input`, streamed from `url`, so that it is written to the cache as
+	/// it is read. If the cache cannot be written to, `input` is returned as it
+	/// is.
+	pub fn write_through(
+		self: &Arc<Self>,
+		url: &str,
+		input: Box<dyn MediaSource>,
+	) -> Box<dyn MediaSource> {
+		let key = key(url);
+
+		// unique, in case the same url is streamed twice at once
+		let part = self
+			.dir
+			.join(format!("{}.{}.{}", key, Uuid::new_v4(), PART_EXTENSION));
+
+		match File::create(&part) {
+			Ok(file) => Box::new(WriteThrough {
+				inner: input,
+				cache: self.clone(),
+				key,
+				part,
+				file: Some(BufWriter::new(file)),
+				len: 0,
+			}),
+			Err(e) => {
+				error!("Unable to create {:?}: {:?}", part, e);
+				input
+			}
+		}
+	}
+
+	/// Add the fully written file `part` to the cache as `key`.
+	fn insert(&self, key: String, part: &Path, len: u64) -> io::Result<()> {
+		fs::rename(part, self.dir.join(&key))?;
+
+		let mut index = self.index();
+
+		let entry = Entry {
+			len,
+			used: SystemTime::now(),
+		};
+
+		index.len += len;
+
+		if let Some(old) = index.entries.insert(key, entry) {
+			index.len -= old.len;
+		}
+
+		self.evict(&mut index);
+
+		Ok(())
+	}
+
+	/// Remove the least recently played tracks until the cache fits in its
+	/// capacity.
+	fn evict(&self, index: &mut Index) {
+		while index.len > self.capacity {
+			let Some(key) = index
+				.entries
+				.iter()
+				.min_by_key(|(_, entry)| entry.used)
+				.map(|(key, _)| key.clone())
+			else {
+				break;
+			};
+
+			let path = self.dir.join(&key);
+
+			debug!("Evicting {:?} from audio cache", path);
+
+			let _ = fs::remove_file(&path)
+				.inspect_err(|e| error!("Unable to remove {:?}: {:?}", path, e));
+
+			if let Some(entry) = index.entries.remove(&key) {
+				index.len -= entry.len;
+			}
+		}
+	}
+
+	/// Remove every cached track. Tracks which can't be removed are logged and
+	/// kept, and the rest are still removed.
+	///
+	/// This blocks on removing files, so should not be called from async code.
+	pub fn clear(&self) -> Cleared {
+		let mut index = self.index();
+
+		let mut cleared = Cleared {
+			entries: 0,
+			len: 0,
+			failed: 0,
+		};
+
+		let keys = index.entries.keys().cloned().collect::<Vec<_>>();
+
+		for key in keys {
+			let path = self.dir.join(&key);
+
+			match fs::remove_file(&path) {
+				Err(e) if e.kind() != io::ErrorKind::NotFound => {
+					error!("Unable to remove {:?}: {:?}", path, e);
+					cleared.failed += 1;
+				}
+				_ => {
+					if let Some(entry) = index.entries.remove(&key) {
+						index.len -= entry.len;
+						cleared.entries += 1;
+						cleared.len += entry.len;
+					}
+				}
+			}
+		}
+
+		cleared
+	}
+
+	pub fn stats(&self) -> AudioCacheStats {
+		let index = self.index();
+
+		AudioCacheStats {
+			hits: index.hits,
+			misses: index.misses,
+			entries: index.entries.len(),
+			len: index.len,
+			capacity: self.capacity,
+		}
+	}
+}
+
+/// Wraps a [`MediaSource`] to write what is read from it to a file, which is
+/// added to an [`AudioCache`] once the source is read to the end.
+///
+/// Seeking anywhere but the end of what has been written, or dropping the
+/// source before the end, abandons the file.
+struct WriteThrough {
+	inner: Box<dyn MediaSource>,
+	cache: Arc<AudioCache>,
+	key: String,
+
+	/// File being written, until it is finished or abandoned.
+	part: PathBuf,
+	file: Option<BufWriter<File>>,
+
+	/// Bytes written to the file.
+	len: u64,
+}
+
+impl WriteThrough {
+	fn abandon(&mut self) {
+		if self.file.take().is_some() {
+			debug!("Abandoning {:?}", self.part);
+
+			let _ = fs::remove_file(&self.part)
+				.inspect_err(|e| error!("Unable to remove {:?}: {:?}", self.part, e));
+		}
+	}
+
+	fn finish(&mut self) {
+		let Some(file) = self.file.take() else {
+			return;
+		};
+
+		let result = file.into_inner().map_err(|e| e.into_error()).and_then(|_| {
+			self.cache
+				.insert(std::mem::take(&mut self.key), &self.part, self.len)
+		});
+
+		match result {
+			Ok(()) => debug!("Cached {} bytes from {:?}", self.len, self.part),
+			Err(e) => {
+				error!("Unable to cache {:?}: {:?}", self.part, e);
+				let _ = fs::remove_file(&self.part);
+			}
+		}
+	}
+}
+
+impl Read for WriteThrough {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let len = self.inner.read(buf)?;
+
+		if len == 0 {
+			self.finish();
+		} else if let Some(file) = &mut self.file {
+			match file.write_all(&buf[..len]) {
+				Ok(()) => self.len += len as u64,
+				Err(e) => {
+					error!("Unable to write {:?}: {:?}", self.part, e);
+					self.abandon();
+				}
+			}
+		}
+
+		Ok(len)
+	}
+}
+
+impl Seek for WriteThrough {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let position = self.inner.seek(pos)?;
+
+		if position != self.len {
+			self.abandon();
+		}
+
+		Ok(position)
+	}
+}
+
+impl MediaSource for WriteThrough {
+	fn is_seekable(&self) -> bool {
+		self.inner.is_seekable()
+	}
+
+	fn byte_len(&self) -> Option<u64> {
+		self.inner.byte_len()
+	}
+}
+
+impl Drop for WriteThrough {
+	fn drop(&mut self) {
+		self.abandon();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use std::io::Cursor;
+
+	fn temp_cache(capacity: u64) -> Arc<AudioCache> {
+		let dir = std::env::temp_dir().join(format!("utf-nate-audio-{}", Uuid::new_v4()));
+
+		Arc::new(AudioCache::open(dir, capacity).unwrap())
+	}
+
+	fn stream(cache: &Arc<AudioCache>, url: &str, bytes: &[u8]) {
+		let mut input = cache.write_through(url, Box::new(Cursor::new(bytes.to_vec())));
+
+		input.read_to_end(&mut Vec::new()).unwrap();
+	}
+
+	#[test]
+	fn cached_after_streamed() {
+		let cache = temp_cache(1024);
+
+		assert_eq!(cache.get("https://a"), None);
+
+		stream(&cache, "https://a", b"audio");
+
+		let path = cache.get("https://a").expect("Expected a cached file");
+		assert_eq!(fs::read(path).unwrap(), b"audio");
+
+		assert_eq!(
+			cache.stats(),
+			AudioCacheStats {
+				hits: 1,
+				misses: 1,
+				entries: 1,
+				len: 5,
+				capacity: 1024,
+			}
+		);
+
+		// reopened from what is on disk
+		let reopened = AudioCache::open(cache.dir.clone(), 1024).unwrap();
+		assert!(reopened.get("https://a").is_some());
+
+		assert_eq!(
+			cache.clear(),
+			Cleared {
+				entries: 1,
+				len: 5,
+				failed: 0,
+			}
+		);
+		assert_eq!(cache.get("https://a"), None);
+
+		fs::remove_dir_all(&cache.dir).unwrap();
+	}
+
+	#[test]
+	fn clear_continues_past_errors() {
+		let cache = temp_cache(1024);
+
+		stream(&cache, "https://a", &[1; 4]);
+		stream(&cache, "https://b", &[2; 6]);
+
+		// a directory in place of the file can't be removed as a file
+		let path = cache.dir.join(key("https://a"));
+		fs::remove_file(&path).unwrap();
+		fs::create_dir(&path).unwrap();
+
+		assert_eq!(
+			cache.clear(),
+			Cleared {
+				entries: 1,
+				len: 6,
+				failed: 1,
+			}
+		);
+
+		// only what couldn't be removed is still counted
+		assert_eq!(cache.stats().entries, 1);
+		assert_eq!(cache.stats().len, 4);
+
+		fs::remove_dir_all(&cache.dir).unwrap();
+	}
+
+	#[test]
+	fn not_cached_unless_streamed_to_end() {
+		let cache = temp_cache(1024);
+
+		let mut input = cache.write_through("https://a", Box::new(Cursor::new(vec![1; 100])));
+		input.read_exact(&mut [0; 10]).unwrap();
+		drop(input);
+
+		let mut input = cache.write_through("https://b", Box::new(Cursor::new(vec![1; 100])));
+		input.read_exact(&mut [0; 10]).unwrap();
+		input.seek(SeekFrom::Start(50)).unwrap();
+		input.read_to_end(&mut Vec::new()).unwrap();
+
+		assert_eq!(cache.get("https://a"), None);
+		assert_eq!(cache.get("https://b"), None);
+
+		// no part written files are left behind
+		assert_eq!(fs::read_dir(&cache.dir).unwrap().count(), 0);
+
+		fs::remove_dir_all(&cache.dir).unwrap();
+	}
+
+	#[test]
+	fn least_recently_played_evicted() {
+		let cache = temp_cache(10);
+
+		stream(&cache, "https://a", &[1; 4]);
+		stream(&cache, "https://b", &[2; 4]);
+
+		// play a again, so b is the least recently played
+		assert!(cache.get("https://a").is_some());
+
+		stream(&cache, "https://c", &[3; 4]);
+
+		assert!(cache.get("https://a").is_some());
+		assert_eq!(cache.get("https://b"), None);
+		assert!(cache.get("https://c").is_some());
+		assert_eq!(cache.stats().len, 8);
+
+		fs::remove_dir_all(&cache.dir).unwrap();
+	}
+}
@@ -0,0 +1,172 @@
+//! Cache the results of API lookups in memory, so that playing a popular link
+//! again does not spend more of the API quota.
+//!
+//! Each API keeps a [`ApiCache`] for every kind of lookup, keyed by the id
+//! looked up, or for YouTube by the API url and id, whose statistics are shown
+//! by the `cache` command.
+
+use dashmap::DashMap;
+
+use fxhash::FxBuildHasher as BuildHasher;
+
+use tracing::debug;
+
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Entries past which expired entries are dropped when another is added.
+const PRUNE_LEN: usize = 10_000;
+
+/// Values looked up by id, which are kept for a set time.
+pub struct ApiCache<V> {
+	/// Name shown with the statistics.
+	name: &'static str,
+
+	/// How long values are kept after they are looked up.
+	ttl: Duration,
+
+	/// Values by id, with when they expire.
+	entries: DashMap<String, (Instant, V), BuildHasher>,
+
+	hits: AtomicU64,
+	misses: AtomicU64,
+}
+
+/// Statistics of a single [`ApiCache`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CacheStats {
+	pub name: &'static str,
+	pub hits: u64,
+	pub misses: u64,
+	pub entries: usize,
+}
+
+impl fmt::Display for CacheStats {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let total = self.hits + self.misses;
+
+		write!(
+			f,
+			"{}: {} hits, {} misses",
+			self.name, self.hits, self.misses
+		)?;
+
+		if total > 0 {
+			write!(f, " ({:.0}% hit)", self.hits as f64 / total as f64 * 100.0)?;
+		}
+
+		write!(f, ", {} cached", self.entries)
+	}
+}
+
+impl<V: Clone> ApiCache<V> {
+	pub fn new(name: &'static str, ttl: Duration) -> Self {
+		Self {
+			name,
+			ttl,
+			entries: DashMap::default(),
+			hits: AtomicU64::new(0),
+			misses: AtomicU64::new(0),
+		}
+	}
+
+	/// Get the value cached for `key`, unless it has expired.
+	pub fn get(&self, key: &str) -> Option<V> {
+		let value = match self.entries.get(key) {
+			Some(entry) if entry.0 > Instant::now() => Some(entry.1.clone()),
+			Some(entry) => {
+				drop(entry);
+				self.entries.remove(key);
+				None
+			}
+			None => None,
+		};
+
+		match &value {
+			Some(_) => self.hits.fetch_add(1, Ordering::Relaxed),
+			None => self.misses.fetch_add(1, Ordering::Relaxed),
+		};
+
+		debug!(
+			"{} cache {} for {}",
+			self.name,
+			if value.is_some() { "hit" } else { "miss" },
+			key
+		);
+
+		value
+	}
+
+	/// Cache `value` for `key` until the time to live passes.
+	pub fn insert(&self, key: String, value: V) {
+		if self.entries.len() >= PRUNE_LEN {
+			let now = Instant::now();
+			self.entries.retain(|_, (expires, _)| *expires > now);
+		}
+
+		self.entries.insert(key, (Instant::now() + self.ttl, value));
+	}
+
+	/// Get the value cached for `key`, or else cache what `fetch` looks up.
+	/// Errors are not cached.
+	pub async fn get_or_fetch<E>(
+		&self,
+		key: &str,
+		fetch: impl Future<Output = Result<V, E>>,
+	) -> Result<V, E> {
+		if let Some(value) = self.get(key) {
+			return Ok(value);
+		}
+
+		let value = fetch.await?;
+		self.insert(key.to_owned(), value.clone());
+
+		Ok(value)
+	}
+
+	pub fn stats(&self) -> CacheStats {
+		CacheStats {
+			name: self.name,
+			hits: self.hits.load(Ordering::Relaxed),
+			misses: self.misses.load(Ordering::Relaxed),
+			entries: self.entries.len(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[tokio::test]
+	async fn cache_hits_and_misses() {
+		let cache = ApiCache::new("test", Duration::from_secs(60));
+
+		let first = cache.get_or_fetch("a", async { Ok::<_, ()>(1) }).await;
+		let second = cache.get_or_fetch("a", async { Ok::<_, ()>(2) }).await;
+		let failed = cache.get_or_fetch("b", async { Err(()) }).await;
+
+		assert_eq!((first, second, failed), (Ok(1), Ok(1), Err(())));
+		assert_eq!(
+			cache.stats(),
+			CacheStats {
+				name: "test",
+				hits: 1,
+				misses: 2,
+				entries: 1,
+			}
+		);
+	}
+
+	#[test]
+	fn cache_expires() {
+		let cache = ApiCache::new("test", Duration::ZERO);
+
+		cache.insert("a".to_owned(), 1);
+
+		assert_eq!(cache.get("a"), None);
+		assert_eq!(cache.stats().entries, 0);
+	}
+}
@@ -0,0 +1,24 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::BotState;
+use crate::commands::http::{extract_source, render_response};
+use crate::util::GetExpect;
+
+use super::AutoplayArgs;
+
+pub async fn autoplay(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<AutoplayArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::autoplay(&state, &source, &args).await)
+}
@@ -0,0 +1,210 @@
+use rand::seq::IteratorRandom;
+
+use serde::{Deserialize, Serialize};
+
+use serenity::model::id::{ChannelId, GuildId};
+
+use songbird::SongbirdKey;
+use songbird::input::{AuxMetadata, Input};
+
+use tracing::{debug, error, info};
+
+use uuid::Uuid;
+
+use crate::StorageKey;
+use crate::audio::{SearchSource, get_inputs, persist_queue};
+use crate::commands::play::queue_input;
+use crate::commands::{BotState, Source};
+use crate::data::{ArcRw, Keys, TrackData};
+use crate::filter::{self, guild_duck, guild_filters};
+use crate::util::{GetExpect, Respond, Response, check_msg, write_track};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Number of recent history entries that autoplay picks from.
+const HISTORY_PICKS: usize = 50;
+
+pub const fn autoplay_help() -> &'static str {
+	include_str!("help/autoplay.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AutoplayArgs {
+	pub enabled: Option<bool>,
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn autoplay(
+	state: &BotState,
+	source: &Source,
+	args: &AutoplayArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let enabled = match args.enabled {
+		Some(enabled) => {
+			storage
+				.set_autoplay(guild_id, enabled)
+				.await
+				.inspect_err(|e| error!("Unable to set autoplay: {:?}", e))
+				.map_err(|_| "Unable to set autoplay")?;
+
+			enabled
+		}
+		None => storage
+			.get_autoplay(guild_id)
+			.await
+			.inspect_err(|e| error!("Unable to retrieve autoplay: {:?}", e))
+			.map_err(|_| "Unable to retrieve autoplay")?
+			.unwrap_or(false),
+	};
+
+	Ok(match enabled {
+		true => "Autoplay is on",
+		false => "Autoplay is off",
+	}
+	.into())
+}
+
+/// Queue a track related to `last` if autoplay is on for the guild, and
+/// nothing else is left in the queue.
+///
+/// Tracks with a uuid in `ended` are not counted as left in the queue, as they
+/// may not have been removed yet. The track is picked at random from the play
+/// history, falling back to a youtube search for the artist of `last`, or its
+/// title if the artist is unknown.
+#[tracing::instrument(level = "info", skip(state))]
+pub async fn queue_related(
+	state: &BotState,
+	guild_id: GuildId,
+	channel_id: Option<ChannelId>,
+	ended: &[Uuid],
+	last: &AuxMetadata,
+) {
+	let (songbird, keys, storage) = {
+		let data_lock = state.data.read().await;
+
+		(
+			data_lock.clone_expect::<SongbirdKey>(),
+			data_lock.clone_expect::<Keys>(),
+			data_lock.clone_expect::<StorageKey>(),
+		)
+	};
+
+	let enabled = storage
+		.get_autoplay(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve autoplay: {:?}", e))
+		.ok()
+		.flatten()
+		.unwrap_or(false);
+
+	let Some(call) = songbird.get(guild_id) else {
+		return;
+	};
+
+	let queue_empty = |call: &songbird::Call| {
+		call.queue()
+			.current_queue()
+			.iter()
+			.all(|handle| ended.contains(&handle.uuid()))
+	};
+
+	if !enabled || !queue_empty(&*call.lock().await) {
+		return;
+	}
+
+	let history = storage
+		.get_history(guild_id, HISTORY_PICKS)
+		.await
+		.inspect_err(|e| error!("Unable to get history: {:?}", e))
+		.unwrap_or_default();
+
+	let pick = history
+		.into_iter()
+		.filter(|entry| Some(&entry.source_url) != last.source_url.as_ref())
+		.choose(&mut rand::rng());
+
+	let from_history = match pick {
+		Some(entry) => fetch_first(keys.clone(), &entry.source_url, SearchSource::Library).await,
+		None => None,
+	};
+
+	let input = match from_history {
+		Some(input) => Some(input),
+		None => match last.artist.as_ref().or(last.title.as_ref()) {
+			Some(search) => fetch_first(keys, search, SearchSource::Youtube).await,
+			None => None,
+		},
+	};
+
+	let Some(input) = input else {
+		info!("Nothing found to autoplay");
+		return;
+	};
+
+	let volume = storage
+		.get_volume_play(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to get volume: {:?}", e))
+		.ok()
+		.flatten()
+		.unwrap_or(0.5);
+
+	let input = filter::apply(
+		input,
+		guild_filters(state, guild_id).await,
+		guild_duck(state, guild_id).await,
+	);
+
+	let mut lock = call.lock().await;
+
+	// something was queued while searching
+	if !queue_empty(&lock) {
+		return;
+	}
+
+	let respond = channel_id.map(|id| (state.http.clone(), id));
+
+	let Some(handle) = queue_input(
+		Some((&*storage, guild_id)),
+		None,
+		&mut lock,
+		respond.clone(),
+		input,
+		volume,
+	)
+	.await
+	else {
+		return;
+	};
+
+	let queue = lock.queue().clone();
+	drop(lock);
+
+	persist_queue(&*storage, guild_id, &queue, ended).await;
+
+	if let Some(meta) = &handle.data::<TrackData>().metadata {
+		let mut response = String::from("Autoplaying");
+		write_track(&mut response, meta, None).unwrap();
+
+		check_msg(respond.respond_ok(response.into()).await);
+	}
+}
+
+/// Fetch the first input found at `loc`, logging any error.
+async fn fetch_first(keys: ArcRw<Keys>, loc: &str, search_location: SearchSource) -> Option<Input> {
+	debug!("Fetching autoplay input from {}", loc);
+
+	get_inputs(keys, None, loc, false, Some(search_location))
+		.await
+		.inspect_err(|e| error!("Unable to fetch {}: {:?}", loc, e))
+		.ok()
+		.and_then(|mut info| info.inputs.next())
+}
@@ -0,0 +1,22 @@
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+use super::AutoplayArgs;
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::autoplay_help)"
+)]
+pub async fn autoplay(
+	ctx: Context<'_>,
+	#[description = "Whether to queue related songs when the queue runs out"] enabled: Option<bool>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::autoplay(&ctx.into(), &(&ctx).into(), &AutoplayArgs { enabled }),
+	)
+	.await
+}
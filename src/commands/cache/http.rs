@@ -0,0 +1,26 @@
+use axum::extract::State;
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::BotState;
+use crate::commands::http::{extract_source, render_response};
+use crate::util::GetExpect;
+
+pub async fn cache() -> Html<String> {
+	render_response(super::stats().await)
+}
+
+pub async fn cache_stats() -> Html<String> {
+	render_response(super::stats().await)
+}
+
+pub async fn cache_clear(State(state): State<BotState>, jar: CookieJar) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::clear(&state, &source).await)
+}
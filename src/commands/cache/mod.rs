@@ -0,0 +1,73 @@
+use tracing::error;
+
+use std::fmt::Write;
+
+use crate::audio_cache::AUDIO_CACHE;
+use crate::commands::dj::require_owner;
+use crate::commands::{BotState, Source};
+use crate::util::Response;
+use crate::{spotify, youtube};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+pub const fn cache_help() -> &'static str {
+	include_str!("help/cache.md")
+}
+
+pub const fn cache_stats_help() -> &'static str {
+	include_str!("help/cache_stats.md")
+}
+
+pub const fn cache_clear_help() -> &'static str {
+	include_str!("help/cache_clear.md")
+}
+
+#[tracing::instrument(level = "info", ret)]
+pub async fn stats() -> Result<Response, Response> {
+	let mut text = String::new();
+
+	for stats in youtube::cache_stats()
+		.into_iter()
+		.chain(spotify::cache_stats())
+	{
+		writeln!(text, "{}", stats).unwrap();
+	}
+
+	match AUDIO_CACHE.as_ref() {
+		Some(cache) => writeln!(text, "{}", cache.stats()).unwrap(),
+		None => writeln!(text, "Audio: not enabled").unwrap(),
+	}
+
+	Ok(Response::from(text).title("Cache"))
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn clear(state: &BotState, source: &Source) -> Result<Response, Response> {
+	// the audio cache is shared by every guild
+	require_owner(state, source).await?;
+
+	let cache = AUDIO_CACHE
+		.as_ref()
+		.ok_or("The audio cache is not enabled")?;
+
+	let cache = cache.clone();
+
+	let cleared = tokio::task::spawn_blocking(move || cache.clear())
+		.await
+		.inspect_err(|e| error!("Unable to clear audio cache: {:?}", e))
+		.map_err(|_| "Unable to clear the audio cache")?;
+
+	let mut text = format!(
+		"Removed {} cached tracks, freeing {:.1} MiB",
+		cleared.entries,
+		cleared.len as f64 / (1024.0 * 1024.0)
+	);
+
+	if cleared.failed > 0 {
+		write!(text, ", but {} could not be removed", cleared.failed).unwrap();
+	}
+
+	Ok(text.into())
+}
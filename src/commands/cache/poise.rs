@@ -0,0 +1,36 @@
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+#[poise::command(
+	category = "cache",
+	prefix_command,
+	slash_command,
+	subcommands("cache_stats", "cache_clear"),
+	custom_data = "CustomData::new(super::cache_help)"
+)]
+pub async fn cache(ctx: Context<'_>) -> CommandResult {
+	run(&ctx, super::stats()).await
+}
+
+#[poise::command(
+	category = "cache",
+	rename = "stats",
+	prefix_command,
+	slash_command,
+	custom_data = "CustomData::new(super::cache_stats_help)"
+)]
+pub async fn cache_stats(ctx: Context<'_>) -> CommandResult {
+	run(&ctx, super::stats()).await
+}
+
+#[poise::command(
+	category = "cache",
+	rename = "clear",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::cache_clear_help)"
+)]
+pub async fn cache_clear(ctx: Context<'_>) -> CommandResult {
+	run(&ctx, super::clear(&ctx.into(), &(&ctx).into())).await
+}
@@ -0,0 +1,95 @@
+use serde::{Deserialize, Serialize};
+
+use tracing::{error, info};
+
+use std::time::Duration;
+
+use crate::StorageKey;
+use crate::commands::dj::require_dj;
+use crate::commands::{BotState, Source};
+use crate::data::Crossfades;
+use crate::parser::DurationArg;
+use crate::util::{GetExpect, Response};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Longest crossfade, past which most of a short song would be fading.
+const MAX_CROSSFADE: Duration = Duration::from_secs(12);
+
+pub const fn crossfade_help() -> &'static str {
+	include_str!("help/crossfade.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CrossfadeArgs {
+	/// Length of the crossfade, where 0 turns it off.
+	pub length: Option<DurationArg>,
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn crossfade(
+	state: &BotState,
+	source: &Source,
+	args: &CrossfadeArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let (storage, crossfades) = {
+		let lock = state.data.read().await;
+
+		(
+			lock.clone_expect::<StorageKey>(),
+			lock.clone_expect::<Crossfades>(),
+		)
+	};
+
+	let crossfade = match args.length {
+		Some(DurationArg(length)) => {
+			require_dj(state, source).await?;
+
+			if length > MAX_CROSSFADE {
+				return Err(format!(
+					"Crossfade can be at most {} seconds",
+					MAX_CROSSFADE.as_secs()
+				)
+				.into());
+			}
+
+			info!("Setting crossfade to {:?}", length);
+
+			storage
+				.set_crossfade(guild_id, length)
+				.await
+				.inspect_err(|e| error!("Unable to set crossfade: {:?}", e))
+				.map_err(|_| "Unable to set crossfade")?;
+
+			// the change is picked up by the next tick of the crossfade handler
+			if length.is_zero() {
+				crossfades.remove(&guild_id);
+			} else {
+				crossfades.insert(guild_id, length);
+			}
+
+			length
+		}
+		None => storage
+			.get_crossfade(guild_id)
+			.await
+			.inspect_err(|e| error!("Unable to retrieve crossfade: {:?}", e))
+			.map_err(|_| "Unable to retrieve crossfade")?
+			.unwrap_or_default(),
+	};
+
+	Ok(match crossfade.is_zero() {
+		true => "Crossfade is off, so each song starts once the last one ends".into(),
+		false => format!(
+			"Songs crossfade over the last {} seconds",
+			crossfade.as_secs_f32()
+		)
+		.into(),
+	})
+}
@@ -0,0 +1,24 @@
+use crate::commands::{CustomData, run};
+use crate::parser::DurationArg;
+use crate::util::*;
+
+use super::CrossfadeArgs;
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::crossfade_help)"
+)]
+pub async fn crossfade(
+	ctx: Context<'_>,
+	#[description = "How long songs fade into each other, such as 5s, or 0 to turn it off"]
+	length: Option<DurationArg>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::crossfade(&ctx.into(), &(&ctx).into(), &CrossfadeArgs { length }),
+	)
+	.await
+}
@@ -0,0 +1,37 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::BotState;
+use crate::commands::http::{extract_source, render_response};
+use crate::util::GetExpect;
+
+use super::{DjRoleArgs, SkipThresholdArgs};
+
+pub async fn djrole(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<DjRoleArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::djrole(&state, &source, &args).await)
+}
+
+pub async fn skipthreshold(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<SkipThresholdArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::skipthreshold(&state, &source, &args).await)
+}
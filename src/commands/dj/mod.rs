@@ -0,0 +1,291 @@
+use serde::{Deserialize, Serialize};
+
+use serenity::model::guild::Member;
+use serenity::model::id::{GuildId, RoleId};
+
+use songbird::tracks::TrackHandle;
+
+use tracing::{error, info};
+
+use crate::StorageKey;
+use crate::commands::{BotState, Source};
+use crate::data::{SkipVotes, VoiceUserCache};
+use crate::util::{GetExpect, Response};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Percentage of listeners that must vote to skip a track, if none is set.
+const DEFAULT_SKIP_THRESHOLD: u8 = 50;
+
+pub const fn djrole_help() -> &'static str {
+	include_str!("help/djrole.md")
+}
+
+pub const fn skipthreshold_help() -> &'static str {
+	include_str!("help/skipthreshold.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DjRoleArgs {
+	pub role: Option<RoleId>,
+	#[serde(default)]
+	pub clear: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SkipThresholdArgs {
+	pub percent: Option<u8>,
+}
+
+/// Result of voting to skip the current track.
+#[derive(Debug)]
+pub enum Vote {
+	/// Enough listeners have voted, so the track should be skipped.
+	Passed,
+	/// More votes are needed, as the number of votes and the number needed.
+	Pending(usize, usize),
+}
+
+/// Get the member who sent the command in `source`.
+async fn member(state: &BotState, guild_id: GuildId, source: &Source) -> Result<Member, Response> {
+	guild_id
+		.member((&state.cache, state.http.as_ref()), source.user_id)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve member: {:?}", e))
+		.map_err(|_| "Unable to check your roles".into())
+}
+
+/// Whether `member` can manage the guild, which always allows them to act as a
+/// DJ and change the DJ settings.
+fn can_manage(state: &BotState, guild_id: GuildId, member: &Member) -> bool {
+	state
+		.cache
+		.guild(guild_id)
+		.is_some_and(|guild| guild.member_permissions(member).manage_guild())
+}
+
+/// Whether the user of `source` may control the whole queue of the guild.
+///
+/// Everyone may if the guild has no DJ role set, otherwise only members with
+/// the role, or who can manage the guild, may.
+pub async fn is_dj(state: &BotState, source: &Source) -> Result<bool, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let role = storage
+		.get_dj_role(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve DJ role: {:?}", e))
+		.map_err(|_| "Unable to retrieve DJ role")?;
+
+	let Some(role) = role else {
+		return Ok(true);
+	};
+
+	let member = member(state, guild_id, source).await?;
+
+	Ok(member.roles.contains(&role) || can_manage(state, guild_id, &member))
+}
+
+/// Fail unless the user of `source` may control the whole queue, see
+/// [`is_dj`].
+pub async fn require_dj(state: &BotState, source: &Source) -> Result<(), Response> {
+	match is_dj(state, source).await? {
+		true => Ok(()),
+		false => Err("Only members with the DJ role can do this".into()),
+	}
+}
+
+/// Fail unless the user of `source` can manage the guild.
+pub async fn require_manager(state: &BotState, source: &Source) -> Result<GuildId, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let member = member(state, guild_id, source).await?;
+
+	match can_manage(state, guild_id, &member) {
+		true => Ok(guild_id),
+		false => Err("Only members who can manage the server can do this".into()),
+	}
+}
+
+/// Fail unless the user of `source` owns the bot, or is on the team which owns
+/// it, for commands which affect every guild.
+pub async fn require_owner(state: &BotState, source: &Source) -> Result<(), Response> {
+	let info = state
+		.http
+		.get_current_application_info()
+		.await
+		.inspect_err(|e| error!("Unable to retrieve application info: {:?}", e))
+		.map_err(|_| "Unable to retrieve the bot's owner")?;
+
+	let owner = info.owner.is_some_and(|owner| owner.id == source.user_id)
+		|| info
+			.team
+			.is_some_and(|team| team.members.iter().any(|m| m.user.id == source.user_id));
+
+	match owner {
+		true => Ok(()),
+		false => Err("Only the bot's owner can do this".into()),
+	}
+}
+
+/// Vote for the user of `source` to skip `current`.
+///
+/// Only users listening in the bot's channel may vote, and the vote passes
+/// once the guild's skip threshold of those listeners have voted. Votes are
+/// forgotten once the current track changes.
+pub async fn vote_skip(
+	state: &BotState,
+	source: &Source,
+	current: &TrackHandle,
+) -> Result<Vote, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let (cache_guild, votes, storage) = {
+		let lock = state.data.read().await;
+
+		(
+			lock.clone_expect::<VoiceUserCache>()
+				.entry(guild_id)
+				.or_default()
+				.clone(),
+			lock.clone_expect::<SkipVotes>(),
+			lock.clone_expect::<StorageKey>(),
+		)
+	};
+
+	let bot_id = state.cache.current_user().id;
+
+	let bot_channel = cache_guild
+		.get(&bot_id)
+		.and_then(|r| *r.value())
+		.ok_or("Not currently in a voice channel")?;
+
+	let listeners = cache_guild
+		.iter()
+		.filter(|r| *r.value() == Some(bot_channel))
+		.filter(|r| !state.cache.user(*r.key()).is_some_and(|u| u.bot))
+		.count();
+
+	if cache_guild.get(&source.user_id).and_then(|r| *r.value()) != Some(bot_channel) {
+		return Err("Only listeners in the bot's channel can vote to skip".into());
+	}
+
+	let threshold = storage
+		.get_skip_threshold(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve skip threshold: {:?}", e))
+		.map_err(|_| "Unable to retrieve skip threshold")?
+		.unwrap_or(DEFAULT_SKIP_THRESHOLD);
+
+	let needed = (listeners * threshold as usize).div_ceil(100).max(1);
+
+	let mut entry = votes.entry(guild_id).or_default();
+
+	// votes for an earlier track no longer count
+	if entry.0 != current.uuid() {
+		*entry = (current.uuid(), Default::default());
+	}
+
+	entry.1.insert(source.user_id);
+
+	let count = entry.1.len();
+
+	drop(entry);
+
+	info!("{count} of {needed} votes to skip");
+
+	if count >= needed {
+		votes.remove(&guild_id);
+		Ok(Vote::Passed)
+	} else {
+		Ok(Vote::Pending(count, needed))
+	}
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn djrole(
+	state: &BotState,
+	source: &Source,
+	args: &DjRoleArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let role = match (args.role, args.clear) {
+		(None, false) => storage
+			.get_dj_role(guild_id)
+			.await
+			.inspect_err(|e| error!("Unable to retrieve DJ role: {:?}", e))
+			.map_err(|_| "Unable to retrieve DJ role")?,
+		(role, clear) => {
+			require_manager(state, source).await?;
+
+			let role = role.filter(|_| !clear);
+
+			storage
+				.set_dj_role(guild_id, role)
+				.await
+				.inspect_err(|e| error!("Unable to set DJ role: {:?}", e))
+				.map_err(|_| "Unable to set DJ role")?;
+
+			role
+		}
+	};
+
+	Ok(match role {
+		Some(role) => format!("The DJ role is <@&{role}>").into(),
+		None => "There is no DJ role, so anyone can control the queue".into(),
+	})
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn skipthreshold(
+	state: &BotState,
+	source: &Source,
+	args: &SkipThresholdArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let percent = match args.percent {
+		Some(percent) => {
+			require_manager(state, source).await?;
+
+			if !(1..=100).contains(&percent) {
+				return Err("The threshold must be between 1 and 100 percent".into());
+			}
+
+			storage
+				.set_skip_threshold(guild_id, percent)
+				.await
+				.inspect_err(|e| error!("Unable to set skip threshold: {:?}", e))
+				.map_err(|_| "Unable to set skip threshold")?;
+
+			percent
+		}
+		None => storage
+			.get_skip_threshold(guild_id)
+			.await
+			.inspect_err(|e| error!("Unable to retrieve skip threshold: {:?}", e))
+			.map_err(|_| "Unable to retrieve skip threshold")?
+			.unwrap_or(DEFAULT_SKIP_THRESHOLD),
+	};
+
+	Ok(format!("{percent}% of listeners must vote to skip a track").into())
+}
@@ -0,0 +1,46 @@
+use serenity::model::guild::Role;
+
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+use super::{DjRoleArgs, SkipThresholdArgs};
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::djrole_help)"
+)]
+pub async fn djrole(
+	ctx: Context<'_>,
+	#[description = "Role allowed to control the whole queue"] role: Option<Role>,
+	#[description = "Remove the DJ role, letting anyone control the queue"]
+	#[flag]
+	clear: bool,
+) -> CommandResult {
+	let args = DjRoleArgs {
+		role: role.map(|r| r.id),
+		clear,
+	};
+
+	run(&ctx, super::djrole(&ctx.into(), &(&ctx).into(), &args)).await
+}
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::skipthreshold_help)"
+)]
+pub async fn skipthreshold(
+	ctx: Context<'_>,
+	#[description = "Percentage of listeners that must vote to skip a track"] percent: Option<u8>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::skipthreshold(&ctx.into(), &(&ctx).into(), &SkipThresholdArgs { percent }),
+	)
+	.await
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use tracing::{error, info};
+
+use crate::StorageKey;
+use crate::commands::dj::require_dj;
+use crate::commands::{BotState, Source};
+use crate::filter::DUCK_LEVEL;
+use crate::util::{GetExpect, Response};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+pub const fn duck_help() -> &'static str {
+	include_str!("help/duck.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DuckArgs {
+	/// Volume songs are lowered to while clips play, where 1 turns it off.
+	pub level: Option<f32>,
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn duck(
+	state: &BotState,
+	source: &Source,
+	args: &DuckArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let level = match args.level {
+		Some(level) => {
+			require_dj(state, source).await?;
+
+			if !(0.0..=1.0).contains(&level) {
+				return Err("Duck level must be between 0 and 1".into());
+			}
+
+			info!("Setting duck level to {}", level);
+
+			// clips read the level as they start, so the next one uses it
+			storage
+				.set_duck_level(guild_id, level)
+				.await
+				.inspect_err(|e| error!("Unable to set duck level: {:?}", e))
+				.map_err(|_| "Unable to set duck level")?;
+
+			level
+		}
+		None => storage
+			.get_duck_level(guild_id)
+			.await
+			.inspect_err(|e| error!("Unable to retrieve duck level: {:?}", e))
+			.map_err(|_| "Unable to retrieve duck level")?
+			.unwrap_or(DUCK_LEVEL),
+	};
+
+	Ok(match level {
+		1.0 => "Ducking is off, so songs keep their volume while clips play".into(),
+		level => format!("Songs are lowered to {} volume while clips play", level).into(),
+	})
+}
@@ -0,0 +1,23 @@
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+use super::DuckArgs;
+
+#[poise::command(
+	category = "voice",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::duck_help)"
+)]
+pub async fn duck(
+	ctx: Context<'_>,
+	#[description = "Volume songs are lowered to while clips play, from 0 to 1, or 1 to turn it off"]
+	level: Option<f32>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::duck(&ctx.into(), &(&ctx).into(), &DuckArgs { level }),
+	)
+	.await
+}
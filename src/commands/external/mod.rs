@@ -98,5 +98,5 @@ pub async fn cmdlist(CmdlistArgs { path }: &CmdlistArgs) -> Result<Response, Res
 		.map(|chunk| chunk.fold("".to_owned(), |acc, s| acc + &s))
 		.fold("".to_owned(), |acc, s| acc + "\n" + &s);
 
-	Ok(("```\n".to_owned() + &message + "\n```").into())
+	Ok(Response::from("```\n".to_owned() + &message + "\n```").title("Commands"))
 }
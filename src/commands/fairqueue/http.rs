@@ -0,0 +1,37 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::BotState;
+use crate::commands::http::{extract_source, render_response};
+use crate::util::GetExpect;
+
+use super::{FairQueueArgs, QueueLimitArgs};
+
+pub async fn fairqueue(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<FairQueueArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::fairqueue(&state, &source, &args).await)
+}
+
+pub async fn queuelimit(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<QueueLimitArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::queuelimit(&state, &source, &args).await)
+}
@@ -0,0 +1,303 @@
+use itertools::Itertools;
+
+use serde::{Deserialize, Serialize};
+
+use serenity::model::id::UserId;
+
+use songbird::tracks::TrackQueue;
+
+use tracing::{error, info};
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::StorageKey;
+use crate::commands::dj::require_dj;
+use crate::commands::{BotState, Source};
+use crate::data::TrackData;
+use crate::parser::DurationArg;
+use crate::persistence::QueueLimits;
+use crate::util::{GetExpect, Response, write_duration};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+pub const fn fairqueue_help() -> &'static str {
+	include_str!("help/fairqueue.md")
+}
+
+pub const fn queuelimit_help() -> &'static str {
+	include_str!("help/queuelimit.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct FairQueueArgs {
+	pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct QueueLimitArgs {
+	/// Number of tracks each user can queue, where 0 removes the limit.
+	pub tracks: Option<usize>,
+
+	/// Total length each user can queue, where 0 removes the limit.
+	pub duration: Option<DurationArg>,
+}
+
+/// What a single user has in the queue, to check against [`QueueLimits`].
+#[derive(Debug, Default)]
+pub struct QueueUsage {
+	pub tracks: usize,
+	pub duration: Duration,
+}
+
+impl QueueUsage {
+	/// Add up the tracks in `queue` requested by `user_id`.
+	pub fn of(queue: &TrackQueue, user_id: UserId) -> Self {
+		queue
+			.current_queue()
+			.iter()
+			.map(|track| track.data::<TrackData>())
+			.filter(|data| data.requester == Some(user_id))
+			.fold(Self::default(), |mut usage, data| {
+				usage.add(data.metadata.as_ref().and_then(|m| m.duration));
+				usage
+			})
+	}
+
+	/// Count another track of length `duration`.
+	pub fn add(&mut self, duration: Option<Duration>) {
+		self.tracks += 1;
+		self.duration += duration.unwrap_or_default();
+	}
+
+	/// Check whether another track of length `duration` fits within `limits`,
+	/// returning the reason if it does not. A track of unknown length never
+	/// fits within a limit on length.
+	pub fn check(&self, limits: &QueueLimits, duration: Option<Duration>) -> Result<(), String> {
+		if let Some(tracks) = limits.tracks
+			&& self.tracks >= tracks
+		{
+			return Err(format!("You can only have {tracks} songs in the queue"));
+		}
+
+		let Some(limit) = limits.duration else {
+			return Ok(());
+		};
+
+		let Some(duration) = duration else {
+			return Err(
+				"Songs of unknown length can't be queued while the length is limited".into(),
+			);
+		};
+
+		if self.duration + duration > limit {
+			let mut reason = String::from("You can only have ");
+			write_duration(&mut reason, limit).unwrap();
+			reason.push_str(" of songs in the queue");
+
+			return Err(reason);
+		}
+
+		Ok(())
+	}
+}
+
+/// Index to insert a track by `requester` into a queue of tracks by
+/// `requesters`, so that every requester gets a turn before anyone gets their
+/// next track.
+///
+/// The first track is playing, so the track is never put in front of it.
+pub fn fair_index(requesters: &[Option<UserId>], requester: Option<UserId>) -> usize {
+	let mut turns = HashMap::new();
+
+	let rounds = requesters
+		.iter()
+		.map(|r| {
+			let turn = turns.entry(*r).or_insert(0);
+			*turn += 1;
+			*turn - 1
+		})
+		.collect_vec();
+
+	let round = turns.get(&requester).copied().unwrap_or(0);
+
+	// after the last track in the same round or an earlier one
+	rounds
+		.iter()
+		.rposition(|r| *r <= round)
+		.map_or(0, |i| i + 1)
+		.max(1)
+		.min(requesters.len())
+}
+
+/// Move each of the last `count` tracks in `queue` to its turn, as found by
+/// [`fair_index`], returning where they ended up in order.
+pub fn interleave(queue: &TrackQueue, count: usize) -> Vec<usize> {
+	queue.modify_queue(|deque| {
+		// the playing track stays in front
+		let start = deque.len().saturating_sub(count).max(1).min(deque.len());
+
+		let added = deque.drain(start..).collect_vec();
+		let uuids = added.iter().map(|t| t.uuid()).collect_vec();
+
+		for track in added {
+			let requesters = deque
+				.iter()
+				.map(|t| t.data::<TrackData>().requester)
+				.collect_vec();
+
+			let index = fair_index(&requesters, track.data::<TrackData>().requester);
+
+			deque.insert(index, track);
+		}
+
+		deque
+			.iter()
+			.positions(|t| uuids.contains(&t.uuid()))
+			.collect()
+	})
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn fairqueue(
+	state: &BotState,
+	source: &Source,
+	args: &FairQueueArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let enabled = match args.enabled {
+		Some(enabled) => {
+			require_dj(state, source).await?;
+
+			storage
+				.set_fair_queue(guild_id, enabled)
+				.await
+				.inspect_err(|e| error!("Unable to set fair queue: {:?}", e))
+				.map_err(|_| "Unable to set fair queue")?;
+
+			enabled
+		}
+		None => storage
+			.get_fair_queue(guild_id)
+			.await
+			.inspect_err(|e| error!("Unable to retrieve fair queue: {:?}", e))
+			.map_err(|_| "Unable to retrieve fair queue")?
+			.unwrap_or(false),
+	};
+
+	Ok(match enabled {
+		true => "Fair queue is on, so songs take turns by who queued them",
+		false => "Fair queue is off, so songs are queued in order",
+	}
+	.into())
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn queuelimit(
+	state: &BotState,
+	source: &Source,
+	args: &QueueLimitArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let mut limits = storage
+		.get_queue_limits(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve queue limits: {:?}", e))
+		.map_err(|_| "Unable to retrieve queue limits")?;
+
+	if args.tracks.is_some() || args.duration.is_some() {
+		require_dj(state, source).await?;
+
+		if let Some(tracks) = args.tracks {
+			limits.tracks = Some(tracks).filter(|t| *t != 0);
+		}
+
+		if let Some(DurationArg(duration)) = args.duration {
+			limits.duration = Some(duration).filter(|d| !d.is_zero());
+		}
+
+		info!("Setting queue limits to {:?}", limits);
+
+		storage
+			.set_queue_limits(guild_id, &limits)
+			.await
+			.inspect_err(|e| error!("Unable to set queue limits: {:?}", e))
+			.map_err(|_| "Unable to set queue limits")?;
+	}
+
+	let mut response = String::from("Each user can queue ");
+
+	match limits.tracks {
+		Some(tracks) => response.push_str(&format!("up to {tracks} songs")),
+		None => response.push_str("any number of songs"),
+	}
+
+	match limits.duration {
+		Some(duration) => {
+			response.push_str(", up to ");
+			write_duration(&mut response, duration).unwrap();
+			response.push_str(" in total");
+		}
+		None => response.push_str(", of any total length"),
+	}
+
+	Ok(response.into())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// queue tracks one at a time, as `play` would
+	fn queue_fairly(requests: &[u64]) -> Vec<u64> {
+		let mut queue = Vec::new();
+
+		for &r in requests {
+			let requesters = queue.iter().map(|q| Some(UserId::new(*q))).collect_vec();
+			queue.insert(fair_index(&requesters, Some(UserId::new(r))), r);
+		}
+
+		queue
+	}
+
+	#[test]
+	fn unknown_length_exceeds_limit() {
+		let usage = QueueUsage::default();
+
+		let limits = QueueLimits {
+			tracks: None,
+			duration: Some(Duration::from_secs(60)),
+		};
+
+		assert!(usage.check(&limits, Some(Duration::from_secs(60))).is_ok());
+		assert!(usage.check(&limits, Some(Duration::from_secs(61))).is_err());
+		assert!(usage.check(&limits, None).is_err());
+
+		// without a limit on length, it only counts towards the number of songs
+		assert!(usage.check(&QueueLimits::default(), None).is_ok());
+	}
+
+	#[test]
+	fn fair_index_takes_turns() {
+		assert_eq!(queue_fairly(&[1, 1, 1, 2, 2, 3]), vec![1, 2, 3, 1, 2, 1]);
+	}
+
+	#[test]
+	fn fair_index_keeps_playing_track() {
+		// the first track is playing, so a new requester still goes after it
+		assert_eq!(queue_fairly(&[1, 2]), vec![1, 2]);
+		assert_eq!(fair_index(&[], Some(UserId::new(1))), 0);
+	}
+}
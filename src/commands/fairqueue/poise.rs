@@ -0,0 +1,47 @@
+use crate::commands::{CustomData, run};
+use crate::parser::DurationArg;
+use crate::util::*;
+
+use super::{FairQueueArgs, QueueLimitArgs};
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::fairqueue_help)"
+)]
+pub async fn fairqueue(
+	ctx: Context<'_>,
+	#[description = "Whether songs take turns by who queued them"] enabled: Option<bool>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::fairqueue(&ctx.into(), &(&ctx).into(), &FairQueueArgs { enabled }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::queuelimit_help)"
+)]
+pub async fn queuelimit(
+	ctx: Context<'_>,
+	#[description = "Number of songs each user can queue, or 0 for no limit"] tracks: Option<usize>,
+	#[description = "Total length each user can queue, such as 1h, or 0 for no limit"]
+	duration: Option<DurationArg>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::queuelimit(
+			&ctx.into(),
+			&(&ctx).into(),
+			&QueueLimitArgs { tracks, duration },
+		),
+	)
+	.await
+}
@@ -0,0 +1,94 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::BotState;
+use crate::commands::http::{extract_source, render_response};
+use crate::util::GetExpect;
+
+use super::{BassBoostArgs, NightcoreArgs, NormalizeArgs, PitchArgs, SpeedArgs};
+
+pub async fn filter(State(state): State<BotState>, jar: CookieJar) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::filter(&state, &source).await)
+}
+
+pub async fn filter_bassboost(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<BassBoostArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::bassboost(&state, &source, &args).await)
+}
+
+pub async fn filter_nightcore(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<NightcoreArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::nightcore(&state, &source, &args).await)
+}
+
+pub async fn filter_speed(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<SpeedArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::speed(&state, &source, &args).await)
+}
+
+pub async fn filter_pitch(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<PitchArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::pitch(&state, &source, &args).await)
+}
+
+pub async fn filter_normalize(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<NormalizeArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::normalize(&state, &source, &args).await)
+}
+
+pub async fn filter_reset(State(state): State<BotState>, jar: CookieJar) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::reset(&state, &source).await)
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+
+use tracing::{error, info};
+
+use crate::StorageKey;
+use crate::commands::dj::require_dj;
+use crate::commands::{BotState, Source};
+use crate::filter::{Filters, NIGHTCORE_RATE, guild_filters};
+use crate::util::{GetExpect, Response};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Bass boost in decibels when no gain is given.
+const DEFAULT_BASS_BOOST: f32 = 6.0;
+
+/// Highest bass boost in decibels, above which most tracks clip.
+const MAX_BASS_BOOST: f32 = 20.0;
+
+const MIN_SPEED: f32 = 0.5;
+const MAX_SPEED: f32 = 2.0;
+
+/// Furthest the pitch can be shifted in semitones, which is an octave.
+const MAX_PITCH: f32 = 12.0;
+
+pub const fn filter_help() -> &'static str {
+	include_str!("help/filter.md")
+}
+
+pub const fn filter_bassboost_help() -> &'static str {
+	include_str!("help/filter_bassboost.md")
+}
+
+pub const fn filter_nightcore_help() -> &'static str {
+	include_str!("help/filter_nightcore.md")
+}
+
+pub const fn filter_speed_help() -> &'static str {
+	include_str!("help/filter_speed.md")
+}
+
+pub const fn filter_pitch_help() -> &'static str {
+	include_str!("help/filter_pitch.md")
+}
+
+pub const fn filter_normalize_help() -> &'static str {
+	include_str!("help/filter_normalize.md")
+}
+
+pub const fn filter_reset_help() -> &'static str {
+	include_str!("help/filter_reset.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BassBoostArgs {
+	/// Gain in decibels, where 0 turns bass boost off.
+	pub gain: Option<f32>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NightcoreArgs {
+	/// Whether nightcore is on, toggling it if `None`.
+	pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NormalizeArgs {
+	/// Whether loudness is normalized, toggling it if `None`.
+	pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SpeedArgs {
+	pub speed: f32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PitchArgs {
+	pub semitones: f32,
+}
+
+/// Response listing the filters of a guild.
+fn describe(filters: &Filters) -> Response {
+	Response::from(filters.to_string()).title("Filters")
+}
+
+/// Change the filters of the guild with `change`, which is heard straight
+/// away in the current track, and save them for tracks queued later.
+async fn update(
+	state: &BotState,
+	source: &Source,
+	change: impl FnOnce(&mut Filters),
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	require_dj(state, source).await?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+	let shared = guild_filters(state, guild_id).await;
+
+	let filters = {
+		let mut lock = shared.write().unwrap_or_else(|e| e.into_inner());
+		change(&mut lock);
+		*lock
+	};
+
+	info!("Changed filters to {:?}", filters);
+
+	storage
+		.set_filters(guild_id, &filters)
+		.await
+		.inspect_err(|e| error!("Unable to set filters: {:?}", e))
+		.map_err(|_| "Unable to save filters")?;
+
+	Ok(describe(&filters))
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn filter(state: &BotState, source: &Source) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let shared = guild_filters(state, guild_id).await;
+	let filters = *shared.read().unwrap_or_else(|e| e.into_inner());
+
+	Ok(describe(&filters))
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn bassboost(
+	state: &BotState,
+	source: &Source,
+	args: &BassBoostArgs,
+) -> Result<Response, Response> {
+	let gain = args.gain.unwrap_or(DEFAULT_BASS_BOOST);
+
+	if !(0.0..=MAX_BASS_BOOST).contains(&gain) {
+		return Err(format!("Bass boost must be between 0 and {MAX_BASS_BOOST} dB").into());
+	}
+
+	update(state, source, |filters| filters.bass_boost = gain).await
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn nightcore(
+	state: &BotState,
+	source: &Source,
+	args: &NightcoreArgs,
+) -> Result<Response, Response> {
+	update(state, source, |filters| {
+		let enabled = args.enabled.unwrap_or(filters.rate == 1.0);
+
+		filters.rate = match enabled {
+			true => NIGHTCORE_RATE,
+			false => 1.0,
+		};
+	})
+	.await
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn speed(
+	state: &BotState,
+	source: &Source,
+	args: &SpeedArgs,
+) -> Result<Response, Response> {
+	if !(MIN_SPEED..=MAX_SPEED).contains(&args.speed) {
+		return Err(format!("Speed must be between {MIN_SPEED}x and {MAX_SPEED}x").into());
+	}
+
+	update(state, source, |filters| filters.speed = args.speed).await
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn pitch(
+	state: &BotState,
+	source: &Source,
+	args: &PitchArgs,
+) -> Result<Response, Response> {
+	if !(-MAX_PITCH..=MAX_PITCH).contains(&args.semitones) {
+		return Err(format!("Pitch must be between -{MAX_PITCH} and {MAX_PITCH} semitones").into());
+	}
+
+	update(state, source, |filters| filters.pitch = args.semitones).await
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn normalize(
+	state: &BotState,
+	source: &Source,
+	args: &NormalizeArgs,
+) -> Result<Response, Response> {
+	update(state, source, |filters| {
+		filters.normalize = args.enabled.unwrap_or(!filters.normalize);
+	})
+	.await
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn reset(state: &BotState, source: &Source) -> Result<Response, Response> {
+	update(state, source, |filters| *filters = Filters::default()).await
+}
@@ -0,0 +1,130 @@
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+use super::{BassBoostArgs, NightcoreArgs, NormalizeArgs, PitchArgs, SpeedArgs};
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands(
+		"filter_bassboost",
+		"filter_nightcore",
+		"filter_speed",
+		"filter_pitch",
+		"filter_normalize",
+		"filter_reset"
+	),
+	custom_data = "CustomData::new(super::filter_help)"
+)]
+pub async fn filter(ctx: Context<'_>) -> CommandResult {
+	run(&ctx, super::filter(&ctx.into(), &(&ctx).into())).await
+}
+
+#[poise::command(
+	category = "queue",
+	rename = "bassboost",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::filter_bassboost_help)"
+)]
+pub async fn filter_bassboost(
+	ctx: Context<'_>,
+	#[description = "Decibels to boost the bass by, or 0 to turn it off"] gain: Option<f32>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::bassboost(&ctx.into(), &(&ctx).into(), &BassBoostArgs { gain }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	rename = "nightcore",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::filter_nightcore_help)"
+)]
+pub async fn filter_nightcore(
+	ctx: Context<'_>,
+	#[description = "Whether songs play faster and higher"] enabled: Option<bool>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::nightcore(&ctx.into(), &(&ctx).into(), &NightcoreArgs { enabled }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	rename = "speed",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::filter_speed_help)"
+)]
+pub async fn filter_speed(
+	ctx: Context<'_>,
+	#[description = "How fast songs play, from 0.5 to 2"] speed: f32,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::speed(&ctx.into(), &(&ctx).into(), &SpeedArgs { speed }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	rename = "pitch",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::filter_pitch_help)"
+)]
+pub async fn filter_pitch(
+	ctx: Context<'_>,
+	#[description = "Semitones to shift the pitch by, from -12 to 12"] semitones: f32,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::pitch(&ctx.into(), &(&ctx).into(), &PitchArgs { semitones }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	rename = "normalize",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::filter_normalize_help)"
+)]
+pub async fn filter_normalize(
+	ctx: Context<'_>,
+	#[description = "Whether songs and clips play at the same loudness"] enabled: Option<bool>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::normalize(&ctx.into(), &(&ctx).into(), &NormalizeArgs { enabled }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	rename = "reset",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::filter_reset_help)"
+)]
+pub async fn filter_reset(ctx: Context<'_>) -> CommandResult {
+	run(&ctx, super::reset(&ctx.into(), &(&ctx).into())).await
+}
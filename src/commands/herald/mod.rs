@@ -59,7 +59,7 @@ pub async fn intro_outro(
 ) -> Result<Response, Response> {
 	let clip = match &args.clip {
 		Some(clip) => {
-			let mut clips = search_clips(clip.as_ref());
+			let mut clips = search_clips(source.guild_id, clip.as_ref());
 
 			if clips.is_empty() {
 				return Ok(format!("Clip {} not found", clip).into());
@@ -136,7 +136,7 @@ pub async fn introbot(
 
 	let clip = match &args.clip {
 		Some(clip) => {
-			let mut clips = search_clips(clip.as_ref());
+			let mut clips = search_clips(source.guild_id, clip.as_ref());
 
 			if clips.is_empty() {
 				return Ok(format!("Clip {} not found", clip).into());
@@ -0,0 +1,37 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::BotState;
+use crate::commands::http::{extract_source, render_response};
+use crate::util::GetExpect;
+
+use super::{HistoryArgs, ReplayArgs};
+
+pub async fn history(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<HistoryArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::history(&state, &source, args).await)
+}
+
+pub async fn replay(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<ReplayArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::replay(&state, &source, &args).await)
+}
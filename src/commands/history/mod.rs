@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+
+use tracing::error;
+
+use crate::StorageKey;
+use crate::audio::is_url;
+use crate::commands::dj::require_dj;
+use crate::commands::play::queue_urls;
+use crate::commands::{BotState, Source};
+use crate::parser::{NumOrRange, Selection};
+use crate::util::{GetExpect, Response};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Maximum number of entries from the history that can be selected.
+const HISTORY_LIMIT: usize = 1000;
+
+pub const fn history_help() -> &'static str {
+	include_str!("help/history.md")
+}
+
+pub const fn replay_help() -> &'static str {
+	include_str!("help/replay.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct HistoryArgs {
+	#[serde(default = "HistoryArgs::default_selection")]
+	pub selection: Selection<usize>,
+}
+
+impl HistoryArgs {
+	fn default_selection() -> Selection<usize> {
+		Selection(vec![NumOrRange::Range(0..=10)])
+	}
+}
+
+impl Default for HistoryArgs {
+	fn default() -> Self {
+		Self {
+			selection: Self::default_selection(),
+		}
+	}
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReplayArgs {
+	pub selection: Selection<usize>,
+}
+
+/// Flatten a selection into indices, in the order they were selected.
+fn selected_indices(selection: &Selection<usize>) -> impl Iterator<Item = usize> + '_ {
+	selection.0.iter().flat_map(|v| match v {
+		NumOrRange::Num(n) => *n..=*n,
+		NumOrRange::Range(r) => r.clone(),
+	})
+}
+
+/// Number of history entries needed to cover every index in the selection.
+fn selection_limit(selection: &Selection<usize>) -> usize {
+	selected_indices(selection)
+		.max()
+		.map_or(0, |max| max.saturating_add(1))
+		.min(HISTORY_LIMIT)
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn history(
+	state: &BotState,
+	source: &Source,
+	args: HistoryArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let history = storage
+		.get_history(guild_id, selection_limit(&args.selection))
+		.await
+		.inspect_err(|e| error!("Unable to get history: {:?}", e))
+		.map_err(|_| "Error reading history")?;
+
+	if history.is_empty() {
+		return Ok("Nothing has been played".into());
+	}
+
+	let mut response = String::from("Recently queued:\n");
+
+	let entries = selected_indices(&args.selection).filter_map(|i| history.get(i).map(|e| (i, e)));
+
+	for (i, entry) in entries {
+		use std::fmt::Write;
+
+		let title = entry.title.as_deref().unwrap_or("Unknown title");
+
+		if is_url(&entry.source_url) {
+			writeln!(response, "{i}: [{}]({})", title, entry.source_url).unwrap();
+		} else {
+			writeln!(response, "{i}: {}", title).unwrap();
+		}
+	}
+
+	Ok(response.into())
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn replay(
+	state: &BotState,
+	source: &Source,
+	args: &ReplayArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	require_dj(state, source).await?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let history = storage
+		.get_history(guild_id, selection_limit(&args.selection))
+		.await
+		.inspect_err(|e| error!("Unable to get history: {:?}", e))
+		.map_err(|_| "Error reading history")?;
+
+	let urls = selected_indices(&args.selection)
+		.filter_map(|i| history.get(i))
+		.map(|e| e.source_url.as_str())
+		.collect::<Vec<_>>();
+
+	if urls.is_empty() {
+		return Err("No songs in the history match the selection".into());
+	}
+
+	queue_urls(state, source, &urls).await
+}
@@ -0,0 +1,50 @@
+use crate::commands::{CustomData, run};
+use crate::parser::Selection;
+use crate::util::*;
+
+use super::{HistoryArgs, ReplayArgs};
+
+#[poise::command(
+	category = "history",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::history_help)"
+)]
+pub async fn history(
+	ctx: Context<'_>,
+	#[description = "Range or index of songs to show, separated by commas"] selection: Option<
+		Selection<usize>,
+	>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::history(
+			&ctx.into(),
+			&(&ctx).into(),
+			HistoryArgs {
+				selection: selection.unwrap_or_else(HistoryArgs::default_selection),
+			},
+		),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "history",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::replay_help)"
+)]
+pub async fn replay(
+	ctx: Context<'_>,
+	#[description = "Range or index of songs to queue again, separated by commas"]
+	selection: Selection<usize>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::replay(&ctx.into(), &(&ctx).into(), &ReplayArgs { selection }),
+	)
+	.await
+}
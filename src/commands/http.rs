@@ -30,7 +30,8 @@ struct CommandFormTemplate<'a> {
 #[template(path = "response.html")]
 struct ResponseTemplate<'a> {
 	success: bool,
-	response: &'a str,
+	title: Option<&'a str>,
+	pages: Vec<String>,
 }
 
 pub trait FormRouter<S> {
@@ -110,14 +111,37 @@ pub fn extract_source(jar: &CookieJar, key: &LessSafeKey) -> Result<super::Sourc
 		.map_err(|_| "Token expired, please regenerate using the `/token` slash command".into())
 }
 
-pub fn response_string(response: Result<Response, Response>) -> String {
-	response.unwrap_or_else(|e| e).text
+/// Pages of the response in markdown, with any fields and thumbnail appended
+/// to the last page, since there is no embed to put them in.
+pub fn response_pages(response: &Response) -> Vec<String> {
+	use std::fmt::Write;
+
+	let mut pages = response.pages();
+
+	let markdown = pages.last_mut().expect("a response always has a page");
+
+	for (name, value, _) in &response.fields {
+		write!(markdown, "\n\n**{name}**\n{value}").unwrap();
+	}
+
+	if let Some(thumbnail) = &response.thumbnail {
+		write!(markdown, "\n\n![]({thumbnail})").unwrap();
+	}
+
+	pages
 }
 
 pub fn render_response(response: Result<Response, Response>) -> Html<String> {
+	let success = response.is_ok();
+	let response = response.unwrap_or_else(|e| e);
+
 	ResponseTemplate {
-		success: response.is_ok(),
-		response: &markdown::to_html(&response_string(response)),
+		success,
+		title: response.title.as_deref(),
+		pages: response_pages(&response)
+			.iter()
+			.map(|page| markdown::to_html(page))
+			.collect(),
 	}
 	.render()
 	.unwrap()
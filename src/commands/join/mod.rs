@@ -1,14 +1,26 @@
-use songbird::SongbirdKey;
+use songbird::{Event, SongbirdKey, TrackEvent};
 
 use tracing::error;
 
+use std::time::Duration;
+
+use crate::StorageKey;
+use crate::audio::persist_queue;
+use crate::commands::play::restore_queue;
 use crate::commands::{BotState, Source};
+use crate::data::{
+	AutoplayHandler, CrossfadeHandler, Crossfades, PrefetchHandler, QueuePersistHandler,
+};
+use crate::filter::guild_filters;
 use crate::util::{GetExpect, Response};
 
 #[cfg(feature = "http-interface")]
 pub mod http;
 pub mod poise;
 
+/// How often to check whether the current track should start crossfading.
+const CROSSFADE_TICK: Duration = Duration::from_millis(250);
+
 pub const fn summon_help() -> &'static str {
 	include_str!("help/summon.md")
 }
@@ -32,14 +44,93 @@ pub async fn summon(state: &BotState, source: &Source) -> Result<Response, Respo
 
 	let connect_to = channel_id.ok_or("Not in a voice channel")?;
 
-	let songbird = state.data.read().await.clone_expect::<SongbirdKey>();
-	songbird
+	let (songbird, storage, crossfades) = {
+		let data_lock = state.data.read().await;
+
+		(
+			data_lock.clone_expect::<SongbirdKey>(),
+			data_lock.clone_expect::<StorageKey>(),
+			data_lock.clone_expect::<Crossfades>(),
+		)
+	};
+
+	let crossfade = storage
+		.get_crossfade(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve crossfade: {:?}", e))
+		.ok()
+		.flatten()
+		.filter(|c| !c.is_zero());
+
+	match crossfade {
+		Some(crossfade) => {
+			crossfades.insert(guild_id, crossfade);
+		}
+		None => {
+			crossfades.remove(&guild_id);
+		}
+	}
+
+	let filters = guild_filters(state, guild_id).await;
+
+	let call = songbird
 		.join(guild_id, connect_to)
 		.await
 		.inspect_err(|e| error!("Error joining the channel: {e:?}"))
 		.map_err(|_| "Error joining the channel")?;
 
-	Ok("Joined channel".into())
+	{
+		let mut call = call.lock().await;
+
+		// summoning again should not duplicate handlers
+		call.remove_all_global_events();
+
+		let queue = call.queue().clone();
+
+		call.add_global_event(
+			Event::Track(TrackEvent::End),
+			QueuePersistHandler {
+				guild_id,
+				queue: queue.clone(),
+				storage,
+			},
+		);
+
+		let autoplay = AutoplayHandler {
+			guild_id,
+			state: state.clone(),
+			queue: queue.clone(),
+			channel_id: source.channel_id,
+			playing: Default::default(),
+		};
+
+		call.add_global_event(Event::Track(TrackEvent::Play), autoplay.clone());
+		call.add_global_event(Event::Track(TrackEvent::End), autoplay);
+
+		call.add_global_event(
+			Event::Track(TrackEvent::Play),
+			PrefetchHandler {
+				queue: queue.clone(),
+			},
+		);
+
+		call.add_global_event(
+			Event::Periodic(CROSSFADE_TICK, None),
+			CrossfadeHandler {
+				guild_id,
+				queue,
+				crossfades,
+				filters,
+				fading: Default::default(),
+			},
+		);
+	}
+
+	match restore_queue(state, source, guild_id, call).await {
+		0 => Ok("Joined channel".into()),
+		1 => Ok("Joined channel and restored 1 track to the queue".into()),
+		count => Ok(format!("Joined channel and restored {count} tracks to the queue").into()),
+	}
 }
 
 #[tracing::instrument(level = "info", ret, skip(state))]
@@ -48,7 +139,27 @@ pub async fn banish(state: &BotState, source: &Source) -> Result<Response, Respo
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
-	let songbird = state.data.read().await.clone_expect::<SongbirdKey>();
+	let (songbird, storage) = {
+		let data_lock = state.data.read().await;
+
+		(
+			data_lock.clone_expect::<SongbirdKey>(),
+			data_lock.clone_expect::<StorageKey>(),
+		)
+	};
+
+	// save the queue before leaving, since it is lost with the call
+	if let Some(call) = songbird.get(guild_id) {
+		let mut call = call.lock().await;
+
+		// tracks ending as the call is removed should not overwrite the save
+		call.remove_all_global_events();
+
+		let queue = call.queue().clone();
+		drop(call);
+
+		persist_queue(&*storage, guild_id, &queue, &[]).await;
+	}
 
 	{
 		use songbird::error::JoinError::*;
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+use tracing::error;
+
+use crate::commands::queue::current_track;
+use crate::commands::{BotState, Source};
+use crate::data::{LyricsKey, TrackData};
+use crate::lyrics::LyricsQuery;
+use crate::util::{GetExpect, Response};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+pub const fn lyrics_help() -> &'static str {
+	include_str!("help/lyrics.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LyricsArgs {
+	/// Song to look up, instead of the current one.
+	pub query: Option<String>,
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn lyrics(
+	state: &BotState,
+	source: &Source,
+	args: &LyricsArgs,
+) -> Result<Response, Response> {
+	let query = match args.query.as_deref().map(str::trim) {
+		Some(query) if !query.is_empty() => LyricsQuery {
+			title: query.to_owned(),
+			artist: None,
+		},
+		_ => current_track(state, source)
+			.await?
+			.data::<TrackData>()
+			.metadata
+			.as_ref()
+			.and_then(LyricsQuery::from_metadata)
+			.ok_or("The current song has no title to look up")?,
+	};
+
+	let provider = state.data.read().await.clone_expect::<LyricsKey>();
+
+	let lyrics = provider
+		.lyrics(&query)
+		.await
+		.inspect_err(|e| error!("Unable to fetch lyrics: {:?}", e))
+		.map_err(|_| "Unable to fetch lyrics")?
+		.ok_or_else(|| format!("No lyrics found for {}", query.title))?;
+
+	let title = match lyrics.artist {
+		Some(artist) => format!("{} - {}", artist, lyrics.title),
+		None => lyrics.title,
+	};
+
+	// long lyrics are split into pages when responding
+	Ok(Response::from(lyrics.text.trim().to_owned()).title(title))
+}
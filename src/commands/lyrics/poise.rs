@@ -0,0 +1,24 @@
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+use super::LyricsArgs;
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::lyrics_help)"
+)]
+pub async fn lyrics(
+	ctx: Context<'_>,
+	#[description = "Song to look up, instead of the one playing now"]
+	#[rest]
+	query: Option<String>,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::lyrics(&ctx.into(), &(&ctx).into(), &LyricsArgs { query }),
+	)
+	.await
+}
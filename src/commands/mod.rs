@@ -2,18 +2,30 @@
 //!
 //! Commands can be created for adding to the bot using [`commands()`].
 
+pub mod autoplay;
+pub mod cache;
+pub mod crossfade;
+pub mod dj;
+pub mod duck;
 pub mod external;
+pub mod fairqueue;
+pub mod filter;
 pub mod help;
 pub mod herald;
+pub mod history;
 #[cfg(feature = "http-interface")]
 pub mod http;
 pub mod join;
+pub mod lyrics;
 pub mod play;
+pub mod playlist;
 pub mod queue;
 pub mod roll;
+pub mod search;
 #[cfg(feature = "http-interface")]
 pub mod token;
 pub mod unicode;
+pub mod upload;
 pub mod voice;
 
 use futures::Future;
@@ -28,31 +40,50 @@ use std::sync::Arc;
 use crate::util::{Command, CommandResult, Context, Respond, Response};
 
 pub static COMMAND_CREATES: &[fn() -> Command] = &[
+	autoplay::poise::autoplay,
+	cache::poise::cache,
+	crossfade::poise::crossfade,
+	dj::poise::djrole,
+	dj::poise::skipthreshold,
+	duck::poise::duck,
 	external::poise::cmd,
 	external::poise::cmdlist,
+	fairqueue::poise::fairqueue,
+	fairqueue::poise::queuelimit,
+	filter::poise::filter,
 	help::poise::help,
 	herald::poise::intro,
 	herald::poise::introbot,
 	herald::poise::outro,
+	history::poise::history,
+	history::poise::replay,
 	join::poise::summon,
 	join::poise::banish,
+	lyrics::poise::lyrics,
 	play::poise::clip,
 	play::poise::play,
 	play::poise::playnext,
 	play::poise::playnow,
+	playlist::poise::playlist,
 	queue::poise::stop,
 	queue::poise::skip,
 	queue::poise::pause,
 	queue::poise::unpause,
 	queue::poise::queue,
+	queue::poise::nowplaying,
 	queue::poise::shuffle,
 	queue::poise::shufflenow,
 	queue::poise::r#loop,
 	queue::poise::r#move,
+	queue::poise::seek,
+	queue::poise::forward,
+	queue::poise::rewind,
 	roll::poise::roll,
+	search::poise::search,
 	#[cfg(feature = "http-interface")]
 	token::poise::token,
 	unicode::poise::unicode,
+	upload::poise::upload,
 	voice::poise::volume,
 	voice::poise::list,
 ];
@@ -101,6 +132,16 @@ impl From<Context<'_>> for BotState {
 	}
 }
 
+impl From<&serenity::prelude::Context> for BotState {
+	fn from(ctx: &serenity::prelude::Context) -> Self {
+		Self {
+			data: ctx.data.clone(),
+			cache: ctx.cache.clone(),
+			http: ctx.http.clone(),
+		}
+	}
+}
+
 /// Create a vector containing all the commands.
 pub fn commands() -> Vec<Command> {
 	COMMAND_CREATES.iter().map(|c| c()).collect()
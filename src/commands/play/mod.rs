@@ -1,26 +1,36 @@
+use futures::StreamExt;
+
 use serde::{Deserialize, Serialize};
 
 use serenity::http::Http;
-use serenity::model::id::ChannelId;
+use serenity::model::id::{ChannelId, GuildId, UserId};
+use serenity::prelude::Mutex;
 
 use songbird::Call;
 use songbird::SongbirdKey;
-use songbird::input::AuxMetadata;
 use songbird::input::Input;
-use songbird::tracks::Track;
+use songbird::tracks::{Track, TrackHandle};
 
-use tracing::{debug, error};
+use tracing::{debug, error, info};
 
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
-use crate::StorageKey;
 use crate::audio::{AudioError, PlayStyle};
-use crate::audio::{SearchSource, get_inputs, move_queue};
+use crate::audio::{SearchSource, get_inputs, move_queue, persist_queue};
+use crate::commands::dj::require_dj;
+use crate::commands::fairqueue::{QueueUsage, interleave};
 use crate::commands::{BotState, Source};
-use crate::data::{ArcRw, Keys, VoiceGuild, VoiceGuilds};
+use crate::data::{ArcRw, Keys, TrackData, VoiceGuild, VoiceGuilds};
+use crate::filter::{self, duck_level, guild_duck, guild_filters};
+use crate::loudness;
 use crate::parser::Selection;
+use crate::persistence::{HistoryEntry, QueueLimits, Storage};
+use crate::prefetch::{self, prefetch_queue};
 use crate::util::write_duration;
 use crate::util::{GetExpect, Response};
+use crate::{CONFIG, StorageKey};
 
 #[cfg(feature = "http-interface")]
 pub mod http;
@@ -44,7 +54,7 @@ pub const fn playnow_help() -> &'static str {
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PlayArgs {
-	search: String,
+	pub search: String,
 }
 
 #[tracing::instrument(level = "info", ret, skip(state))]
@@ -59,7 +69,12 @@ pub async fn play(
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
-	let (songbird, voice_guild_arc, volume, keys) = {
+	// jumping the queue skips everyone else's tracks
+	if play_index.is_some() {
+		require_dj(state, source).await?;
+	}
+
+	let (songbird, voice_guild_arc, volume, keys, storage) = {
 		debug!("Acquiring lock for play");
 
 		let data_lock = state.data.read().await;
@@ -85,8 +100,9 @@ pub async fn play(
 		.unwrap_or(0.5);
 
 		let keys = data_lock.clone_expect::<Keys>();
+		let storage = storage.clone();
 
-		(songbird, voice_guild_arc, volume, keys)
+		(songbird, voice_guild_arc, volume, keys, storage)
 	};
 
 	debug!("Dropped lock for play");
@@ -98,33 +114,100 @@ pub async fn play(
 
 			let search_location = Some(match play_style {
 				PlayStyle::Clip => SearchSource::Local,
-				PlayStyle::Play => SearchSource::Youtube,
+				PlayStyle::Play => match CONFIG.library {
+					Some(_) => SearchSource::Library,
+					None => SearchSource::Youtube,
+				},
 			});
 
 			let channel_id = source.channel_id;
 
-			let result = match get_inputs(keys, &args.search, true, search_location).await {
+			// limits and turns only apply to the queue, not clips
+			let (limits, fair) = match play_style {
+				PlayStyle::Play => queue_rules(&*storage, guild_id).await,
+				PlayStyle::Clip => (QueueLimits::default(), false),
+			};
+
+			let filters = guild_filters(state, guild_id).await;
+			let duck = voice_guild_arc.read().await.duck();
+			let duck_level = duck_level(&*storage, guild_id).await;
+
+			let result = match get_inputs(keys, Some(guild_id), &args.search, true, search_location)
+				.await
+			{
 				Ok(info) => {
 					use std::fmt::Write;
 
+					let usage = QueueUsage::of(call.lock().await.queue(), source.user_id);
+					let (inputs, mut limited) =
+						take_within_limits(info.inputs, &limits, usage).await;
+
+					// prepared before locking, since it may measure the clip
+					let inputs = match play_style {
+						PlayStyle::Play => inputs,
+						PlayStyle::Clip => {
+							let mut prepared = Vec::with_capacity(inputs.len());
+
+							for (input, duration) in inputs {
+								let input = loudness::prepare_clip(
+									state,
+									guild_id,
+									info.clip.as_deref(),
+									input,
+								)
+								.await;
+
+								prepared.push((input, duration));
+							}
+
+							prepared
+						}
+					};
+
 					let mut lock = call.lock().await;
 
+					// the queue may have changed while fetching lengths
+					let mut usage = QueueUsage::of(lock.queue(), source.user_id);
+
 					let mut input_count = 0;
 
-					for input in info.inputs {
+					for (mut input, duration) in inputs {
+						if play_style == PlayStyle::Play {
+							if let Err(reason) = usage.check(&limits, duration) {
+								limited = Some(reason);
+								break;
+							}
+
+							usage.add(duration);
+
+							input = filter::apply(input, filters.clone(), duck.clone());
+						}
+
 						play_input(
+							(&*storage, guild_id, source.user_id),
 							play_style,
 							&mut lock,
 							voice_guild_arc.clone(),
 							channel_id.map(|id| (state.http.clone(), id)),
 							input,
-							volume,
+							(volume, duck_level),
 						)
 						.await;
 
 						input_count += 1;
 					}
 
+					if input_count == 0
+						&& let Some(reason) = limited
+					{
+						return Err(reason.into());
+					}
+
+					let positions = match (play_index, fair) {
+						(None, true) => Some(interleave(lock.queue(), input_count)),
+						_ => None,
+					};
+
 					if let Some(play_index) = play_index {
 						let start = lock.queue().len() - input_count;
 
@@ -166,7 +249,17 @@ pub async fn play(
 							None => lock.queue().len() - input_count,
 						};
 
-						if input_count > 1 {
+						if let Some(positions) = positions
+							&& let (Some(first), Some(last)) = (positions.first(), positions.last())
+						{
+							// taking turns spreads the tracks out through the queue
+							if first == last {
+								write!(response, " at position {}", first).unwrap();
+							} else {
+								write!(response, " between positions {} and {}", first, last)
+									.unwrap();
+							}
+						} else if input_count > 1 {
 							write!(
 								response,
 								" at positions {} to {}",
@@ -177,6 +270,20 @@ pub async fn play(
 						} else {
 							write!(response, " at position {}", start).unwrap();
 						}
+
+						if let Some(reason) = limited {
+							write!(
+								response,
+								"\n{}, so only {} of {} were queued",
+								reason, input_count, info.count
+							)
+							.unwrap();
+						}
+
+						let queue = lock.queue().clone();
+						drop(lock);
+
+						persist_queue(&*storage, guild_id, &queue, &[]).await;
 					}
 
 					Ok(response)
@@ -201,6 +308,7 @@ pub async fn play(
 						AudioError::Spotify => "Error reading from Spotify".into(),
 						AudioError::YoutubeApiKey => "Error reading from Youtube".into(),
 						AudioError::YoutubeApi(_) => "Error reading from Youtube".into(),
+						AudioError::Ytdl(_) => format!("Unable to read {}", &args.search).into(),
 						AudioError::PlaylistNotAllowed => {
 							"A playlist is not allowed in this context".into()
 						}
@@ -211,56 +319,435 @@ pub async fn play(
 	}
 }
 
-async fn queue_input(
+/// Queue every track saved for the guild with
+/// [`crate::persistence::Storage::set_queue`], if nothing is queued already.
+///
+/// Each saved url is fetched again with [`get_inputs`], and the loop state and
+/// playback position of each track is restored. Tracks which can no longer be
+/// fetched are skipped. Returns the number of tracks that were queued.
+#[tracing::instrument(level = "info", ret, skip(state, call))]
+pub async fn restore_queue(
+	state: &BotState,
+	source: &Source,
+	guild_id: GuildId,
+	call: Arc<Mutex<Call>>,
+) -> usize {
+	if !call.lock().await.queue().is_empty() {
+		return 0;
+	}
+
+	let (keys, storage) = {
+		let data_lock = state.data.read().await;
+
+		(
+			data_lock.clone_expect::<Keys>(),
+			data_lock.clone_expect::<StorageKey>(),
+		)
+	};
+
+	let saved = match storage.get_queue(guild_id).await {
+		Ok(saved) => saved,
+		Err(e) => {
+			error!("Unable to get saved queue: {:?}", e);
+			return 0;
+		}
+	};
+
+	if saved.is_empty() {
+		return 0;
+	}
+
+	let volume = storage
+		.get_volume_play(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to get volume: {:?}", e))
+		.ok()
+		.flatten()
+		.unwrap_or(0.5);
+
+	let urls = saved
+		.iter()
+		.map(|t| t.source_url.as_str())
+		.collect::<Vec<_>>();
+	let mut inputs = fetch_inputs(keys, &urls).await;
+	let filters = guild_filters(state, guild_id).await;
+	let duck = guild_duck(state, guild_id).await;
+
+	let (limits, fair) = queue_rules(&*storage, guild_id).await;
+
+	let mut durations = Vec::with_capacity(inputs.len());
+
+	for input in &mut inputs {
+		durations.push(match input {
+			Some(input) => limited_duration(input, &limits).await,
+			None => None,
+		});
+	}
+
+	let mut lock = call.lock().await;
+
+	// something was queued while fetching, so don't mix in the old queue
+	if !lock.queue().is_empty() {
+		info!("Queue changed while restoring, discarding saved queue");
+		return 0;
+	}
+
+	let mut count = 0;
+	let mut usages = HashMap::<UserId, QueueUsage>::new();
+
+	for ((track, input), duration) in saved.into_iter().zip(inputs).zip(durations) {
+		let Some(input) = input else {
+			continue;
+		};
+
+		// limits may have changed since the queue was saved, and don't apply
+		// to tracks the bot queued itself
+		if let Some(requester) = track.requester {
+			let usage = usages.entry(requester).or_default();
+
+			if let Err(reason) = usage.check(&limits, duration) {
+				info!("Not restoring track: {}", reason);
+				continue;
+			}
+
+			usage.add(duration);
+		}
+
+		let respond = source.channel_id.map(|id| (state.http.clone(), id));
+
+		let input = filter::apply(input, filters.clone(), duck.clone());
+
+		let Some(handle) =
+			queue_input(None, track.requester, &mut lock, respond, input, volume).await
+		else {
+			continue;
+		};
+
+		count += 1;
+
+		let looping = match track.loops {
+			None => handle.enable_loop(),
+			Some(0) => Ok(()),
+			Some(n) => handle.loop_for(n),
+		};
+
+		if let Err(e) = looping {
+			error!("Unable to restore loop state: {:?}", e);
+		}
+
+		if !track.position.is_zero() {
+			// the result is not needed, the track seeks once it is ready
+			drop(handle.seek(track.position));
+		}
+	}
+
+	if fair {
+		interleave(lock.queue(), count);
+	}
+
+	count
+}
+
+/// Queue the first track found at each url, in order, for the guild.
+///
+/// Used to queue urls which have been saved before, such as from the play
+/// history. Urls which can no longer be fetched are skipped.
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn queue_urls(
+	state: &BotState,
+	source: &Source,
+	urls: &[&str],
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let (songbird, keys, storage) = {
+		let data_lock = state.data.read().await;
+
+		(
+			data_lock.clone_expect::<SongbirdKey>(),
+			data_lock.clone_expect::<Keys>(),
+			data_lock.clone_expect::<StorageKey>(),
+		)
+	};
+
+	let call = songbird.get(guild_id).ok_or("Not in a voice channel")?;
+
+	let volume = storage
+		.get_volume_play(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to get volume: {:?}", e))
+		.ok()
+		.flatten()
+		.unwrap_or(0.5);
+
+	let (limits, fair) = queue_rules(&*storage, guild_id).await;
+
+	let inputs = fetch_inputs(keys, urls).await.into_iter().flatten();
+	let usage = QueueUsage::of(call.lock().await.queue(), source.user_id);
+	let (inputs, mut limited) = take_within_limits(inputs, &limits, usage).await;
+
+	let filters = guild_filters(state, guild_id).await;
+	let duck = guild_duck(state, guild_id).await;
+
+	let mut lock = call.lock().await;
+
+	// the queue may have changed while fetching
+	let mut usage = QueueUsage::of(lock.queue(), source.user_id);
+
+	let start = lock.queue().len();
+	let mut count = 0;
+
+	for (input, duration) in inputs {
+		if let Err(reason) = usage.check(&limits, duration) {
+			limited = Some(reason);
+			break;
+		}
+
+		let respond = source.channel_id.map(|id| (state.http.clone(), id));
+
+		if queue_input(
+			Some((&*storage, guild_id)),
+			Some(source.user_id),
+			&mut lock,
+			respond,
+			filter::apply(input, filters.clone(), duck.clone()),
+			volume,
+		)
+		.await
+		.is_some()
+		{
+			usage.add(duration);
+			count += 1;
+		}
+	}
+
+	let positions = match fair {
+		true => interleave(lock.queue(), count),
+		false => (start..start + count).collect(),
+	};
+
+	let queue = lock.queue().clone();
+	drop(lock);
+
+	persist_queue(&*storage, guild_id, &queue, &[]).await;
+
+	let (Some(first), Some(last)) = (positions.first(), positions.last()) else {
+		return Err(limited
+			.unwrap_or_else(|| "Unable to queue any tracks".into())
+			.into());
+	};
+
+	use std::fmt::Write;
+
+	let mut response = match (count, urls.len()) {
+		(1, 1) => String::from("Queued 1 track"),
+		(count, total) if count == total => format!("Queued {} tracks", count),
+		(count, total) => format!("Queued {} of {} tracks", count, total),
+	};
+
+	match (first == last, fair) {
+		(true, _) => write!(response, " at position {}", first),
+		// taking turns spreads the tracks out through the queue
+		(false, true) => write!(response, " between positions {} and {}", first, last),
+		(false, false) => write!(response, " at positions {} to {}", first, last),
+	}
+	.unwrap();
+
+	if let Some(reason) = limited {
+		write!(response, "\n{}", reason).unwrap();
+	}
+
+	Ok(response.into())
+}
+
+/// Queue limits and whether members take turns in the queue of the guild.
+async fn queue_rules(
+	storage: &(dyn Storage + Send + Sync),
+	guild_id: GuildId,
+) -> (QueueLimits, bool) {
+	let limits = storage
+		.get_queue_limits(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to get queue limits: {:?}", e))
+		.unwrap_or_default();
+
+	let fair = storage
+		.get_fair_queue(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to get fair queue: {:?}", e))
+		.ok()
+		.flatten()
+		.unwrap_or(false);
+
+	(limits, fair)
+}
+
+/// Length of `input` if the length of queued tracks is limited by `limits`.
+///
+/// Fetching the length may need a request, so this should be done before
+/// locking the call.
+async fn limited_duration(input: &mut Input, limits: &QueueLimits) -> Option<Duration> {
+	match limits.duration {
+		Some(_) => input
+			.aux_metadata()
+			.await
+			.inspect_err(|e| error!("Unable to fetch metadata: {:?}", e))
+			.ok()
+			.and_then(|m| m.duration),
+		None => None,
+	}
+}
+
+/// Take inputs while they fit within `limits` on top of `usage`, along with
+/// their [`limited_duration`], and the reason for stopping early if they
+/// don't all fit.
+async fn take_within_limits(
+	inputs: impl Iterator<Item = Input>,
+	limits: &QueueLimits,
+	mut usage: QueueUsage,
+) -> (Vec<(Input, Option<Duration>)>, Option<String>) {
+	let mut taken = Vec::new();
+
+	for mut input in inputs {
+		let duration = limited_duration(&mut input, limits).await;
+
+		if let Err(reason) = usage.check(limits, duration) {
+			return (taken, Some(reason));
+		}
+
+		usage.add(duration);
+		taken.push((input, duration));
+	}
+
+	(taken, None)
+}
+
+/// Fetch the first input at each url, keeping the order of `urls`.
+///
+/// Errors are logged, and result in `None` for that url.
+async fn fetch_inputs(keys: ArcRw<Keys>, urls: &[&str]) -> Vec<Option<Input>> {
+	// owned urls keep the stream from borrowing across await points
+	let urls = urls.iter().map(|url| url.to_string()).collect::<Vec<_>>();
+
+	futures::stream::iter(urls)
+		.map(|url| {
+			let keys = keys.clone();
+
+			async move {
+				get_inputs(keys, None, &url, false, Some(SearchSource::Library))
+					.await
+					.inspect_err(|e| error!("Unable to fetch {}: {:?}", url, e))
+					.ok()
+					.and_then(|mut info| info.inputs.next())
+			}
+		})
+		// same concurrency as fetching playlists
+		.buffered(4)
+		.collect()
+		.await
+}
+
+/// Queue a single input, recording it in the play history of the guild if
+/// `history` is set.
+///
+/// The track is attributed to `requester`, which is `None` when the bot queued
+/// it itself.
+pub async fn queue_input(
+	history: Option<(&(dyn Storage + Send + Sync), GuildId)>,
+	requester: Option<UserId>,
 	call: &mut Call,
 	respond: Option<(Arc<Http>, ChannelId)>,
 	mut input: Input,
 	volume: f32,
-) -> bool {
+) -> Option<TrackHandle> {
 	let aux_metadata = input
 		.aux_metadata()
 		.await
 		.inspect_err(|e| error!("Unable to fetch metadata: {:?}", e))
 		.ok();
 
-	let track = Track::new_with_data(input, Arc::new(aux_metadata)).volume(volume);
+	if let Some((storage, guild_id)) = history
+		&& let Some(source_url) = aux_metadata.as_ref().and_then(|m| m.source_url.clone())
+	{
+		let entry = HistoryEntry {
+			source_url,
+			title: aux_metadata.as_ref().and_then(|m| m.title.clone()),
+			queued_at: SystemTime::now(),
+		};
+
+		let _ = storage
+			.add_history(guild_id, &entry)
+			.await
+			.inspect_err(|e| error!("Unable to add history: {:?}", e));
+	}
+
+	let (input, prefetch) = prefetch::wrap(input);
+
+	let data = TrackData {
+		prefetch,
+		..TrackData::new(aux_metadata, requester)
+	};
+	let track = Track::new_with_data(input, Arc::new(data)).volume(volume);
 
 	let handle = call.enqueue(track).await;
 
-	if let Err(e) = VoiceGuild::add_error_handler(handle, respond) {
+	// the track may be one of the next to play
+	prefetch_queue(call.queue());
+
+	if let Err(e) = VoiceGuild::add_error_handler(handle.clone(), respond) {
 		error!("Error setting up error handler for track: {:?}", e);
-		return false;
+		return None;
 	}
 
-	true
+	Some(handle)
 }
 
 async fn immediate_input(
+	requester: UserId,
 	call: &mut Call,
 	voice_guild_arc: ArcRw<VoiceGuild>,
 	input: Input,
 	volume: f32,
+	duck_level: f32,
 ) -> bool {
-	let track = Track::new_with_data(input, Arc::new(None::<AuxMetadata>)).volume(volume);
+	let data = TrackData::new(None, Some(requester));
+	let track = Track::new_with_data(input, Arc::new(data)).volume(volume);
 
 	let handle = call.play(track);
 	voice_guild_arc
 		.write()
 		.await
-		.add_audio(handle, volume)
+		.add_audio(handle, volume, duck_level)
 		.is_ok()
 }
 
+/// Play a single input in the style of `play_style`, attributed to
+/// `requester`. Queued inputs are recorded in the play history of `guild_id`,
+/// and clips duck the queue to `duck_level` while they play.
 async fn play_input(
+	(storage, guild_id, requester): (&(dyn Storage + Send + Sync), GuildId, UserId),
 	play_style: PlayStyle,
 	call: &mut Call,
 	voice_guild_arc: ArcRw<VoiceGuild>,
 	respond: Option<(Arc<Http>, ChannelId)>,
 	input: Input,
-	volume: f32,
+	(volume, duck_level): (f32, f32),
 ) -> bool {
 	match play_style {
-		PlayStyle::Clip => immediate_input(call, voice_guild_arc, input, volume).await,
-		PlayStyle::Play => queue_input(call, respond, input, volume).await,
+		PlayStyle::Clip => {
+			immediate_input(requester, call, voice_guild_arc, input, volume, duck_level).await
+		}
+		PlayStyle::Play => queue_input(
+			Some((storage, guild_id)),
+			Some(requester),
+			call,
+			respond,
+			input,
+			volume,
+		)
+		.await
+		.is_some(),
 	}
 }
@@ -57,7 +57,7 @@ async fn play_type_command(
 )]
 pub async fn play(
 	ctx: Context<'_>,
-	#[description = "Youtube or Spotify URL, or Youtube search"]
+	#[description = "Youtube, Spotify, SoundCloud or Bandcamp URL, or Youtube search"]
 	#[rest]
 	query: String,
 ) -> CommandResult {
@@ -73,7 +73,7 @@ pub async fn play(
 )]
 pub async fn playnext(
 	ctx: Context<'_>,
-	#[description = "Youtube or Spotify URL, or Youtube search"]
+	#[description = "Youtube, Spotify, SoundCloud or Bandcamp URL, or Youtube search"]
 	#[rest]
 	query: String,
 ) -> CommandResult {
@@ -89,7 +89,7 @@ pub async fn playnext(
 )]
 pub async fn playnow(
 	ctx: Context<'_>,
-	#[description = "Youtube or Spotify URL, or Youtube search"]
+	#[description = "Youtube, Spotify, SoundCloud or Bandcamp URL, or Youtube search"]
 	#[rest]
 	query: String,
 ) -> CommandResult {
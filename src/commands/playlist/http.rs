@@ -0,0 +1,59 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::BotState;
+use crate::commands::http::{extract_source, render_response};
+use crate::util::GetExpect;
+
+use super::PlaylistArgs;
+
+pub async fn playlist_save(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<PlaylistArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::save(&state, &source, &args).await)
+}
+
+pub async fn playlist_load(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<PlaylistArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::load(&state, &source, &args).await)
+}
+
+pub async fn playlist_list(State(state): State<BotState>, jar: CookieJar) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::list(&state, &source).await)
+}
+
+pub async fn playlist_delete(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<PlaylistArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::delete(&state, &source, &args).await)
+}
@@ -0,0 +1,216 @@
+use serde::{Deserialize, Serialize};
+
+use songbird::SongbirdKey;
+
+use tracing::error;
+
+use crate::StorageKey;
+use crate::audio::snapshot_queue;
+use crate::commands::dj::require_dj;
+use crate::commands::play::queue_urls;
+use crate::commands::{BotState, Source};
+use crate::persistence::{PlaylistTrack, StorageError};
+use crate::util::{GetExpect, Response};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Maximum length of a playlist name, in characters.
+const NAME_MAX_LEN: usize = 100;
+
+pub const fn playlist_help() -> &'static str {
+	include_str!("help/playlist.md")
+}
+
+pub const fn playlist_save_help() -> &'static str {
+	include_str!("help/playlist_save.md")
+}
+
+pub const fn playlist_load_help() -> &'static str {
+	include_str!("help/playlist_load.md")
+}
+
+pub const fn playlist_list_help() -> &'static str {
+	include_str!("help/playlist_list.md")
+}
+
+pub const fn playlist_delete_help() -> &'static str {
+	include_str!("help/playlist_delete.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PlaylistArgs {
+	pub name: String,
+}
+
+impl PlaylistArgs {
+	/// Trimmed name of the playlist, or an error response if it is not a
+	/// valid name.
+	fn name(&self) -> Result<&str, Response> {
+		let name = self.name.trim();
+
+		if name.is_empty() {
+			Err("Playlist name cannot be empty".into())
+		} else if name.chars().count() > NAME_MAX_LEN {
+			Err(format!(
+				"Playlist name cannot be longer than {} characters",
+				NAME_MAX_LEN
+			)
+			.into())
+		} else {
+			Ok(name)
+		}
+	}
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn save(
+	state: &BotState,
+	source: &Source,
+	args: &PlaylistArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let name = args.name()?;
+
+	let (songbird, storage) = {
+		let data_lock = state.data.read().await;
+
+		(
+			data_lock.clone_expect::<SongbirdKey>(),
+			data_lock.clone_expect::<StorageKey>(),
+		)
+	};
+
+	let call = songbird.get(guild_id).ok_or("Not in a voice channel")?;
+
+	let queue = call.lock().await.queue().clone();
+
+	let tracks = snapshot_queue(&queue, &[])
+		.await
+		.into_iter()
+		.map(|track| PlaylistTrack {
+			source_url: track.source_url,
+			title: track.title,
+		})
+		.collect::<Vec<_>>();
+
+	if tracks.is_empty() {
+		return Err("Nothing queued to save".into());
+	}
+
+	let existing = storage
+		.get_playlist(guild_id, name)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve playlist: {:?}", e))
+		.map_err(|_| "Error saving playlist")?;
+
+	if existing.is_some() {
+		require_dj(state, source).await?;
+	}
+
+	storage
+		.save_playlist(guild_id, name, &tracks)
+		.await
+		.inspect_err(|e| error!("Unable to save playlist: {:?}", e))
+		.map_err(|_| "Error saving playlist")?;
+
+	Ok(match tracks.len() {
+		1 => format!("Saved 1 track to playlist {}", name),
+		count => format!("Saved {} tracks to playlist {}", count, name),
+	}
+	.into())
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn load(
+	state: &BotState,
+	source: &Source,
+	args: &PlaylistArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	require_dj(state, source).await?;
+
+	let name = args.name()?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let tracks = storage
+		.get_playlist(guild_id, name)
+		.await
+		.inspect_err(|e| error!("Unable to get playlist: {:?}", e))
+		.map_err(|_| "Error loading playlist")?
+		.ok_or_else(|| format!("No playlist named {}", name))?;
+
+	let urls = tracks
+		.iter()
+		.map(|t| t.source_url.as_str())
+		.collect::<Vec<_>>();
+
+	queue_urls(state, source, &urls).await
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn list(state: &BotState, source: &Source) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	let playlists = storage
+		.list_playlists(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to list playlists: {:?}", e))
+		.map_err(|_| "Error listing playlists")?;
+
+	if playlists.is_empty() {
+		return Ok("No playlists saved".into());
+	}
+
+	let mut response = String::from("Saved playlists:\n");
+
+	for (name, count) in playlists {
+		use std::fmt::Write;
+
+		match count {
+			1 => writeln!(response, "{name} (1 track)"),
+			_ => writeln!(response, "{name} ({count} tracks)"),
+		}
+		.unwrap();
+	}
+
+	Ok(response.into())
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn delete(
+	state: &BotState,
+	source: &Source,
+	args: &PlaylistArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	require_dj(state, source).await?;
+
+	let name = args.name()?;
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+
+	match storage.delete_playlist(guild_id, name).await {
+		Ok(()) => Ok(format!("Deleted playlist {}", name).into()),
+		Err(StorageError::NoRowsChanged) => Err(format!("No playlist named {}", name).into()),
+		Err(e) => {
+			error!("Unable to delete playlist: {:?}", e);
+			Err("Error deleting playlist".into())
+		}
+	}
+}
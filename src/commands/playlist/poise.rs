@@ -0,0 +1,91 @@
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+use super::PlaylistArgs;
+
+#[poise::command(
+	category = "playlist",
+	prefix_command,
+	slash_command,
+	guild_only,
+	subcommands("playlist_save", "playlist_load", "playlist_list", "playlist_delete"),
+	custom_data = "CustomData::new(super::playlist_help)"
+)]
+pub async fn playlist(ctx: Context<'_>) -> CommandResult {
+	run(&ctx, super::list(&ctx.into(), &(&ctx).into())).await
+}
+
+#[poise::command(
+	category = "playlist",
+	rename = "save",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::playlist_save_help)"
+)]
+pub async fn playlist_save(
+	ctx: Context<'_>,
+	#[description = "Name to save the playlist as"]
+	#[rest]
+	name: String,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::save(&ctx.into(), &(&ctx).into(), &PlaylistArgs { name }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "playlist",
+	rename = "load",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::playlist_load_help)"
+)]
+pub async fn playlist_load(
+	ctx: Context<'_>,
+	#[description = "Name of the playlist to queue"]
+	#[rest]
+	name: String,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::load(&ctx.into(), &(&ctx).into(), &PlaylistArgs { name }),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "playlist",
+	rename = "list",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::playlist_list_help)"
+)]
+pub async fn playlist_list(ctx: Context<'_>) -> CommandResult {
+	run(&ctx, super::list(&ctx.into(), &(&ctx).into())).await
+}
+
+#[poise::command(
+	category = "playlist",
+	rename = "delete",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::playlist_delete_help)"
+)]
+pub async fn playlist_delete(
+	ctx: Context<'_>,
+	#[description = "Name of the playlist to delete"]
+	#[rest]
+	name: String,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::delete(&ctx.into(), &(&ctx).into(), &PlaylistArgs { name }),
+	)
+	.await
+}
@@ -3,12 +3,20 @@ use axum::response::Html;
 
 use axum_extra::extract::CookieJar;
 
+use serde::{Deserialize, Serialize};
+
 use crate::AeadKey;
 use crate::commands::BotState;
 use crate::commands::http::{extract_source, render_response};
+use crate::parser::DurationArg;
 use crate::util::GetExpect;
 
-use super::{LoopArgs, MoveArgs, QueueArgs, SkipArgs};
+use super::{LoopArgs, MoveArgs, QueueArgs, SeekMode, SkipArgs};
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SeekArgs {
+	time: DurationArg,
+}
 
 pub async fn stop(State(state): State<BotState>, jar: CookieJar) -> Html<String> {
 	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
@@ -106,3 +114,51 @@ pub async fn r#move(
 
 	render_response(super::r#move(&state, &source, args).await)
 }
+
+pub async fn seek(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<SeekArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::seek(&state, &source, SeekMode::To(args.time.0)).await)
+}
+
+pub async fn forward(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<SeekArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::seek(&state, &source, SeekMode::Forward(args.time.0)).await)
+}
+
+pub async fn rewind(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<SeekArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::seek(&state, &source, SeekMode::Rewind(args.time.0)).await)
+}
+
+pub async fn nowplaying(State(state): State<BotState>, jar: CookieJar) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(super::nowplaying(&state, &source).await)
+}
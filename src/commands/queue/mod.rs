@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::time::{Duration, UNIX_EPOCH};
 
 use rand::Rng;
 
@@ -6,17 +7,19 @@ use serde::{Deserialize, Serialize};
 
 use songbird::SongbirdKey;
 use songbird::input::AuxMetadata;
-use songbird::tracks::PlayMode;
+use songbird::tracks::{LoopState, PlayMode, TrackHandle};
 
 use thiserror::Error;
 
 use tracing::{error, info};
 
-use crate::audio::move_queue;
+use crate::StorageKey;
+use crate::audio::{move_queue, persist_queue};
+use crate::commands::dj::{Vote, is_dj, require_dj, vote_skip};
 use crate::commands::{BotState, Source};
-use crate::data::VoiceGuilds;
+use crate::data::{TrackData, VoiceGuilds};
 use crate::parser::{NumOrRange, Selection};
-use crate::util::{GetExpect, Response, write_track};
+use crate::util::{GetExpect, Response, write_duration, write_track};
 
 #[cfg(feature = "http-interface")]
 pub mod http;
@@ -58,9 +61,29 @@ pub const fn move_help() -> &'static str {
 	include_str!("help/move.md")
 }
 
+pub const fn nowplaying_help() -> &'static str {
+	include_str!("help/nowplaying.md")
+}
+
+pub const fn seek_help() -> &'static str {
+	include_str!("help/seek.md")
+}
+
+pub const fn forward_help() -> &'static str {
+	include_str!("help/forward.md")
+}
+
+pub const fn rewind_help() -> &'static str {
+	include_str!("help/rewind.md")
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SkipArgs {
 	pub skip_set: Option<Selection<usize>>,
+
+	/// Skip every track requested by the user instead.
+	#[serde(default)]
+	pub mine: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -94,12 +117,25 @@ pub struct MoveArgs {
 	pub position: usize,
 }
 
+/// Where to seek to in the current track.
+#[derive(Debug)]
+pub enum SeekMode {
+	/// Seek to an exact position.
+	To(Duration),
+	/// Seek ahead of the current position.
+	Forward(Duration),
+	/// Seek behind the current position.
+	Rewind(Duration),
+}
+
 #[tracing::instrument(level = "info", ret, skip(state))]
 pub async fn stop(state: &BotState, source: &Source) -> Result<Response, Response> {
 	let guild_id = source
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
+	require_dj(state, source).await?;
+
 	let lock = state.data.read().await;
 
 	if let Some(voice_guild) = lock.clone_expect::<VoiceGuilds>().get(&guild_id) {
@@ -130,6 +166,8 @@ pub async fn skip(
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
+	let dj = is_dj(state, source).await?;
+
 	let call = state
 		.data
 		.read()
@@ -138,12 +176,86 @@ pub async fn skip(
 		.get_or_insert(guild_id)
 		.clone();
 
+	let requested_by_user =
+		|track: &TrackHandle| track.data::<TrackData>().requester == Some(source.user_id);
+
+	// the vote is resolved before locking the call, since it reads storage
+	let voted = if !dj && !args.mine && args.skip_set.as_ref().is_none_or(|s| s.0.is_empty()) {
+		let current = call
+			.lock()
+			.await
+			.queue()
+			.current()
+			.ok_or("Nothing is currently playing")?;
+
+		if requested_by_user(&current) {
+			None
+		} else {
+			match vote_skip(state, source, &current).await? {
+				Vote::Pending(count, needed) => {
+					return Ok(format!("Voted to skip ({count}/{needed} votes)").into());
+				}
+				Vote::Passed => Some(current.uuid()),
+			}
+		}
+	} else {
+		None
+	};
+
 	let call = call.lock().await;
 
 	let queue = call.queue();
 
 	let current = queue.current().ok_or("Nothing is currently playing")?;
 
+	let skip_set = if args.mine {
+		let mine = queue
+			.current_queue()
+			.iter()
+			.enumerate()
+			.filter(|(_, track)| requested_by_user(track))
+			.map(|(i, _)| NumOrRange::Num(i))
+			.collect::<Vec<_>>();
+
+		if mine.is_empty() {
+			return Ok("You have no songs in the queue".into());
+		}
+
+		Some(Selection(mine))
+	} else {
+		args.skip_set.clone()
+	};
+
+	// without the dj role, only your own tracks can be skipped, or else the
+	// current track by vote
+	if !dj {
+		match &skip_set {
+			Some(skip_set) if !skip_set.0.is_empty() => {
+				let current_queue = queue.current_queue();
+
+				let others = skip_set
+					.0
+					.iter()
+					.flat_map(|v| match v {
+						NumOrRange::Num(n) => *n..=*n,
+						NumOrRange::Range(r) => *r.start()..=(*r.end()).min(current_queue.len()),
+					})
+					.filter_map(|i| current_queue.get(i))
+					.any(|track| !requested_by_user(track));
+
+				if others {
+					return Err(
+						"Only members with the DJ role can skip songs queued by others".into(),
+					);
+				}
+			}
+			_ if requested_by_user(&current) => {}
+			_ if voted == Some(current.uuid()) => {}
+			// the track changed since the vote
+			_ => return Err("The current song changed, try skipping again".into()),
+		}
+	}
+
 	let resume = current
 		.get_info()
 		.await
@@ -152,9 +264,8 @@ pub async fn skip(
 		.playing
 		== PlayMode::Play;
 
-	let result = match &args.skip_set {
-		Some(skip_set) if !skip_set.0.is_empty() => {
-			let mut skip_set = skip_set.clone();
+	let result = match skip_set {
+		Some(mut skip_set) if !skip_set.0.is_empty() => {
 			let mut removed = HashSet::new();
 
 			queue.modify_queue(|deque| {
@@ -224,6 +335,8 @@ pub async fn pause(state: &BotState, source: &Source) -> Result<Response, Respon
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
+	require_dj(state, source).await?;
+
 	state
 		.data
 		.read()
@@ -245,6 +358,8 @@ pub async fn unpause(state: &BotState, source: &Source) -> Result<Response, Resp
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
+	require_dj(state, source).await?;
+
 	state
 		.data
 		.read()
@@ -282,7 +397,7 @@ pub async fn queue(
 		return Ok("Nothing queued".into());
 	}
 
-	let mut response = format!("Current queue ({} total):\n", len);
+	let mut response = String::new();
 
 	let tracks = args
 		.selection
@@ -299,16 +414,22 @@ pub async fn queue(
 
 		write!(response, "{i}:").unwrap();
 
-		if let Some(meta) = track.data::<Option<AuxMetadata>>().as_ref() {
+		let data = track.data::<TrackData>();
+
+		if let Some(meta) = &data.metadata {
 			write_track(&mut response, meta, track.get_info().await.ok()).unwrap();
 		} else {
 			response.push_str("No metadata");
 		}
 
+		if let Some(requester) = data.requester {
+			write!(response, " requested by <@{requester}>").unwrap();
+		}
+
 		writeln!(response).unwrap();
 	}
 
-	Ok(response.into())
+	Ok(Response::from(response).title(format!("Current queue ({len} total)")))
 }
 
 #[tracing::instrument(level = "info", ret, skip(state))]
@@ -321,6 +442,8 @@ pub async fn shuffle(
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
+	require_dj(state, source).await?;
+
 	let call = state
 		.data
 		.read()
@@ -333,7 +456,7 @@ pub async fn shuffle(
 
 	let queue = call.queue();
 
-	queue
+	let result = queue
 		.pause()
 		.map(|_| {
 			queue.modify_queue(|deque| {
@@ -348,7 +471,15 @@ pub async fn shuffle(
 		.and_then(|_| queue.resume())
 		.map(|_| "Shuffled queue".into())
 		.inspect_err(|e| error!("{:?}", e))
-		.map_err(|_| "Error shuffling queue".into())
+		.map_err(|_| "Error shuffling queue".into());
+
+	let queue = queue.clone();
+	drop(call);
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+	persist_queue(&*storage, guild_id, &queue, &[]).await;
+
+	result
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
@@ -386,6 +517,8 @@ pub async fn r#loop(
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
+	require_dj(state, source).await?;
+
 	let call = state
 		.data
 		.read()
@@ -422,6 +555,8 @@ pub async fn r#move(
 		.guild_id
 		.ok_or("This command is only available in guilds")?;
 
+	require_dj(state, source).await?;
+
 	let call = state
 		.data
 		.read()
@@ -432,7 +567,7 @@ pub async fn r#move(
 
 	let mut call = call.lock().await;
 
-	move_queue(&mut call, args.selection, args.position)
+	let result = move_queue(&mut call, args.selection, args.position)
 		.await
 		.map(|count| match count {
 			0 => "No clips moved".into(),
@@ -440,5 +575,197 @@ pub async fn r#move(
 			c => format!("Moved {} clips", c).into(),
 		})
 		.inspect_err(|e| error!("{:?}", e))
-		.map_err(|_| "Error moving clips".into())
+		.map_err(|_| "Error moving clips".into());
+
+	let queue = call.queue().clone();
+	drop(call);
+
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+	persist_queue(&*storage, guild_id, &queue, &[]).await;
+
+	result
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn seek(state: &BotState, source: &Source, mode: SeekMode) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	require_dj(state, source).await?;
+
+	let call = state
+		.data
+		.read()
+		.await
+		.clone_expect::<SongbirdKey>()
+		.get_or_insert(guild_id)
+		.clone();
+
+	// seeking can take a while, so don't hold the call while waiting
+	let current = call
+		.lock()
+		.await
+		.queue()
+		.current()
+		.ok_or("Nothing is currently playing")?;
+
+	let position = current
+		.get_info()
+		.await
+		.inspect_err(|e| error!("{:?}", e))
+		.map_err(|_| "Error seeking in the current song")?
+		.position;
+
+	let target = match mode {
+		SeekMode::To(time) => time,
+		SeekMode::Forward(time) => position + time,
+		SeekMode::Rewind(time) => position.saturating_sub(time),
+	};
+
+	let length = current
+		.data::<TrackData>()
+		.metadata
+		.as_ref()
+		.and_then(|meta| meta.duration);
+
+	if length.is_some_and(|length| target >= length) {
+		return Err("Cannot seek past the end of the current song".into());
+	}
+
+	let position = current
+		.seek_async(target)
+		.await
+		.inspect_err(|e| error!("{:?}", e))
+		.map_err(|_| "Unable to seek in the current song")?;
+
+	let mut response = String::from("Moved to ");
+	write_duration(&mut response, position).unwrap();
+
+	if let Some(length) = length {
+		response.push('/');
+		write_duration(&mut response, length).unwrap();
+	}
+
+	Ok(response.into())
+}
+
+/// Number of characters in the progress bar of [`describe_track`].
+const PROGRESS_BAR_LEN: usize = 20;
+
+/// Get the track that is playing now in the guild of `source`.
+pub async fn current_track(state: &BotState, source: &Source) -> Result<TrackHandle, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	state
+		.data
+		.read()
+		.await
+		.clone_expect::<SongbirdKey>()
+		.get_or_insert(guild_id)
+		.lock()
+		.await
+		.queue()
+		.current()
+		.ok_or_else(|| "Nothing is currently playing".into())
+}
+
+/// Describe the title, artist, progress and loop state of a track.
+///
+/// Returns an error once the track has finished.
+pub async fn describe_track(track: &TrackHandle) -> Result<Response, Response> {
+	let state = track
+		.get_info()
+		.await
+		.map_err(|_| "Nothing is currently playing")?;
+
+	if state.playing.is_done() {
+		return Err("Nothing is currently playing".into());
+	}
+
+	let data = track.data::<TrackData>();
+	let meta = data.metadata.clone().unwrap_or_default();
+
+	let mut text = String::from("Now playing");
+	write_track(
+		&mut text,
+		&AuxMetadata {
+			duration: None,
+			..meta.clone()
+		},
+		None,
+	)
+	.unwrap();
+
+	let mut progress = String::new();
+
+	if state.playing == PlayMode::Pause {
+		progress.push_str("⏸ ");
+	}
+
+	if let Some(length) = meta.duration.filter(|d| !d.is_zero()) {
+		let filled = ((state.position.as_secs_f64() / length.as_secs_f64())
+			* PROGRESS_BAR_LEN as f64) as usize;
+		let filled = filled.min(PROGRESS_BAR_LEN - 1);
+
+		progress.push_str(&"▬".repeat(filled));
+		progress.push('🔘');
+		progress.push_str(&"▬".repeat(PROGRESS_BAR_LEN - 1 - filled));
+		progress.push(' ');
+		write_duration(&mut progress, state.position).unwrap();
+		progress.push('/');
+		write_duration(&mut progress, length).unwrap();
+	} else {
+		write_duration(&mut progress, state.position).unwrap();
+	}
+
+	let looping = match state.loops {
+		LoopState::Infinite => String::from("Forever"),
+		LoopState::Finite(0) => String::from("Off"),
+		LoopState::Finite(1) => String::from("1 more time"),
+		LoopState::Finite(n) => format!("{n} more times"),
+	};
+
+	let mut response = Response::from(text);
+
+	if let Some(artist) = meta.artist {
+		response = response.field("Artist", artist, true);
+	}
+
+	if let Some(channel) = meta.channel {
+		response = response.field("Channel", channel, true);
+	}
+
+	if let Some(requester) = data.requester {
+		let queued_at = data
+			.queued_at
+			.duration_since(UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_secs();
+
+		// discord shows the timestamp relative to now, such as "5 minutes ago"
+		response = response.field(
+			"Requested by",
+			format!("<@{requester}> <t:{queued_at}:R>"),
+			true,
+		);
+	}
+
+	response = response
+		.field("Progress", progress, false)
+		.field("Loop", looping, true);
+
+	if let Some(thumbnail) = meta.thumbnail {
+		response = response.thumbnail(thumbnail);
+	}
+
+	Ok(response)
+}
+
+#[cfg(feature = "http-interface")]
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn nowplaying(state: &BotState, source: &Source) -> Result<Response, Response> {
+	describe_track(&current_track(state, source).await?).await
 }
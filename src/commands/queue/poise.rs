@@ -1,10 +1,14 @@
+use std::time::Duration;
+
+use tracing::debug;
+
 use crate::commands::queue::QueueArgs;
 use crate::commands::{CustomData, run};
-use crate::parser::Selection;
+use crate::parser::{DurationArg, Selection};
 use crate::util::*;
 
 pub use super::LoopArg;
-use super::{LoopArgs, MoveArgs, SkipArgs};
+use super::{LoopArgs, MoveArgs, SeekMode, SkipArgs};
 
 #[poise::command(
 	category = "queue",
@@ -29,6 +33,9 @@ pub async fn skip(
 	#[description = "Range or index of songs to skip, separated by commas"] selection: Option<
 		Selection<usize>,
 	>,
+	#[description = "Skip every song you queued"]
+	#[flag]
+	mine: bool,
 ) -> CommandResult {
 	run(
 		&ctx,
@@ -37,6 +44,7 @@ pub async fn skip(
 			&(&ctx).into(),
 			&SkipArgs {
 				skip_set: selection,
+				mine,
 			},
 		),
 	)
@@ -168,3 +176,97 @@ pub async fn r#move(
 	)
 	.await
 }
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::seek_help)"
+)]
+pub async fn seek(
+	ctx: Context<'_>,
+	#[description = "Time to jump to, such as 1:30 or 90s"] time: DurationArg,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::seek(&ctx.into(), &(&ctx).into(), SeekMode::To(time.0)),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::forward_help)"
+)]
+pub async fn forward(
+	ctx: Context<'_>,
+	#[description = "Time to skip ahead, such as 30s or 1:00"] time: DurationArg,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::seek(&ctx.into(), &(&ctx).into(), SeekMode::Forward(time.0)),
+	)
+	.await
+}
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::rewind_help)"
+)]
+pub async fn rewind(
+	ctx: Context<'_>,
+	#[description = "Time to go back, such as 30s or 1:00"] time: DurationArg,
+) -> CommandResult {
+	run(
+		&ctx,
+		super::seek(&ctx.into(), &(&ctx).into(), SeekMode::Rewind(time.0)),
+	)
+	.await
+}
+
+/// Time between updates of a `nowplaying` message.
+const NOWPLAYING_UPDATE: Duration = Duration::from_secs(5);
+
+#[poise::command(
+	category = "queue",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::nowplaying_help)"
+)]
+pub async fn nowplaying(ctx: Context<'_>) -> CommandResult {
+	ctx.defer_or_broadcast().await?;
+
+	let track = match super::current_track(&ctx.into(), &(&ctx).into()).await {
+		Ok(track) => track,
+		Err(e) => {
+			ctx.respond_err(e).await?;
+			return Ok(());
+		}
+	};
+
+	let reply = ctx.respond(super::describe_track(&track).await).await?;
+
+	// keep the message up to date until the track ends, or it can't be edited
+	loop {
+		tokio::time::sleep(NOWPLAYING_UPDATE).await;
+
+		let Ok(response) = super::describe_track(&track).await else {
+			break;
+		};
+
+		if let Err(e) = reply.edit(ctx, Response::reply(Ok(response))).await {
+			debug!("Stopped updating now playing: {:?}", e);
+			break;
+		}
+	}
+
+	Ok(())
+}
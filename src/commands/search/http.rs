@@ -0,0 +1,40 @@
+use axum::extract::{Query, State};
+use axum::response::Html;
+
+use axum_extra::extract::CookieJar;
+
+use crate::AeadKey;
+use crate::commands::http::{extract_source, render_response};
+use crate::commands::{BotState, Source};
+use crate::util::{GetExpect, Response};
+
+use super::{SearchArgs, list_results, search_results};
+
+pub async fn search(
+	State(state): State<BotState>,
+	jar: CookieJar,
+	Query(args): Query<SearchArgs>,
+) -> Html<String> {
+	let source = match extract_source(&jar, state.data.read().await.get_expect::<AeadKey>()) {
+		Err(e) => return render_response(Err(e)),
+		Ok(source) => source,
+	};
+
+	render_response(list(&state, &source, &args).await)
+}
+
+/// List the results of a search, each linking to where it can be queued from
+/// the http interface, since there is no menu to pick from.
+#[tracing::instrument(level = "info", ret, skip(state))]
+async fn list(state: &BotState, source: &Source, args: &SearchArgs) -> Result<Response, Response> {
+	source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	let results = search_results(state, args).await?;
+
+	// video urls only use characters which are allowed in a query as they are
+	Ok(list_results(args, &results, |url| {
+		format!("/play/run?search={}", url)
+	}))
+}
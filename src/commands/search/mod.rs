@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+
+use songbird::input::{AuxMetadata, YoutubeDl};
+
+use tracing::error;
+
+use crate::audio::ComposeWithMetadata;
+use crate::commands::BotState;
+use crate::data::Keys;
+use crate::util::{GetExpect, Response, write_duration};
+use crate::youtube;
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Number of results shown to pick from.
+const RESULT_COUNT: usize = 5;
+
+pub const fn search_help() -> &'static str {
+	include_str!("help/search.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SearchArgs {
+	pub query: String,
+}
+
+/// Search youtube for the best matches of the query, in order.
+pub async fn search_results(
+	state: &BotState,
+	args: &SearchArgs,
+) -> Result<Vec<AuxMetadata>, Response> {
+	let api = state
+		.data
+		.read()
+		.await
+		.clone_expect::<Keys>()
+		.read()
+		.await
+		.youtube
+		.clone()
+		.ok_or("Searching is not set up")?;
+
+	let videos = youtube::search(&api, &args.query, RESULT_COUNT)
+		.await
+		.inspect_err(|e| error!("Unable to search youtube: {:?}", e))
+		.map_err(|_| "Unable to search Youtube")?;
+
+	if videos.is_empty() {
+		return Err(format!("Nothing found for {}", args.query).into());
+	}
+
+	Ok(videos
+		.into_iter()
+		.map(|video| ComposeWithMetadata::<YoutubeDl>::from(video).aux_metadata)
+		.collect())
+}
+
+/// List the results numbered from 1, where each title links to whatever
+/// `link` makes of its url.
+pub fn list_results(
+	args: &SearchArgs,
+	results: &[AuxMetadata],
+	link: impl Fn(&str) -> String,
+) -> Response {
+	use std::fmt::Write;
+
+	let mut text = String::new();
+
+	for (i, result) in results.iter().enumerate() {
+		let title = result.title.as_deref().unwrap_or("Unknown");
+
+		match &result.source_url {
+			Some(url) => write!(text, "{}. [{}]({})", i + 1, title, link(url)).unwrap(),
+			None => write!(text, "{}. {}", i + 1, title).unwrap(),
+		}
+
+		if let Some(duration) = result.duration {
+			text.push_str(" (");
+			write_duration(&mut text, duration).unwrap();
+			text.push(')');
+		}
+
+		if let Some(channel) = &result.channel {
+			write!(text, " by {}", channel).unwrap();
+		}
+
+		text.push('\n');
+	}
+
+	Response::from(text).title(format!("Results for {}", args.query))
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use std::time::Duration;
+
+	#[test]
+	fn list_numbered_results() {
+		let args = SearchArgs {
+			query: "test".to_owned(),
+		};
+
+		let results = [
+			AuxMetadata {
+				title: Some("First".to_owned()),
+				channel: Some("Channel".to_owned()),
+				source_url: Some("https://youtu.be/a".to_owned()),
+				duration: Some(Duration::from_secs(65)),
+				..Default::default()
+			},
+			AuxMetadata {
+				title: Some("Second".to_owned()),
+				..Default::default()
+			},
+		];
+
+		let response = list_results(&args, &results, |url| format!("/play/run?search={url}"));
+
+		assert_eq!(
+			response.text,
+			"1. [First](/play/run?search=https://youtu.be/a) (1:05) by Channel\n2. Second\n"
+		);
+		assert_eq!(response.title.as_deref(), Some("Results for test"));
+	}
+}
@@ -0,0 +1,134 @@
+use serenity::builder::{
+	CreateActionRow, CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+	CreateSelectMenuKind, CreateSelectMenuOption, EditMessage,
+};
+use serenity::collector::ComponentInteractionCollector;
+use serenity::model::application::ComponentInteractionDataKind;
+
+use tracing::error;
+
+use std::time::Duration;
+
+use crate::audio::PlayStyle;
+use crate::commands::play::{PlayArgs, play};
+use crate::commands::{BotState, CustomData, Source};
+use crate::util::*;
+
+use super::SearchArgs;
+
+/// How long the results can be picked from after searching.
+const PICK_TIMEOUT: Duration = Duration::from_secs(120);
+
+const PICK_ID: &str = "search_pick";
+
+/// Longest label discord allows for a menu option.
+const LABEL_LEN: usize = 100;
+
+#[poise::command(
+	category = "play",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::search_help)"
+)]
+pub async fn search(
+	ctx: Context<'_>,
+	#[description = "Youtube search"]
+	#[rest]
+	query: String,
+) -> CommandResult {
+	let typing = ctx.defer_or_broadcast().await?;
+
+	let state = BotState::from(ctx);
+	let source = Source::from(&ctx);
+	let args = SearchArgs { query };
+
+	let results = match super::search_results(&state, &args).await {
+		Ok(results) => results,
+		Err(e) => {
+			ctx.respond(Err(e)).await?;
+			return Ok(());
+		}
+	};
+
+	let options = results
+		.iter()
+		.enumerate()
+		.map(|(i, result)| {
+			let title = result.title.as_deref().unwrap_or("Unknown");
+			let label = format!("{}. {}", i + 1, title);
+			let label = &label[..label.floor_char_boundary(LABEL_LEN)];
+
+			CreateSelectMenuOption::new(label, i.to_string())
+		})
+		.collect();
+
+	let menu = CreateSelectMenu::new(PICK_ID, CreateSelectMenuKind::String { options })
+		.placeholder("Pick a song to queue");
+
+	let reply = ctx
+		.send(
+			Response::reply(Ok(super::list_results(&args, &results, str::to_owned)))
+				.components(vec![CreateActionRow::SelectMenu(menu)]),
+		)
+		.await?;
+
+	drop(typing);
+
+	let message = reply.message().await?.into_owned();
+
+	let pick = ComponentInteractionCollector::new(ctx)
+		.message_id(message.id)
+		.author_id(ctx.author().id)
+		.custom_ids(vec![PICK_ID.to_owned()])
+		.timeout(PICK_TIMEOUT)
+		.await;
+
+	let Some(pick) = pick else {
+		// nothing was picked in time, so stop offering the menu
+		message
+			.channel_id
+			.edit_message(ctx, message.id, EditMessage::new().components(vec![]))
+			.await
+			.inspect_err(|e| error!("Unable to remove search menu: {:?}", e))?;
+
+		return Ok(());
+	};
+
+	pick.create_response(
+		ctx,
+		CreateInteractionResponse::UpdateMessage(
+			CreateInteractionResponseMessage::new().components(vec![]),
+		),
+	)
+	.await?;
+
+	let url = match &pick.data.kind {
+		ComponentInteractionDataKind::StringSelect { values } => values
+			.first()
+			.and_then(|v| v.parse::<usize>().ok())
+			.and_then(|i| results.get(i))
+			.and_then(|result| result.source_url.clone()),
+		_ => None,
+	};
+
+	let Some(url) = url else {
+		ctx.respond(Err("Unable to queue the picked song".into()))
+			.await?;
+		return Ok(());
+	};
+
+	ctx.respond(
+		play(
+			&state,
+			&source,
+			PlayStyle::Play,
+			None,
+			&PlayArgs { search: url },
+		)
+		.await,
+	)
+	.await?;
+
+	Ok(())
+}
@@ -0,0 +1,312 @@
+use percent_encoding::percent_decode_str;
+
+use reqwest::Url;
+
+use serde::{Deserialize, Serialize};
+
+use songbird::input::{AudioStream, AudioStreamError};
+
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::io::MediaSource;
+use symphonia::core::probe::Hint;
+
+use tokio::io::AsyncWriteExt;
+
+use tracing::error;
+
+use std::io::Cursor;
+use std::path::{Component, Path};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::audio::{CLIP_EXTENSIONS, GUILD_CLIP_PATH, guild_clip_path};
+use crate::commands::dj::require_dj;
+use crate::commands::{BotState, Source};
+use crate::filter::PacketDecoder;
+use crate::loudness::clip_loudness;
+use crate::util::{GetExpect, Response, sandboxed_join};
+use crate::{REQWEST_CLIENT, StorageKey};
+
+#[cfg(feature = "http-interface")]
+pub mod http;
+pub mod poise;
+
+/// Largest file which can be uploaded as a clip, in bytes.
+const MAX_UPLOAD_LEN: usize = 8 * 1024 * 1024;
+
+/// Longest clip which can be uploaded.
+const MAX_CLIP_DURATION: Duration = Duration::from_secs(30);
+
+/// Hosts which files can be uploaded from, which are where Discord keeps
+/// attachments.
+const UPLOAD_HOSTS: [&str; 2] = ["cdn.discordapp.com", "media.discordapp.net"];
+
+pub const fn upload_help() -> &'static str {
+	include_str!("help/upload.md")
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UploadArgs {
+	/// Url of the Discord attachment to upload.
+	pub file: String,
+
+	/// Name of the clip, or the name of the file if `None`.
+	pub name: Option<String>,
+}
+
+/// Name and extension of the file at `url`.
+fn file_name(url: &str) -> Option<(String, String)> {
+	let url = Url::parse(url).ok()?;
+	let segment = percent_decode_str(url.path_segments()?.next_back()?)
+		.decode_utf8()
+		.ok()?;
+	let path = Path::new(&*segment);
+
+	Some((
+		path.file_stem()?.to_str()?.to_owned(),
+		path.extension()?.to_str()?.to_lowercase(),
+	))
+}
+
+/// Parse `url` if it is a Discord attachment, as the bot should not fetch
+/// arbitrary urls on behalf of users.
+fn attachment_url(url: &str) -> Option<Url> {
+	let url = Url::parse(url).ok()?;
+
+	(url.scheme() == "https"
+		&& url.port().is_none()
+		&& url
+			.host_str()
+			.is_some_and(|host| UPLOAD_HOSTS.contains(&host)))
+	.then_some(url)
+}
+
+/// Check that `name` can be a clip name in a single directory.
+///
+/// Dots are not allowed, as everything after one would be taken as the
+/// extension of the clip.
+fn valid_name(name: &str) -> bool {
+	let mut components = Path::new(name).components();
+
+	!name.contains('.')
+		&& matches!(components.next(), Some(Component::Normal(_)))
+		&& components.next().is_none()
+}
+
+/// Download the file at `url`, unless it is larger than [`MAX_UPLOAD_LEN`].
+async fn download(url: Url) -> Result<Vec<u8>, Response> {
+	let too_large = || format!("Clips can be at most {} MiB", MAX_UPLOAD_LEN / 1024 / 1024);
+
+	let mut response = REQWEST_CLIENT
+		.get(url)
+		.send()
+		.await
+		.and_then(|r| r.error_for_status())
+		.inspect_err(|e| error!("Unable to download clip: {:?}", e))
+		.map_err(|_| "Unable to download the file")?;
+
+	if response
+		.content_length()
+		.is_some_and(|len| len > MAX_UPLOAD_LEN as u64)
+	{
+		return Err(too_large().into());
+	}
+
+	let mut bytes = Vec::new();
+
+	while let Some(chunk) = response
+		.chunk()
+		.await
+		.inspect_err(|e| error!("Unable to download clip: {:?}", e))
+		.map_err(|_| "Unable to download the file")?
+	{
+		bytes.extend_from_slice(&chunk);
+
+		if bytes.len() > MAX_UPLOAD_LEN {
+			return Err(too_large().into());
+		}
+	}
+
+	Ok(bytes)
+}
+
+/// Decode `bytes` as audio of the format `extension` to find how long it is.
+///
+/// Decoding stops once it is longer than [`MAX_CLIP_DURATION`], so a longer
+/// clip only reports being longer.
+fn clip_duration(bytes: Arc<[u8]>, extension: &str) -> Result<Duration, AudioStreamError> {
+	let fail = |e: SymphoniaError| AudioStreamError::Fail(Box::new(e));
+
+	let mut hint = Hint::new();
+	hint.with_extension(extension);
+
+	let mut packets = PacketDecoder::new(AudioStream {
+		input: Box::new(Cursor::new(bytes)) as Box<dyn MediaSource>,
+		hint: Some(hint),
+	})?;
+
+	let mut seconds = 0.0;
+
+	while let Some((spec, samples)) = packets.next().map_err(fail)? {
+		seconds += samples.len() as f64 / spec.channels.count() as f64 / spec.rate as f64;
+
+		if seconds > MAX_CLIP_DURATION.as_secs_f64() {
+			break;
+		}
+	}
+
+	match seconds {
+		0.0 => Err(AudioStreamError::Unsupported),
+		seconds => Ok(Duration::from_secs_f64(seconds)),
+	}
+}
+
+#[tracing::instrument(level = "info", ret, skip(state))]
+pub async fn upload(
+	state: &BotState,
+	source: &Source,
+	args: &UploadArgs,
+) -> Result<Response, Response> {
+	let guild_id = source
+		.guild_id
+		.ok_or("This command is only available in guilds")?;
+
+	require_dj(state, source).await?;
+
+	let url = attachment_url(&args.file).ok_or("Clips must be uploaded as Discord attachments")?;
+
+	let (file_stem, extension) = file_name(&args.file).ok_or("Unable to read the file name")?;
+
+	if !CLIP_EXTENSIONS.contains(&&*extension) {
+		return Err(format!("Clips must be {} files", CLIP_EXTENSIONS.join(" or ")).into());
+	}
+
+	let name = args.name.clone().unwrap_or(file_stem);
+
+	if !valid_name(&name) {
+		return Err("Clip names cannot be empty, or contain dots or slashes".into());
+	}
+
+	let bytes: Arc<[u8]> = download(url).await?.into();
+
+	let duration = {
+		let bytes = bytes.clone();
+		let extension = extension.clone();
+
+		tokio::task::spawn_blocking(move || clip_duration(bytes, &extension))
+			.await
+			.inspect_err(|e| error!("Unable to join decoding task: {:?}", e))
+			.map_err(|_| "Unable to read the clip")?
+	};
+
+	let duration = duration
+		.inspect_err(|e| error!("Unable to decode clip: {:?}", e))
+		.map_err(|_| format!("The file is not a valid {} file", extension))?;
+
+	if duration > MAX_CLIP_DURATION {
+		return Err(format!(
+			"Clips can be at most {} seconds long",
+			MAX_CLIP_DURATION.as_secs()
+		)
+		.into());
+	}
+
+	tokio::fs::create_dir_all(guild_clip_path(guild_id))
+		.await
+		.inspect_err(|e| error!("Unable to create clip directory: {:?}", e))
+		.map_err(|_| "Unable to save the clip")?;
+
+	let dir =
+		sandboxed_join(&GUILD_CLIP_PATH, guild_id.to_string()).ok_or("Unable to save the clip")?;
+
+	// clips of either format have the same name
+	if CLIP_EXTENSIONS
+		.iter()
+		.any(|ext| sandboxed_join(&dir, format!("{}.{}", name, ext)).is_some())
+	{
+		return Err(format!("A clip named {} already exists", name).into());
+	}
+
+	let file_name = format!("{}.{}", name, extension);
+
+	let mut file = tokio::fs::File::options()
+		.write(true)
+		.create_new(true)
+		.open(dir.join(&file_name))
+		.await
+		.inspect_err(|e| error!("Unable to create clip: {:?}", e))
+		.map_err(|_| "Unable to save the clip")?;
+
+	file.write_all(&bytes)
+		.await
+		.inspect_err(|e| error!("Unable to write clip: {:?}", e))
+		.map_err(|_| "Unable to save the clip")?;
+
+	// measure it now, so it plays normalized straight away
+	let storage = state.data.read().await.clone_expect::<StorageKey>();
+	clip_loudness(&*storage, &guild_clip_path(guild_id).join(&file_name)).await;
+
+	Ok(format!(
+		"Added clip {} ({:.1} seconds)",
+		name,
+		duration.as_secs_f64()
+	)
+	.into())
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use std::path::PathBuf;
+
+	use crate::audio::{CLIP_PATH, clip_iter};
+
+	#[test]
+	fn file_names() {
+		assert_eq!(
+			file_name("https://cdn.discordapp.com/attachments/1/2/Air%20Horn.MP3?ex=1"),
+			Some(("Air Horn".to_owned(), "mp3".to_owned()))
+		);
+		assert_eq!(
+			file_name("https://cdn.discordapp.com/attachments/1/2/%E2%9C%A8.wav"),
+			Some(("✨".to_owned(), "wav".to_owned()))
+		);
+		assert_eq!(file_name("https://example.com/clip"), None);
+		assert_eq!(file_name("not a url.mp3"), None);
+	}
+
+	#[test]
+	fn attachment_urls() {
+		assert!(attachment_url("https://cdn.discordapp.com/attachments/1/2/horn.mp3").is_some());
+		assert!(attachment_url("https://media.discordapp.net/attachments/1/2/horn.mp3").is_some());
+		assert!(attachment_url("http://cdn.discordapp.com/attachments/1/2/horn.mp3").is_none());
+		assert!(attachment_url("https://cdn.discordapp.com:8080/horn.mp3").is_none());
+		assert!(attachment_url("https://example.com/horn.mp3").is_none());
+		assert!(attachment_url("https://cdn.discordapp.com.example.com/horn.mp3").is_none());
+		assert!(attachment_url("http://127.0.0.1:8080/horn.mp3").is_none());
+	}
+
+	#[test]
+	fn clip_names() {
+		assert!(valid_name("air horn"));
+		assert!(!valid_name(""));
+		assert!(!valid_name("air.horn"));
+		assert!(!valid_name("../horn"));
+		assert!(!valid_name("horns/air"));
+		assert!(!valid_name("/horn"));
+	}
+
+	#[test]
+	fn clip_durations() {
+		let path = clip_iter(None)
+			.map(PathBuf::from)
+			.find(|path| path.extension().is_some_and(|e| e == "mp3"))
+			.expect("No mp3 clips found");
+
+		let bytes = std::fs::read(CLIP_PATH.join(&path)).unwrap();
+
+		assert!(clip_duration(bytes.into(), "mp3").is_ok_and(|d| d > Duration::ZERO));
+		assert!(clip_duration(Arc::from(&b"not audio"[..]), "mp3").is_err());
+	}
+}
@@ -0,0 +1,26 @@
+use serenity::model::channel::Attachment;
+
+use crate::commands::{CustomData, run};
+use crate::util::*;
+
+use super::UploadArgs;
+
+#[poise::command(
+	category = "voice",
+	prefix_command,
+	slash_command,
+	guild_only,
+	custom_data = "CustomData::new(super::upload_help)"
+)]
+pub async fn upload(
+	ctx: Context<'_>,
+	#[description = "Mp3 or wav file to add as a clip"] file: Attachment,
+	#[description = "Name of the clip, if not the name of the file"] name: Option<String>,
+) -> CommandResult {
+	let args = UploadArgs {
+		file: file.url,
+		name,
+	};
+
+	run(&ctx, super::upload(&ctx.into(), &(&ctx).into(), &args)).await
+}
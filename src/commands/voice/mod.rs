@@ -7,7 +7,7 @@ use songbird::tracks::ControlError;
 
 use std::fs::read_dir;
 
-use crate::audio::{CLIP_PATH, PlayStyle};
+use crate::audio::{PlayStyle, clip_paths};
 use crate::commands::{BotState, Source};
 use crate::data::VoiceGuilds;
 use crate::{StorageKey, util::*};
@@ -47,15 +47,31 @@ pub enum VolumeMode {
 	Current(Option<f32>),
 }
 
+/// List the clips and sections at `path`, in the clips of the guild of
+/// `source` and the shared clips together.
 #[tracing::instrument(level = "info", ret)]
-pub async fn list(path: Option<&str>) -> Result<Response, Response> {
-	let dir = sandboxed_join(&CLIP_PATH, path.unwrap_or("")).ok_or("Invalid directory")?;
+pub async fn list(source: &Source, path: Option<&str>) -> Result<Response, Response> {
+	let dirs = clip_paths(source.guild_id)
+		.into_iter()
+		.filter_map(|dir| sandboxed_join(&dir, path.unwrap_or("")))
+		.collect_vec();
+
+	if dirs.is_empty() {
+		return Err("Invalid directory".into());
+	}
+
+	let mut entries = Vec::new();
 
-	let dir_iter = read_dir(dir)
-		.inspect_err(|reason| error!("Unable to read directory: {:?}", reason))
-		.map_err(|_| "Invalid directory")?;
+	for dir in dirs {
+		let dir_iter = read_dir(dir)
+			.inspect_err(|reason| error!("Unable to read directory: {:?}", reason))
+			.map_err(|_| "Invalid directory")?;
+
+		entries.extend(dir_iter);
+	}
 
-	let message = dir_iter
+	let message = entries
+		.into_iter()
 		.filter_map(|e| e.inspect_err(|e| error!("{:?}", e)).ok())
 		.map(|e| {
 			(
@@ -72,9 +88,11 @@ pub async fn list(path: Option<&str>) -> Result<Response, Response> {
 			(!t0.is_dir(), f0.to_lowercase()).cmp(&(!t1.is_dir(), f1.to_lowercase()))
 		})
 		.map(|(f, t)| f + if t.is_dir() { "/" } else { "" })
+		// the guild may have clips with the same name as shared clips
+		.dedup()
 		.fold("".to_owned(), |acc, s| acc + "\n" + &s);
 
-	Ok(("```\n".to_owned() + &message + "\n```").into())
+	Ok(Response::from("```\n".to_owned() + &message + "\n```").title("Clips"))
 }
 
 #[tracing::instrument(level = "info", ret, skip(state))]
@@ -111,5 +111,5 @@ pub async fn list(
 	ctx: Context<'_>,
 	#[description = "Path to list clips underneath"] path: Option<String>,
 ) -> CommandResult {
-	run(&ctx, super::list(path.as_deref())).await
+	run(&ctx, super::list(&(&ctx).into(), path.as_deref())).await
 }
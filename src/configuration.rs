@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use serenity::gateway::ActivityData;
 
 use std::net::SocketAddr;
+use std::path::PathBuf;
 
 /// Configuration struct that holds values from a file, and implements
 /// functions to read other values from the database.
@@ -20,6 +21,49 @@ pub struct Config {
 	pub prefixes: Vec<String>,
 	pub activity: Option<ActivityConfig>,
 	pub http: Option<HttpConfig>,
+	/// Directory of music files which `play` searches before youtube.
+	pub library: Option<PathBuf>,
+	/// Directory of lyrics files which `lyrics` reads instead of LRCLIB.
+	pub lyrics: Option<PathBuf>,
+	/// Url of the LRCLIB API, which only needs changing to use something
+	/// other than the real one.
+	pub lrclib_url: Option<String>,
+	/// Names of the sources `play` tries first, in order, see
+	/// [`crate::resolver::resolvers`].
+	pub sources: Option<Vec<String>>,
+	/// How queued tracks are prepared before they play, see
+	/// [`crate::prefetch`].
+	#[serde(default)]
+	pub prefetch: PrefetchConfig,
+	/// Where streamed audio is kept to play again, see
+	/// [`crate::audio_cache`].
+	pub audio_cache: Option<AudioCacheConfig>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioCacheConfig {
+	/// Directory the audio is kept in.
+	pub path: PathBuf,
+	/// Mebibytes of audio to keep at most.
+	pub size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrefetchConfig {
+	/// Number of tracks after the current one to create ahead of time.
+	pub depth: usize,
+	/// Mebibytes of streamed audio to read ahead at most, across all tracks.
+	pub memory: usize,
+}
+
+impl Default for PrefetchConfig {
+	fn default() -> Self {
+		Self {
+			depth: 2,
+			memory: 64,
+		}
+	}
 }
 
 #[derive(Debug, Serialize, Deserialize)]
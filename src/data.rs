@@ -14,12 +14,21 @@ use serenity::prelude::{RwLock, TypeMapKey};
 
 use serde::Deserialize;
 
-use songbird::tracks::{ControlError, PlayMode, TrackHandle};
+use songbird::tracks::{ControlError, LoopState, PlayMode, TrackHandle, TrackQueue};
 
 use uuid::Uuid;
 
+use std::collections::HashSet;
 use std::sync::Arc;
-
+use std::time::{Duration, SystemTime};
+
+use crate::audio::persist_queue;
+use crate::commands::BotState;
+use crate::commands::autoplay::queue_related;
+use crate::filter::{Duck, SharedFilters};
+use crate::lyrics::LyricsProvider;
+use crate::persistence::Storage;
+use crate::prefetch::{Prefetch, prefetch_queue};
 use crate::spotify::SpotifyApi;
 use crate::util::{Respond, check_msg, write_track};
 use crate::youtube::YoutubeApi;
@@ -72,11 +81,79 @@ impl TypeMapKey for VoiceUserCache {
 		Arc<DashMap<GuildId, Arc<DashMap<UserId, Option<ChannelId>, BuildHasher>>, BuildHasher>>;
 }
 
+/// Empty struct to be a [`TypeMapKey`].
+pub struct SkipVotes;
+
+/// Allow the votes to skip the current track of each guild to be tracked, as
+/// the uuid of the track and the users who voted to skip it.
+impl TypeMapKey for SkipVotes {
+	type Value = Arc<DashMap<GuildId, (Uuid, HashSet<UserId>), BuildHasher>>;
+}
+
+/// Empty struct to be a [`TypeMapKey`].
+pub struct GuildFilters;
+
+/// Allow the audio filters of each guild to be shared with its tracks, see
+/// [`crate::filter::guild_filters`].
+impl TypeMapKey for GuildFilters {
+	type Value = Arc<DashMap<GuildId, SharedFilters, BuildHasher>>;
+}
+
+/// Empty struct to be a [`TypeMapKey`].
+pub struct Crossfades;
+
+/// Allow how long each guild crossfades its queue for to be read on every
+/// tick, see [`CrossfadeHandler`]. Guilds without crossfade are left out.
+impl TypeMapKey for Crossfades {
+	type Value = Arc<DashMap<GuildId, Duration, BuildHasher>>;
+}
+
+/// Empty struct to be a [`TypeMapKey`].
+pub struct LyricsKey;
+
+/// Allow the provider `lyrics` looks songs up with to be shared, see
+/// [`crate::lyrics::provider`].
+impl TypeMapKey for LyricsKey {
+	type Value = Arc<dyn LyricsProvider + Send + Sync>;
+}
+
+/// Data stored with every track that is played, which can be retrieved from
+/// its handle with `handle.data::<TrackData>()`.
+#[derive(Clone, Debug)]
+pub struct TrackData {
+	/// Metadata of the track, if it could be fetched.
+	pub metadata: Option<AuxMetadata>,
+
+	/// User who requested the track, or `None` if the bot queued it itself.
+	pub requester: Option<UserId>,
+
+	/// When the track was added to the queue.
+	pub queued_at: SystemTime,
+
+	/// Handle to create the track before it plays, if it can be.
+	pub prefetch: Option<Prefetch>,
+}
+
+impl TrackData {
+	/// Create data for a track requested now.
+	pub fn new(metadata: Option<AuxMetadata>, requester: Option<UserId>) -> Self {
+		Self {
+			metadata,
+			requester,
+			queued_at: SystemTime::now(),
+			prefetch: None,
+		}
+	}
+}
+
 /// Collection of audios that have been queued.
 pub struct VoiceGuild {
 	audios: Vec<TrackHandle>,
 	to_remove: mpsc::UnboundedReceiver<Uuid>,
 	to_remove_sender: mpsc::UnboundedSender<Uuid>,
+
+	/// Lowers the queued tracks while any audios are playing.
+	duck: Duck,
 }
 
 impl Default for VoiceGuild {
@@ -87,12 +164,14 @@ impl Default for VoiceGuild {
 			audios: Vec::default(),
 			to_remove,
 			to_remove_sender,
+			duck: Duck::default(),
 		}
 	}
 }
 
 impl VoiceGuild {
-	/// Add an audio with the specified volume.
+	/// Add an audio with the specified volume, which lowers queued tracks to
+	/// `duck_level` until it ends.
 	///
 	/// Before the audio is added, any audios that need to be cleaned up are
 	/// first cleared with [`Self::clean_audios`].
@@ -102,15 +181,18 @@ impl VoiceGuild {
 		&mut self,
 		audio: TrackHandle,
 		volume: f32,
+		duck_level: f32,
 	) -> songbird::error::TrackResult<()> {
 		self.clean_audios();
 
 		audio.set_volume(volume)?;
+		self.duck.start(duck_level);
 		audio
 			.add_event(
 				songbird::Event::Track(songbird::TrackEvent::End),
 				TrackEventHandler {
 					done_sender: self.to_remove_sender.clone(),
+					duck: self.duck.clone(),
 				},
 			)
 			.unwrap();
@@ -129,6 +211,12 @@ impl VoiceGuild {
 		)
 	}
 
+	/// Get the duck which lowers queued tracks while audios play, to be shared
+	/// with the tracks. See [`crate::filter::apply`].
+	pub fn duck(&self) -> Duck {
+		self.duck.clone()
+	}
+
 	/// Stop all audios. See [`TrackHandle::stop`].
 	pub fn stop(&mut self) {
 		for audio in &self.audios {
@@ -185,9 +273,10 @@ pub struct TrackErrorHandler<R> {
 }
 
 /// Send events when the tracks finish, to remove them from a [`VoiceGuild`],
-/// for example.
+/// for example, and stop them ducking the queue.
 pub struct TrackEventHandler {
 	done_sender: mpsc::UnboundedSender<Uuid>,
+	duck: Duck,
 }
 
 #[async_trait]
@@ -200,6 +289,7 @@ impl songbird::EventHandler for TrackEventHandler {
 				if state.playing.is_done() {
 					debug!("Sending event to clean {}", handle.uuid());
 					self.done_sender.unbounded_send(handle.uuid()).unwrap();
+					self.duck.end();
 				}
 			}
 		};
@@ -218,12 +308,18 @@ impl<R: Respond + Send + Sync> songbird::EventHandler for TrackErrorHandler<R> {
 
 					let mut response = String::from("Error playing ");
 
-					if let Some(meta) = handle.data::<Option<AuxMetadata>>().as_ref() {
+					let data = handle.data::<TrackData>();
+
+					if let Some(meta) = &data.metadata {
 						write_track(&mut response, meta, None).unwrap();
 					} else {
 						write!(response, "unknown track").unwrap();
 					}
 
+					if let Some(requester) = data.requester {
+						write!(response, " requested by <@{}>", requester).unwrap();
+					}
+
 					write!(response, ": {}", e).unwrap();
 
 					error!("{}", response);
@@ -237,6 +333,228 @@ impl<R: Respond + Send + Sync> songbird::EventHandler for TrackErrorHandler<R> {
 	}
 }
 
+/// Save the queue of a guild whenever a track ends, so that it can be restored
+/// if the bot restarts or leaves the channel.
+pub struct QueuePersistHandler {
+	pub guild_id: GuildId,
+	pub queue: TrackQueue,
+	pub storage: Arc<dyn Storage + Send + Sync>,
+}
+
+#[async_trait]
+impl songbird::EventHandler for QueuePersistHandler {
+	async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+		if let songbird::EventContext::Track(track_events) = ctx {
+			// ended tracks may not have been removed from the queue yet
+			let ended: Vec<_> = track_events
+				.iter()
+				.map(|(_, handle)| handle.uuid())
+				.collect();
+
+			persist_queue(&*self.storage, self.guild_id, &self.queue, &ended).await;
+		}
+
+		None
+	}
+}
+
+/// Queue a related track when the last track of a guild finishes playing, if
+/// autoplay is on. See [`crate::commands::autoplay::queue_related`].
+///
+/// Registered for both [`songbird::TrackEvent::Play`] and
+/// [`songbird::TrackEvent::End`]. Ended tracks have already been removed from
+/// the queue, so tracks are remembered as queued when they start playing, and
+/// intros, outros and clips played outside of the queue are ignored.
+#[derive(Clone)]
+pub struct AutoplayHandler {
+	pub guild_id: GuildId,
+	pub state: BotState,
+	pub queue: TrackQueue,
+
+	/// Channel to announce autoplayed tracks in.
+	pub channel_id: Option<ChannelId>,
+
+	/// Queued tracks that have started playing and not yet ended.
+	pub playing: Arc<std::sync::Mutex<HashSet<Uuid>>>,
+}
+
+#[async_trait]
+impl songbird::EventHandler for AutoplayHandler {
+	async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+		let songbird::EventContext::Track(track_events) = ctx else {
+			return None;
+		};
+
+		let (started, ended): (Vec<_>, Vec<_>) = track_events
+			.iter()
+			.partition(|(state, _)| state.playing == PlayMode::Play);
+
+		let finished = {
+			let mut playing = self.playing.lock().unwrap();
+			let queued = self.queue.current_queue();
+
+			playing.extend(
+				started
+					.iter()
+					.map(|(_, handle)| handle.uuid())
+					.filter(|uuid| queued.iter().any(|handle| handle.uuid() == *uuid)),
+			);
+
+			// only queued tracks that played to the end, not ones that were stopped or skipped
+			ended
+				.iter()
+				.filter(|(_, handle)| playing.remove(&handle.uuid()))
+				.filter(|(state, _)| state.playing == PlayMode::End)
+				.map(|(_, handle)| (*handle).clone())
+				.last()
+		};
+
+		if let Some(handle) = finished {
+			let ended: Vec<_> = ended.iter().map(|(_, handle)| handle.uuid()).collect();
+
+			let last = handle
+				.data::<TrackData>()
+				.metadata
+				.clone()
+				.unwrap_or_default();
+
+			queue_related(&self.state, self.guild_id, self.channel_id, &ended, &last).await;
+		}
+
+		None
+	}
+}
+
+/// Prefetch the next tracks in the queue whenever a track starts playing, see
+/// [`crate::prefetch`].
+pub struct PrefetchHandler {
+	pub queue: TrackQueue,
+}
+
+#[async_trait]
+impl songbird::EventHandler for PrefetchHandler {
+	async fn act(&self, _ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+		prefetch_queue(&self.queue);
+
+		None
+	}
+}
+
+/// Start the next track in the queue early as the current one ends, fading
+/// between them over the guild's crossfade length.
+///
+/// Registered as a periodic global event, so that it sees the state of every
+/// track. Tracks that will loop are left to loop, rather than faded out.
+pub struct CrossfadeHandler {
+	pub guild_id: GuildId,
+	pub queue: TrackQueue,
+	pub crossfades: Arc<DashMap<GuildId, Duration, BuildHasher>>,
+	pub filters: SharedFilters,
+
+	/// Track currently being faded out, so it is only faded once.
+	pub fading: Arc<std::sync::Mutex<Option<Uuid>>>,
+}
+
+#[async_trait]
+impl songbird::EventHandler for CrossfadeHandler {
+	async fn act(&self, ctx: &songbird::EventContext<'_>) -> Option<songbird::Event> {
+		let songbird::EventContext::Track(track_events) = ctx else {
+			return None;
+		};
+
+		let crossfade = *self.crossfades.get(&self.guild_id)?;
+
+		let queue = self.queue.current_queue();
+		let (current, next) = (queue.first()?, queue.get(1)?);
+
+		let state = |handle: &TrackHandle| {
+			track_events
+				.iter()
+				.find(|(_, h)| h.uuid() == handle.uuid())
+				.map(|(state, _)| *state)
+		};
+
+		let (current_state, next_state) = (state(current)?, state(next)?);
+
+		if current_state.playing != PlayMode::Play || current_state.loops != LoopState::Finite(0) {
+			return None;
+		}
+
+		let duration = current.data::<TrackData>().metadata.as_ref()?.duration?;
+
+		// positions are in the filtered track, which may play faster
+		let rate = self
+			.filters
+			.read()
+			.map(|filters| filters.resample_ratio())
+			.unwrap_or(1.0);
+
+		let remaining = duration
+			.div_f64(rate)
+			.saturating_sub(current_state.position);
+
+		if remaining > crossfade {
+			return None;
+		}
+
+		{
+			let mut fading = self.fading.lock().unwrap_or_else(|e| e.into_inner());
+
+			if *fading == Some(current.uuid()) {
+				return None;
+			}
+
+			*fading = Some(current.uuid());
+		}
+
+		debug!("Crossfading into the next track over {:?}", remaining);
+
+		tokio::spawn(crossfade_tracks(
+			current.clone(),
+			next.clone(),
+			current_state.volume,
+			next_state.volume,
+			remaining,
+		));
+
+		None
+	}
+}
+
+/// Time between each change in volume while crossfading.
+const FADE_STEP: Duration = Duration::from_millis(50);
+
+/// Start `next` and fade it in to volume `to`, while fading `current` out from
+/// volume `from`, over `length`.
+async fn crossfade_tracks(
+	current: TrackHandle,
+	next: TrackHandle,
+	from: f32,
+	to: f32,
+	length: Duration,
+) {
+	if let Err(e) = next.set_volume(0.0).and_then(|_| next.play()) {
+		error!("Unable to start crossfade: {:?}", e);
+		return;
+	}
+
+	let steps = (length.as_millis() / FADE_STEP.as_millis()).max(1);
+
+	for step in 1..=steps {
+		tokio::time::sleep(FADE_STEP).await;
+
+		// equal power curves keep the loudness steady through the fade
+		let t = step as f32 / steps as f32 * std::f32::consts::FRAC_PI_2;
+
+		// the current track may end or be skipped first
+		let _ = current.set_volume(from * t.cos());
+
+		if next.set_volume(to * t.sin()).is_err() {
+			return;
+		}
+	}
+}
+
 /// Allow storing a [`VoiceGuild`] for each guild.
 pub struct VoiceGuilds;
 
@@ -0,0 +1,928 @@
+//! Audio filters, such as bass boost and nightcore, applied to queued tracks.
+//!
+//! Tracks are wrapped in [`ComposeWithFilters`] when they are queued, which
+//! decodes the audio created by the wrapped [`Compose`], usually a
+//! [`crate::audio::ComposeWithMetadata`], and passes it through the filters of
+//! the guild. The filters are shared with every track of the guild, so a change
+//! is heard straight away, even in the current track. While clips play over
+//! the tracks, a [`Duck`] shared the same way lowers them.
+//!
+//! The filtered audio is handed back to songbird as raw `f32` samples, in the
+//! format read by [`songbird::input::codecs::RawReader`].
+
+use async_trait::async_trait;
+
+use serenity::model::id::GuildId;
+
+use songbird::input::codecs::{get_codec_registry, get_probe};
+use songbird::input::{AudioStream, AudioStreamError, AuxMetadata, Compose, Input};
+
+use symphonia::core::audio::{SampleBuffer, SignalSpec};
+use symphonia::core::codecs::{CODEC_TYPE_NULL, Decoder, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::{MediaSource, MediaSourceStream};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::units::Time;
+
+use tracing::{debug, error};
+
+use std::f32::consts::PI;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::StorageKey;
+use crate::commands::BotState;
+use crate::data::{GuildFilters, VoiceGuilds};
+use crate::loudness::Normalizer;
+use crate::persistence::Storage;
+use crate::util::GetExpect;
+
+/// Magic string which starts the raw format read by songbird.
+const RAW_MAGIC: &[u8; 8] = b"SbirdRaw";
+
+/// Length of the raw format header, which is the magic string followed by the
+/// sample rate and channel count as little endian `u32`s.
+const RAW_HEADER_LEN: u64 = 16;
+
+/// Bytes in a single raw sample.
+const SAMPLE_LEN: u64 = size_of::<f32>() as u64;
+
+/// Frequency below which bass boost raises the volume.
+const BASS_FREQUENCY: f32 = 100.0;
+
+/// Length in frames of the window used to shift pitch, which is about 40ms at
+/// common sample rates. Longer windows smear transients, while shorter ones
+/// sound rough.
+const PITCH_WINDOW: usize = 2048;
+
+/// Change in rate at which nightcore plays tracks.
+pub const NIGHTCORE_RATE: f32 = 1.25;
+
+/// Volume tracks are lowered to while clips play, unless a guild sets its own.
+pub const DUCK_LEVEL: f32 = 0.4;
+
+/// Time constant in seconds with which ducking follows a change, so tracks dip
+/// and recover smoothly instead of jumping.
+const DUCK_FADE: f32 = 0.1;
+
+/// Settings for every filter, where the default leaves audio unchanged.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Filters {
+	/// Gain in decibels added to the bass.
+	pub bass_boost: f32,
+
+	/// Playback rate, which changes speed and pitch together, like playing a
+	/// record faster.
+	pub rate: f32,
+
+	/// Playback speed, without changing pitch.
+	pub speed: f32,
+
+	/// Change in pitch in semitones, without changing speed.
+	pub pitch: f32,
+
+	/// Whether to even out loudness, so every track sounds about as loud.
+	pub normalize: bool,
+}
+
+impl Default for Filters {
+	fn default() -> Self {
+		Self {
+			bass_boost: 0.0,
+			rate: 1.0,
+			speed: 1.0,
+			pitch: 0.0,
+			normalize: false,
+		}
+	}
+}
+
+impl fmt::Display for Filters {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		if *self == Self::default() {
+			return write!(f, "No filters");
+		}
+
+		let mut filters = Vec::new();
+
+		if self.bass_boost != 0.0 {
+			filters.push(format!("Bass boost: +{} dB", self.bass_boost));
+		}
+
+		if self.rate != 1.0 {
+			filters.push(format!("Rate: {}x", self.rate));
+		}
+
+		if self.speed != 1.0 {
+			filters.push(format!("Speed: {}x", self.speed));
+		}
+
+		if self.pitch != 0.0 {
+			filters.push(format!("Pitch: {:+} semitones", self.pitch));
+		}
+
+		if self.normalize {
+			filters.push(String::from("Loudness normalization: on"));
+		}
+
+		write!(f, "{}", filters.join("\n"))
+	}
+}
+
+impl Filters {
+	/// Factor to resample by, which sets how much faster the track plays.
+	pub fn resample_ratio(&self) -> f64 {
+		(self.rate * self.speed) as f64
+	}
+
+	/// Factor to shift the pitch of resampled audio by, undoing the pitch
+	/// change from speed.
+	fn pitch_ratio(&self) -> f32 {
+		2f32.powf(self.pitch / 12.0) / self.speed
+	}
+}
+
+/// Filters shared by every track of a guild.
+pub type SharedFilters = Arc<RwLock<Filters>>;
+
+/// Gain shared by every queued track of a guild, which lowers them while clips
+/// play over them. Clones share the same gain.
+#[derive(Clone, Debug, Default)]
+pub struct Duck {
+	/// Clips currently playing, and the level they lower tracks to.
+	state: Arc<Mutex<(usize, f32)>>,
+}
+
+impl Duck {
+	/// Lower tracks to `level` until the clip which started ducking calls
+	/// [`Self::end`].
+	pub fn start(&self, level: f32) {
+		let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+		*state = (state.0 + 1, level);
+	}
+
+	/// Stop ducking for a clip, restoring tracks once no clips are left.
+	pub fn end(&self) {
+		let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+		state.0 = state.0.saturating_sub(1);
+	}
+
+	/// Gain tracks should currently play at.
+	fn gain(&self) -> f32 {
+		match *self.state.lock().unwrap_or_else(|e| e.into_inner()) {
+			(0, _) => 1.0,
+			(_, level) => level,
+		}
+	}
+}
+
+/// Get the duck shared by the tracks of a guild with the clips played over
+/// them.
+pub async fn guild_duck(state: &BotState, guild_id: GuildId) -> Duck {
+	let voice_guild = state
+		.data
+		.read()
+		.await
+		.clone_expect::<VoiceGuilds>()
+		.entry(guild_id)
+		.or_default()
+		.clone();
+
+	voice_guild.read().await.duck()
+}
+
+/// Get the level a guild lowers its tracks to while clips play.
+pub async fn duck_level(storage: &(dyn Storage + Send + Sync), guild_id: GuildId) -> f32 {
+	storage
+		.get_duck_level(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to get duck level: {:?}", e))
+		.ok()
+		.flatten()
+		.unwrap_or(DUCK_LEVEL)
+}
+
+/// Get the filters shared by the tracks of a guild, loading them from storage
+/// the first time.
+pub async fn guild_filters(state: &BotState, guild_id: GuildId) -> SharedFilters {
+	let (guild_filters, storage) = {
+		let lock = state.data.read().await;
+
+		(
+			lock.clone_expect::<GuildFilters>(),
+			lock.clone_expect::<StorageKey>(),
+		)
+	};
+
+	if let Some(filters) = guild_filters.get(&guild_id) {
+		return filters.clone();
+	}
+
+	let filters = storage
+		.get_filters(guild_id)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve filters: {:?}", e))
+		.ok()
+		.flatten()
+		.unwrap_or_default();
+
+	guild_filters
+		.entry(guild_id)
+		.or_insert_with(|| Arc::new(RwLock::new(filters)))
+		.clone()
+}
+
+/// Pass the audio of `input` through `filters`.
+///
+/// Only inputs which have not been created yet can be filtered, so any other
+/// input is returned unchanged.
+pub fn apply(input: Input, filters: SharedFilters, duck: Duck) -> Input {
+	match input {
+		Input::Lazy(compose) => Input::Lazy(Box::new(ComposeWithFilters {
+			compose,
+			filters,
+			duck: Some(duck),
+			loudness: None,
+		})),
+		input => input,
+	}
+}
+
+/// Normalize the loudness of a clip, using the `loudness` it was measured to
+/// have, or following its loudness as it plays if it was not measured.
+///
+/// Clips are never filtered otherwise, so only an input which has not been
+/// created yet is changed.
+pub fn normalize_clip(input: Input, loudness: Option<f32>) -> Input {
+	let filters = Filters {
+		normalize: true,
+		..Default::default()
+	};
+
+	match input {
+		Input::Lazy(compose) => Input::Lazy(Box::new(ComposeWithFilters {
+			compose,
+			filters: Arc::new(RwLock::new(filters)),
+			duck: None,
+			loudness,
+		})),
+		input => input,
+	}
+}
+
+/// Wraps a [`Compose`] so that the audio it creates passes through filters
+/// which can change while it plays.
+pub struct ComposeWithFilters {
+	compose: Box<dyn Compose>,
+	filters: SharedFilters,
+
+	/// Gain lowering the audio while clips play, if it is ducked.
+	duck: Option<Duck>,
+
+	/// Loudness of the audio measured beforehand, if it was.
+	loudness: Option<f32>,
+}
+
+#[async_trait]
+impl Compose for ComposeWithFilters {
+	fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+		let stream = self.compose.create()?;
+
+		FilterSource::new(
+			stream,
+			self.filters.clone(),
+			self.duck.clone(),
+			self.loudness,
+		)
+		.map(FilterSource::into_stream)
+	}
+
+	async fn create_async(
+		&mut self,
+	) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+		let stream = self.compose.create_async().await?;
+		let filters = self.filters.clone();
+		let duck = self.duck.clone();
+		let loudness = self.loudness;
+
+		// probing reads from the stream, which blocks
+		tokio::task::spawn_blocking(move || FilterSource::new(stream, filters, duck, loudness))
+			.await
+			.map_err(|e| AudioStreamError::Fail(Box::new(e)))?
+			.map(FilterSource::into_stream)
+	}
+
+	fn should_create_async(&self) -> bool {
+		self.compose.should_create_async()
+	}
+
+	async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+		self.compose.aux_metadata().await
+	}
+}
+
+/// Second order IIR filter, which the other filters are built from.
+#[derive(Clone, Copy, Default)]
+pub struct Biquad {
+	b: [f32; 3],
+	a: [f32; 2],
+	x: [f32; 2],
+	y: [f32; 2],
+}
+
+impl Biquad {
+	/// Filter with feedforward coefficients `b` and feedback coefficients `a`,
+	/// both already divided by `a0`.
+	pub fn new(b: [f32; 3], a: [f32; 2]) -> Self {
+		Self {
+			b,
+			a,
+			..Default::default()
+		}
+	}
+
+	/// Low shelf filter, which changes the volume below [`BASS_FREQUENCY`] by
+	/// `gain` decibels.
+	///
+	/// See the RBJ audio EQ cookbook for how the coefficients are found.
+	fn low_shelf(sample_rate: u32, gain: f32) -> Self {
+		let amp = 10f32.powf(gain / 40.0);
+		let w0 = 2.0 * PI * BASS_FREQUENCY / sample_rate as f32;
+		let cos = w0.cos();
+		// shelf slope of 1
+		let alpha = w0.sin() / 2.0 * 2f32.sqrt();
+		let sqrt_alpha = 2.0 * amp.sqrt() * alpha;
+
+		let a0 = (amp + 1.0) + (amp - 1.0) * cos + sqrt_alpha;
+
+		Self::new(
+			[
+				amp * ((amp + 1.0) - (amp - 1.0) * cos + sqrt_alpha) / a0,
+				2.0 * amp * ((amp - 1.0) - (amp + 1.0) * cos) / a0,
+				amp * ((amp + 1.0) - (amp - 1.0) * cos - sqrt_alpha) / a0,
+			],
+			[
+				-2.0 * ((amp - 1.0) + (amp + 1.0) * cos) / a0,
+				((amp + 1.0) + (amp - 1.0) * cos - sqrt_alpha) / a0,
+			],
+		)
+	}
+
+	/// Change the coefficients to those of `other`, keeping the samples already
+	/// seen so the change is smooth.
+	fn retune(&mut self, other: Self) {
+		self.b = other.b;
+		self.a = other.a;
+	}
+
+	pub fn process(&mut self, x: f32) -> f32 {
+		let y = self.b[0] * x + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+			- self.a[0] * self.y[0]
+			- self.a[1] * self.y[1];
+
+		self.x = [x, self.x[0]];
+		self.y = [y, self.y[0]];
+
+		y
+	}
+}
+
+/// Shifts pitch without changing speed, by reading from a delay line at a
+/// different rate than it is written to.
+///
+/// Two read heads half a window apart are crossfaded, so that each can jump
+/// back across the window while the other is heard.
+struct PitchShifter {
+	buffer: Vec<f32>,
+	write: usize,
+	phase: f32,
+}
+
+impl Default for PitchShifter {
+	fn default() -> Self {
+		Self {
+			buffer: vec![0.0; PITCH_WINDOW * 2],
+			write: 0,
+			phase: 0.0,
+		}
+	}
+}
+
+impl PitchShifter {
+	/// Read `delay` samples behind the last sample written.
+	fn read(&self, delay: f32) -> f32 {
+		let len = self.buffer.len();
+		let position = (self.write + len) as f32 - 1.0 - delay;
+		let i = position.floor() as usize;
+		let t = position.fract();
+
+		self.buffer[i % len] * (1.0 - t) + self.buffer[(i + 1) % len] * t
+	}
+
+	fn push(&mut self, x: f32) {
+		self.buffer[self.write] = x;
+		self.write = (self.write + 1) % self.buffer.len();
+	}
+
+	fn process(&mut self, x: f32, ratio: f32) -> f32 {
+		self.push(x);
+
+		let window = PITCH_WINDOW as f32;
+		let other = (self.phase + 0.5).fract();
+
+		// sin² windows half a cycle apart always add up to 1
+		let y = self.read(self.phase * window) * (PI * self.phase).sin().powi(2)
+			+ self.read(other * window) * (PI * other).sin().powi(2);
+
+		self.phase = (self.phase + (1.0 - ratio) / window).rem_euclid(1.0);
+
+		y
+	}
+}
+
+/// State of the filters for a single stream of interleaved samples.
+struct Dsp {
+	sample_rate: u32,
+	channels: usize,
+
+	/// Gain the bass filters are set to.
+	bass_boost: f32,
+	bass: Vec<Biquad>,
+
+	/// Loudness of the track measured beforehand, if it was.
+	loudness: Option<f32>,
+	normalizer: Normalizer,
+
+	/// Samples waiting to be resampled, and how far through them resampling is
+	/// in frames.
+	pending: Vec<f32>,
+	position: f64,
+
+	pitch: Vec<PitchShifter>,
+
+	/// Gain from ducking, which moves towards the gain it is ducked to.
+	duck: f32,
+}
+
+impl Dsp {
+	fn new(sample_rate: u32, channels: usize, loudness: Option<f32>) -> Self {
+		Self {
+			sample_rate,
+			channels,
+			bass_boost: 0.0,
+			bass: vec![Biquad::default(); channels],
+			loudness,
+			normalizer: Normalizer::new(sample_rate, channels, loudness),
+			pending: Vec::new(),
+			position: 0.0,
+			pitch: (0..channels).map(|_| PitchShifter::default()).collect(),
+			duck: 1.0,
+		}
+	}
+
+	/// Forget all audio, such as after seeking.
+	fn reset(&mut self) {
+		*self = Self {
+			duck: self.duck,
+			..Self::new(self.sample_rate, self.channels, self.loudness)
+		};
+	}
+
+	/// Filter interleaved `samples`, adding the result to `out`.
+	///
+	/// Resampling needs the frame after each one it reads, so the last frame is
+	/// kept back until more samples arrive.
+	fn process(&mut self, samples: &mut [f32], filters: &Filters, duck: f32, out: &mut Vec<f32>) {
+		if filters.normalize {
+			self.normalizer.process(samples);
+		}
+
+		if filters.bass_boost != self.bass_boost {
+			self.bass_boost = filters.bass_boost;
+
+			let shelf = Biquad::low_shelf(self.sample_rate, self.bass_boost);
+
+			for bass in &mut self.bass {
+				bass.retune(shelf);
+			}
+		}
+
+		if self.bass_boost == 0.0 {
+			self.pending.extend_from_slice(samples);
+		} else {
+			let bass = &mut self.bass;
+
+			self.pending.extend(
+				samples
+					.iter()
+					.enumerate()
+					.map(|(i, x)| bass[i % self.channels].process(*x)),
+			);
+		}
+
+		let ratio = filters.resample_ratio();
+		let pitch_ratio = filters.pitch_ratio();
+		let shift = (pitch_ratio - 1.0).abs() > 1e-4;
+
+		let frames = self.pending.len() / self.channels;
+
+		let duck_step = 1.0 - (-1.0 / (DUCK_FADE * self.sample_rate as f32)).exp();
+
+		while self.position + 1.0 < frames as f64 {
+			let i = self.position as usize;
+			let t = self.position.fract() as f32;
+
+			self.duck += (duck - self.duck) * duck_step;
+
+			for c in 0..self.channels {
+				let a = self.pending[i * self.channels + c];
+				let b = self.pending[(i + 1) * self.channels + c];
+				let x = a + (b - a) * t;
+
+				let y = if shift {
+					self.pitch[c].process(x, pitch_ratio)
+				} else {
+					// keep the delay line full, for when the pitch changes
+					self.pitch[c].push(x);
+					x
+				};
+
+				out.push((y * self.duck).clamp(-1.0, 1.0));
+			}
+
+			self.position += ratio;
+		}
+
+		let used = (self.position as usize).min(frames.saturating_sub(1));
+
+		self.pending.drain(..used * self.channels);
+		self.position -= used as f64;
+	}
+}
+
+/// Decodes the packets of the first track in a stream.
+pub struct PacketDecoder {
+	format: Box<dyn FormatReader>,
+	decoder: Box<dyn Decoder>,
+	track_id: u32,
+}
+
+impl PacketDecoder {
+	/// Probe `stream` for a track that can be decoded.
+	pub fn new(stream: AudioStream<Box<dyn MediaSource>>) -> Result<Self, AudioStreamError> {
+		let fail = |e: SymphoniaError| AudioStreamError::Fail(Box::new(e));
+
+		let mss = MediaSourceStream::new(stream.input, Default::default());
+
+		let probed = get_probe()
+			.format(
+				&stream.hint.unwrap_or_default(),
+				mss,
+				&FormatOptions::default(),
+				&MetadataOptions::default(),
+			)
+			.map_err(fail)?;
+
+		let track = probed
+			.format
+			.tracks()
+			.iter()
+			.find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+			.ok_or(AudioStreamError::Unsupported)?;
+
+		let decoder = get_codec_registry()
+			.make(&track.codec_params, &DecoderOptions::default())
+			.map_err(fail)?;
+
+		Ok(Self {
+			track_id: track.id,
+			format: probed.format,
+			decoder,
+		})
+	}
+
+	/// Decode the next packet of the track as interleaved samples, or `None`
+	/// once there are none left.
+	pub fn next(&mut self) -> Result<Option<(SignalSpec, SampleBuffer<f32>)>, SymphoniaError> {
+		loop {
+			let packet = match self.format.next_packet() {
+				Ok(packet) => packet,
+				Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+					return Ok(None);
+				}
+				Err(SymphoniaError::ResetRequired) => {
+					debug!("Track changed while decoding, ending stream");
+					return Ok(None);
+				}
+				Err(e) => return Err(e),
+			};
+
+			if packet.track_id() != self.track_id {
+				continue;
+			}
+
+			let decoded = match self.decoder.decode(&packet) {
+				Ok(decoded) => decoded,
+				// a bad packet can be skipped
+				Err(SymphoniaError::DecodeError(e)) => {
+					debug!("Skipping packet: {}", e);
+					continue;
+				}
+				Err(e) => return Err(e),
+			};
+
+			let spec = *decoded.spec();
+
+			let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+			samples.copy_interleaved_ref(decoded);
+
+			return Ok(Some((spec, samples)));
+		}
+	}
+
+	/// Seek to `seconds` into the track.
+	fn seek(&mut self, seconds: f64) -> Result<(), SymphoniaError> {
+		self.format.seek(
+			SeekMode::Accurate,
+			SeekTo::Time {
+				time: Time::from(seconds),
+				track_id: Some(self.track_id),
+			},
+		)?;
+
+		self.decoder.reset();
+
+		Ok(())
+	}
+}
+
+/// Audio decoded from a stream and passed through filters, as raw samples
+/// preceded by a header.
+pub struct FilterSource {
+	packets: PacketDecoder,
+	seekable: bool,
+
+	filters: SharedFilters,
+	duck: Option<Duck>,
+	dsp: Dsp,
+
+	header: [u8; RAW_HEADER_LEN as usize],
+
+	/// Filtered bytes which have not been read yet.
+	output: Vec<u8>,
+	output_read: usize,
+
+	/// Bytes read so far, including the header.
+	position: u64,
+
+	finished: bool,
+}
+
+impl FilterSource {
+	/// Probe and start decoding `stream`, which is lowered by `duck` while clips
+	/// play, and has the given `loudness` if it was measured beforehand.
+	///
+	/// The first audio is decoded straight away, since the sample rate and
+	/// channels it has are needed for the header.
+	pub fn new(
+		stream: AudioStream<Box<dyn MediaSource>>,
+		filters: SharedFilters,
+		duck: Option<Duck>,
+		loudness: Option<f32>,
+	) -> Result<Self, AudioStreamError> {
+		let seekable = stream.input.is_seekable();
+
+		let mut source = Self {
+			packets: PacketDecoder::new(stream)?,
+			seekable,
+			filters,
+			duck,
+			dsp: Dsp::new(0, 0, loudness),
+			header: [0; RAW_HEADER_LEN as usize],
+			output: Vec::new(),
+			output_read: 0,
+			position: 0,
+			finished: false,
+		};
+
+		source
+			.decode()
+			.map_err(|e| AudioStreamError::Fail(Box::new(e)))?;
+
+		if source.dsp.channels == 0 {
+			return Err(AudioStreamError::Unsupported);
+		}
+
+		source.header[..8].copy_from_slice(RAW_MAGIC);
+		source.header[8..12].copy_from_slice(&source.dsp.sample_rate.to_le_bytes());
+		source.header[12..].copy_from_slice(&(source.dsp.channels as u32).to_le_bytes());
+
+		Ok(source)
+	}
+
+	fn into_stream(self) -> AudioStream<Box<dyn MediaSource>> {
+		AudioStream {
+			input: Box::new(self),
+			// the header is recognized without a hint
+			hint: None,
+		}
+	}
+
+	/// Decode and filter the next packet of the track, or mark the stream as
+	/// finished if there are none left.
+	fn decode(&mut self) -> Result<(), SymphoniaError> {
+		let Some((spec, mut samples)) = self.packets.next()? else {
+			self.finished = true;
+			return Ok(());
+		};
+
+		if self.dsp.channels == 0 {
+			self.dsp = Dsp::new(spec.rate, spec.channels.count(), self.dsp.loudness);
+		}
+
+		let filters = *self.filters.read().unwrap_or_else(|e| e.into_inner());
+		let duck = self.duck.as_ref().map_or(1.0, Duck::gain);
+
+		let mut filtered = Vec::new();
+		self.dsp
+			.process(samples.samples_mut(), &filters, duck, &mut filtered);
+
+		self.output.drain(..self.output_read);
+		self.output_read = 0;
+		self.output
+			.extend(filtered.into_iter().flat_map(|s| s.to_le_bytes()));
+
+		Ok(())
+	}
+
+	fn frame_len(&self) -> u64 {
+		SAMPLE_LEN * self.dsp.channels as u64
+	}
+}
+
+impl Read for FilterSource {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		if self.position < RAW_HEADER_LEN {
+			let header = &self.header[self.position as usize..];
+			let len = header.len().min(buf.len());
+
+			buf[..len].copy_from_slice(&header[..len]);
+			self.position += len as u64;
+
+			return Ok(len);
+		}
+
+		while self.output_read == self.output.len() {
+			if self.finished {
+				return Ok(0);
+			}
+
+			self.decode().map_err(io::Error::other)?;
+		}
+
+		let output = &self.output[self.output_read..];
+		let len = output.len().min(buf.len());
+
+		buf[..len].copy_from_slice(&output[..len]);
+		self.output_read += len;
+		self.position += len as u64;
+
+		Ok(len)
+	}
+}
+
+impl Seek for FilterSource {
+	/// Seeking forward reads through the filtered audio, as how far it goes
+	/// depends on how the filters changed along the way. Seeking back seeks the
+	/// decoded track, assuming the current filters were used from the start.
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let SeekFrom::Start(target) = pos else {
+			return Err(io::ErrorKind::Unsupported.into());
+		};
+
+		if target >= self.position {
+			let mut discard = [0; 4096];
+
+			while self.position < target {
+				let len = discard.len().min((target - self.position) as usize);
+
+				if self.read(&mut discard[..len])? == 0 {
+					break;
+				}
+			}
+
+			return Ok(self.position);
+		}
+
+		if !self.seekable {
+			return Err(io::ErrorKind::Unsupported.into());
+		}
+
+		let frame = target.saturating_sub(RAW_HEADER_LEN) / self.frame_len();
+		let filters = *self.filters.read().unwrap_or_else(|e| e.into_inner());
+		let seconds = frame as f64 / self.dsp.sample_rate as f64 * filters.resample_ratio();
+
+		self.packets.seek(seconds).map_err(io::Error::other)?;
+
+		self.dsp.reset();
+		self.output.clear();
+		self.output_read = 0;
+		self.finished = false;
+		self.position = RAW_HEADER_LEN + frame * self.frame_len();
+
+		Ok(self.position)
+	}
+}
+
+impl MediaSource for FilterSource {
+	fn is_seekable(&self) -> bool {
+		self.seekable
+	}
+
+	fn byte_len(&self) -> Option<u64> {
+		None
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use songbird::input::File;
+
+	use crate::audio::CLIP_PATH;
+
+	/// read every frame of a clip through `filters`
+	async fn filtered_frames(filters: Filters) -> usize {
+		let path = crate::audio::clip_iter(None)
+			.map(|clip| CLIP_PATH.join(clip))
+			.find(|path| path.extension().is_some_and(|e| e == "mp3"))
+			.expect("No mp3 clips found");
+
+		let stream = File::new(path)
+			.create_async()
+			.await
+			.expect("Unable to open clip");
+		let mut source = FilterSource::new(stream, Arc::new(RwLock::new(filters)), None, None)
+			.expect("Unable to filter");
+
+		let mut bytes = Vec::new();
+		source.read_to_end(&mut bytes).expect("Unable to read");
+
+		assert_eq!(&bytes[..8], RAW_MAGIC);
+
+		(bytes.len() - RAW_HEADER_LEN as usize) / source.frame_len() as usize
+	}
+
+	#[tokio::test]
+	async fn filter_rate() {
+		let frames = filtered_frames(Filters::default()).await as f64;
+
+		let nightcore = filtered_frames(Filters {
+			rate: 2.0,
+			..Default::default()
+		})
+		.await as f64;
+
+		let slow = filtered_frames(Filters {
+			speed: 0.5,
+			pitch: 12.0,
+			bass_boost: 6.0,
+			..Default::default()
+		})
+		.await as f64;
+
+		assert!((nightcore / frames - 0.5).abs() < 0.01);
+		assert!((slow / frames - 2.0).abs() < 0.01);
+	}
+
+	#[test]
+	fn duck_restores_after_clips() {
+		let duck = Duck::default();
+		let mut dsp = Dsp::new(48000, 1, None);
+
+		// a second of a constant signal, returning its last sample
+		let last = |dsp: &mut Dsp, duck: &Duck| {
+			let mut out = Vec::new();
+			dsp.process(
+				&mut [0.5; 48000],
+				&Filters::default(),
+				duck.gain(),
+				&mut out,
+			);
+			*out.last().unwrap()
+		};
+
+		duck.start(0.25);
+		duck.start(0.25);
+		assert!((last(&mut dsp, &duck) - 0.125).abs() < 1e-3);
+
+		duck.end();
+		assert!((last(&mut dsp, &duck) - 0.125).abs() < 1e-3);
+
+		duck.end();
+		assert!((last(&mut dsp, &duck) - 0.5).abs() < 1e-3);
+	}
+}
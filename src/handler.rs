@@ -11,7 +11,9 @@ use tracing::{error, info};
 
 use serenity::async_trait;
 use serenity::client::Context as SerenityContext;
+use serenity::model::application::Interaction;
 use serenity::model::gateway::Ready;
+use serenity::model::guild::Guild;
 use serenity::model::prelude::UserId;
 use serenity::model::user::OnlineStatus;
 use serenity::model::voice::VoiceState;
@@ -26,6 +28,8 @@ use crate::StorageKey;
 use crate::Keys;
 use crate::audio::{clip_iter, get_inputs};
 use crate::data::{VoiceGuild, VoiceGuilds, VoiceUserCache};
+use crate::filter;
+use crate::loudness;
 use crate::util::*;
 
 /// Handler that handeles serenity events for playing intros and outros, and
@@ -46,7 +50,7 @@ impl Handler {
 		self.random_audio_cache
 			.entry(seed)
 			.or_insert_with(|| {
-				clip_iter()
+				clip_iter(None)
 					.choose(&mut StdRng::seed_from_u64(seed))
 					.expect("No clips found")
 					.to_string_lossy()
@@ -81,6 +85,32 @@ impl SerenityEventHandler for Handler {
 		ctx.set_presence(activity_data, OnlineStatus::Online);
 	}
 
+	/// Fill the voice user cache with the users already in voice channels when
+	/// the bot starts, since their voice states won't be updated until they
+	/// next move.
+	async fn guild_create(&self, ctx: SerenityContext, guild: Guild, _is_new: Option<bool>) {
+		let cache_guild = ctx
+			.data
+			.read()
+			.await
+			.clone_expect::<VoiceUserCache>()
+			.entry(guild.id)
+			.or_default()
+			.clone();
+
+		for (user_id, state) in guild.voice_states {
+			cache_guild.insert(user_id, state.channel_id);
+		}
+	}
+
+	/// Change the page of paged messages sent outside of a command, see
+	/// [`change_page`].
+	async fn interaction_create(&self, ctx: SerenityContext, interaction: Interaction) {
+		if let Interaction::Component(press) = interaction {
+			change_page(&ctx, &press).await;
+		}
+	}
+
 	async fn voice_state_update(
 		&self,
 		ctx: SerenityContext,
@@ -100,10 +130,8 @@ impl SerenityEventHandler for Handler {
 
 				let bot_id = ctx.cache.current_user().id;
 
-				// update cache if the user is the bot
-				if new_state.user_id == bot_id {
-					cache_guild.insert(bot_id, new_state.channel_id);
-				}
+				// keep track of every user, so that listeners can be counted
+				cache_guild.insert(new_state.user_id, new_state.channel_id);
 
 				// get the bot's channel
 				let bot_channel = cache_guild.get(&bot_id).and_then(|r| *r.value());
@@ -162,7 +190,7 @@ impl SerenityEventHandler for Handler {
 					}
 				};
 
-				let (songbird, voice_guild_arc, keys, volume) = {
+				let (songbird, voice_guild_arc, keys, volume, duck_level) = {
 					let lock = ctx.data.read().await;
 
 					let keys = lock.clone_expect::<Keys>();
@@ -184,13 +212,15 @@ impl SerenityEventHandler for Handler {
 						.flatten()
 						.unwrap_or(0.5);
 
-					(songbird, voice_guild_arc, keys, volume)
+					let duck_level = filter::duck_level(&**storage, guild_id).await;
+
+					(songbird, voice_guild_arc, keys, volume, duck_level)
 				};
 
 				let mut voice_guild = voice_guild_arc.write().await;
 
 				if let Some(call) = songbird.get(guild_id) {
-					match get_inputs(keys, clip.as_ref(), false, None).await {
+					match get_inputs(keys, Some(guild_id), clip.as_ref(), false, None).await {
 						Ok(mut info) => {
 							let respond = new_state
 								.user_id
@@ -204,9 +234,17 @@ impl SerenityEventHandler for Handler {
 								IOClip::Outro => "outro",
 							};
 
-							let audio = call.lock().await.play_input(info.inputs.nth(0).unwrap());
+							let input = loudness::prepare_clip(
+								&(&ctx).into(),
+								guild_id,
+								info.clip.as_deref(),
+								info.inputs.nth(0).unwrap(),
+							)
+							.await;
+
+							let audio = call.lock().await.play_input(input);
 
-							match voice_guild.add_audio(audio.clone(), volume) {
+							match voice_guild.add_audio(audio.clone(), volume, duck_level) {
 								Err(e) => {
 									check_msg(
 										respond
@@ -355,7 +393,7 @@ pub async fn on_error(err: FrameworkError<'_>) {
 		}
 		E::UnknownCommand { ctx, msg, .. } => {
 			check_msg(
-				(*ctx, msg.channel_id)
+				(ctx.http.clone(), msg.channel_id)
 					.respond_err(
 						"Unrecognized command. Use `help` to get a list of commands.".into(),
 					)
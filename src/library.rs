@@ -0,0 +1,240 @@
+//! Index a local directory of music files so that they can be searched and
+//! played like any other source.
+//!
+//! The directory is set by [`crate::configuration::Config::library`], and is
+//! indexed once using symphonia to read the tags of each file. Searches use the
+//! same levenshtein ranking as clips, see [`crate::audio::levenshtein_rank`].
+
+use itertools::Itertools;
+
+use songbird::input::{AuxMetadata, File};
+
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+
+use tracing::{debug, error, info};
+
+use walkdir::WalkDir;
+
+use tokio::sync::OnceCell;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::CONFIG;
+use crate::audio::{ComposeWithMetadata, levenshtein_rank};
+
+/// Every track in the configured library, see [`tracks`].
+static LIBRARY: OnceCell<Vec<LibraryTrack>> = OnceCell::const_new();
+
+/// Every track in the configured library, which is empty if no library is
+/// configured.
+///
+/// Indexing reads every file, so it is done on a blocking thread the first
+/// time, and should be started early rather than on first use. Callers while
+/// it runs wait for it without blocking.
+pub async fn tracks() -> &'static [LibraryTrack] {
+	LIBRARY
+		.get_or_init(|| async {
+			match CONFIG.library.clone() {
+				Some(dir) => tokio::task::spawn_blocking(move || index(&dir))
+					.await
+					.inspect_err(|e| error!("Unable to join indexing task: {:?}", e))
+					.unwrap_or_default(),
+				None => Vec::new(),
+			}
+		})
+		.await
+}
+
+/// A single audio file in the library, with the tags read from it.
+#[derive(Clone, Debug)]
+pub struct LibraryTrack {
+	/// Full path to the file.
+	pub path: PathBuf,
+
+	/// Path relative to the library directory, which identifies the track.
+	pub name: String,
+
+	pub title: Option<String>,
+	pub artist: Option<String>,
+	pub album: Option<String>,
+	pub duration: Option<Duration>,
+}
+
+impl LibraryTrack {
+	/// Read the tags of the file at `path`, returning `None` if it is not a
+	/// supported audio file.
+	fn read(dir: &Path, path: &Path) -> Option<Self> {
+		let file = std::fs::File::open(path)
+			.inspect_err(|e| error!("Unable to open {:?}: {:?}", path, e))
+			.ok()?;
+
+		let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+		let mut hint = Hint::new();
+
+		if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+			hint.with_extension(ext);
+		}
+
+		let mut probed = symphonia::default::get_probe()
+			.format(
+				&hint,
+				mss,
+				&FormatOptions::default(),
+				&MetadataOptions::default(),
+			)
+			.inspect_err(|e| debug!("Skipping {:?}: {:?}", path, e))
+			.ok()?;
+
+		let duration = probed.format.default_track().cloned().and_then(|track| {
+			let n_frames = match track.codec_params.n_frames {
+				Some(n_frames) => n_frames,
+				// some formats, such as mp3 without a xing header, don't store the
+				// length, so add up the length of every packet without decoding
+				None => std::iter::from_fn(|| probed.format.next_packet().ok())
+					.filter(|packet| packet.track_id() == track.id)
+					.map(|packet| packet.dur)
+					.sum(),
+			};
+
+			let time = track.codec_params.time_base?.calc_time(n_frames);
+
+			Some(Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac))
+		});
+
+		let mut track = Self {
+			path: path.to_owned(),
+			name: path.strip_prefix(dir).ok()?.to_string_lossy().into_owned(),
+			title: None,
+			artist: None,
+			album: None,
+			duration,
+		};
+
+		// tags from the container take priority over tags found while probing,
+		// such as id3 tags in front of the audio
+		if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+			track.read_tags(revision);
+		}
+
+		if let Some(revision) = probed.format.metadata().current() {
+			track.read_tags(revision);
+		}
+
+		Some(track)
+	}
+
+	fn read_tags(&mut self, revision: &MetadataRevision) {
+		for tag in revision.tags() {
+			let field = match tag.std_key {
+				Some(StandardTagKey::TrackTitle) => &mut self.title,
+				Some(StandardTagKey::Artist) => &mut self.artist,
+				Some(StandardTagKey::Album) => &mut self.album,
+				_ => continue,
+			};
+
+			*field = Some(tag.value.to_string());
+		}
+	}
+
+	/// Title to display for the track, falling back to the file name.
+	pub fn display_title(&self) -> String {
+		match &self.title {
+			Some(title) => title.clone(),
+			None => self
+				.path
+				.file_stem()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.into_owned(),
+		}
+	}
+
+	/// Text which searches are matched against.
+	fn search_text(&self) -> String {
+		[self.artist.as_deref(), Some(&self.display_title())]
+			.into_iter()
+			.flatten()
+			.join(" ")
+			.to_lowercase()
+	}
+}
+
+impl From<&LibraryTrack> for ComposeWithMetadata<File<PathBuf>> {
+	fn from(track: &LibraryTrack) -> Self {
+		Self::new(
+			File::new(track.path.clone()),
+			AuxMetadata {
+				title: Some(track.display_title()),
+				artist: track.artist.clone(),
+				album: track.album.clone(),
+				duration: track.duration,
+				source_url: Some(track.name.clone()),
+				..Default::default()
+			},
+		)
+	}
+}
+
+/// Read the tags of every audio file under `dir`.
+pub fn index(dir: &Path) -> Vec<LibraryTrack> {
+	info!("Indexing music library in {:?}", dir);
+
+	let tracks = WalkDir::new(dir)
+		.follow_links(true)
+		.into_iter()
+		.filter_map(|f| f.inspect_err(|e| error!("{:?}", e)).ok())
+		.filter(|f| f.file_type().is_file())
+		.filter_map(|f| LibraryTrack::read(dir, f.path()))
+		.collect_vec();
+
+	info!("Indexed {} tracks in music library", tracks.len());
+
+	tracks
+}
+
+/// Get the track with the exact `name`, as found in [`LibraryTrack::name`].
+pub async fn get(name: &str) -> Option<&'static LibraryTrack> {
+	tracks().await.iter().find(|track| track.name == name)
+}
+
+/// Find the track which best matches `search` by artist and title.
+///
+/// Only a track containing the whole search, ignoring case, is returned, so
+/// that searches for music not in the library can be tried elsewhere.
+#[tracing::instrument(level = "info")]
+pub async fn search(search: &str) -> Option<&'static LibraryTrack> {
+	let search = search.to_lowercase();
+
+	tracks()
+		.await
+		.iter()
+		.map(|track| {
+			let rank = levenshtein_rank(search.as_bytes(), track.search_text().as_bytes());
+			(rank, track)
+		})
+		.min_by_key(|(rank, _)| *rank)
+		.filter(|((distance, _, _), _)| *distance == 0)
+		.map(|(_, track)| track)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::audio::CLIP_PATH;
+
+	/// clips are short audio files, so make a good stand in for a library
+	#[test]
+	fn index_clips() {
+		let tracks = index(&CLIP_PATH);
+
+		assert!(!tracks.is_empty());
+		assert!(tracks.iter().all(|t| t.duration.is_some()));
+		assert!(tracks.iter().all(|t| CLIP_PATH.join(&t.name) == t.path));
+	}
+}
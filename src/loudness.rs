@@ -0,0 +1,433 @@
+//! Loudness measurement and normalization, following EBU R128.
+//!
+//! Clips are measured once when they are indexed, and their loudness is cached
+//! in storage, so they can be played at the target loudness from the start.
+//! Streamed tracks can't be measured beforehand, so their gain follows the
+//! loudness of the last few seconds instead.
+
+use serenity::model::id::GuildId;
+
+use songbird::input::{AudioStream, AudioStreamError, Input};
+
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::io::MediaSource;
+use symphonia::core::probe::Hint;
+
+use tracing::{debug, error, info};
+
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::audio::{CLIP_PATH, GUILD_CLIP_PATH, clip_iter};
+use crate::commands::BotState;
+use crate::filter::{self, Biquad, PacketDecoder, guild_filters};
+use crate::persistence::{ClipLoudness, Storage};
+use crate::util::GetExpect;
+use crate::{RESOURCE_PATH, StorageKey};
+
+/// Loudness every track is brought to, in LUFS. This is the reference level of
+/// ReplayGain 2, which leaves more headroom than streaming services use, but
+/// keeps quiet tracks from being boosted too much.
+pub const TARGET_LOUDNESS: f32 = -18.0;
+
+/// Most gain added to quiet audio, in decibels.
+const MAX_BOOST: f32 = 12.0;
+
+/// Most gain taken from loud audio, in decibels.
+const MAX_CUT: f32 = 24.0;
+
+/// Blocks quieter than this are silence, and left out of the loudness.
+const ABSOLUTE_GATE: f64 = -70.0;
+
+/// Blocks this much quieter than the loudness of the ungated blocks are left
+/// out of the loudness.
+const RELATIVE_GATE: f64 = -10.0;
+
+/// Length of the steps loudness is measured in, in seconds. Each gating block
+/// is made of [`GATING_STEPS`] steps, so that they overlap by 75%.
+const STEP: f64 = 0.1;
+
+const GATING_STEPS: usize = 4;
+
+/// Steps in the short term loudness which streams are normalized to.
+const SHORT_TERM_STEPS: usize = 30;
+
+/// Time in seconds for the gain of a stream to mostly settle after its loudness
+/// changes. Shorter times pump with the beat of the music.
+const GAIN_SETTLE: f32 = 2.0;
+
+/// Loudness in LUFS of a mean square power.
+fn lufs(power: f64) -> f64 {
+	-0.691 + 10.0 * power.log10()
+}
+
+/// Linear gain which brings audio of `loudness` to [`TARGET_LOUDNESS`].
+pub fn gain(loudness: f32) -> f32 {
+	10f32.powf((TARGET_LOUDNESS - loudness).clamp(-MAX_CUT, MAX_BOOST) / 20.0)
+}
+
+/// K-weighting filters, which model how loud each frequency sounds.
+///
+/// The coefficients are found for any sample rate the same way as libebur128.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+	let rate = sample_rate as f32;
+
+	// high shelf for the acoustic effect of the head
+	let k = (PI * 1681.9745 / rate).tan();
+	let q = 0.70717525;
+	let vh = 10f32.powf(3.9998438 / 20.0);
+	let vb = vh.powf(0.49966678);
+	let a0 = 1.0 + k / q + k * k;
+
+	let shelf = Biquad::new(
+		[
+			(vh + vb * k / q + k * k) / a0,
+			2.0 * (k * k - vh) / a0,
+			(vh - vb * k / q + k * k) / a0,
+		],
+		[2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+	);
+
+	// high pass for how little low frequencies are heard
+	let k = (PI * 38.13547 / rate).tan();
+	let q = 0.50032704;
+	let a0 = 1.0 + k / q + k * k;
+
+	let pass = Biquad::new(
+		[1.0, -2.0, 1.0],
+		[2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+	);
+
+	[shelf, pass]
+}
+
+/// Measures the loudness of interleaved samples.
+pub struct Meter {
+	channels: usize,
+	weighting: Vec<[Biquad; 2]>,
+
+	/// Frames in each step, and in the step being measured so far.
+	step_len: usize,
+	step_frames: usize,
+
+	/// Sum of the squared weighted samples of the step being measured.
+	step_power: f64,
+
+	/// Mean square power of each finished step, keeping only the last
+	/// `max_steps` if set.
+	steps: VecDeque<f64>,
+	max_steps: Option<usize>,
+}
+
+impl Meter {
+	/// Meter for the integrated loudness of all audio pushed to it.
+	pub fn new(sample_rate: u32, channels: usize) -> Self {
+		Self {
+			channels,
+			weighting: vec![k_weighting(sample_rate); channels],
+			step_len: ((sample_rate as f64 * STEP) as usize).max(1),
+			step_frames: 0,
+			step_power: 0.0,
+			steps: VecDeque::new(),
+			max_steps: None,
+		}
+	}
+
+	/// Meter for the loudness of the last few seconds of audio pushed to it.
+	pub fn short_term(sample_rate: u32, channels: usize) -> Self {
+		Self {
+			max_steps: Some(SHORT_TERM_STEPS),
+			..Self::new(sample_rate, channels)
+		}
+	}
+
+	pub fn push(&mut self, samples: &[f32]) {
+		for frame in samples.chunks_exact(self.channels) {
+			for (x, [shelf, pass]) in frame.iter().zip(&mut self.weighting) {
+				let y = pass.process(shelf.process(*x)) as f64;
+				self.step_power += y * y;
+			}
+
+			self.step_frames += 1;
+
+			if self.step_frames == self.step_len {
+				self.steps.push_back(self.step_power / self.step_len as f64);
+				self.step_frames = 0;
+				self.step_power = 0.0;
+
+				if self.max_steps.is_some_and(|max| self.steps.len() > max) {
+					self.steps.pop_front();
+				}
+			}
+		}
+	}
+
+	/// Gated loudness in LUFS of the audio measured, or `None` if it is too
+	/// short or silent.
+	pub fn loudness(&self) -> Option<f32> {
+		let steps = self.steps.iter().copied().collect::<Vec<_>>();
+
+		let blocks = steps
+			.windows(GATING_STEPS)
+			.map(|w| w.iter().sum::<f64>() / GATING_STEPS as f64)
+			.filter(|p| lufs(*p) > ABSOLUTE_GATE)
+			.collect::<Vec<_>>();
+
+		if blocks.is_empty() {
+			return None;
+		}
+
+		let threshold = lufs(blocks.iter().sum::<f64>() / blocks.len() as f64) + RELATIVE_GATE;
+
+		let gated = blocks
+			.into_iter()
+			.filter(|p| lufs(*p) > threshold)
+			.collect::<Vec<_>>();
+
+		Some(lufs(gated.iter().sum::<f64>() / gated.len() as f64) as f32)
+	}
+}
+
+/// Brings audio to [`TARGET_LOUDNESS`].
+pub enum Normalizer {
+	/// Gain found from loudness measured beforehand.
+	Fixed(f32),
+
+	/// Gain following the short term loudness, moving smoothly towards the
+	/// target by `settle` of the difference each frame.
+	Running {
+		meter: Meter,
+		channels: usize,
+		gain: f32,
+		settle: f32,
+	},
+}
+
+impl Normalizer {
+	/// Normalizer for audio with the given `loudness`, or following the
+	/// loudness as it plays if it is unknown.
+	pub fn new(sample_rate: u32, channels: usize, loudness: Option<f32>) -> Self {
+		match loudness {
+			Some(loudness) => Self::Fixed(gain(loudness)),
+			None => Self::Running {
+				meter: Meter::short_term(sample_rate, channels),
+				channels: channels.max(1),
+				gain: 1.0,
+				settle: 1.0 - (-1.0 / (sample_rate as f32 * GAIN_SETTLE)).exp(),
+			},
+		}
+	}
+
+	/// Apply the gain to interleaved `samples`.
+	pub fn process(&mut self, samples: &mut [f32]) {
+		match self {
+			Self::Fixed(gain) => samples.iter_mut().for_each(|x| *x *= *gain),
+			Self::Running {
+				meter,
+				channels,
+				gain: current,
+				settle,
+			} => {
+				meter.push(samples);
+
+				// keep the gain through silence, rather than boosting the noise
+				let target = meter.loudness().map_or(*current, gain);
+
+				for frame in samples.chunks_exact_mut(*channels) {
+					*current += (target - *current) * *settle;
+					frame.iter_mut().for_each(|x| *x *= *current);
+				}
+			}
+		}
+	}
+}
+
+/// Measure the integrated loudness of the audio file at `path`. Returns `None`
+/// if it is silent.
+pub fn analyse(path: &Path) -> Result<Option<f32>, AudioStreamError> {
+	let fail = |e: SymphoniaError| AudioStreamError::Fail(Box::new(e));
+
+	let file = std::fs::File::open(path).map_err(|e| AudioStreamError::Fail(Box::new(e)))?;
+
+	let mut hint = Hint::new();
+
+	if let Some(extension) = path.extension().and_then(|e| e.to_str()) {
+		hint.with_extension(extension);
+	}
+
+	let mut packets = PacketDecoder::new(AudioStream {
+		input: Box::new(file) as Box<dyn MediaSource>,
+		hint: Some(hint),
+	})?;
+
+	let mut meter = None;
+
+	while let Some((spec, samples)) = packets.next().map_err(fail)? {
+		meter
+			.get_or_insert_with(|| Meter::new(spec.rate, spec.channels.count()))
+			.push(samples.samples());
+	}
+
+	Ok(meter.and_then(|meter| meter.loudness()))
+}
+
+/// Time the clip at `path` was last modified.
+fn modified(path: &Path) -> Option<SystemTime> {
+	std::fs::metadata(path)
+		.and_then(|m| m.modified())
+		.inspect_err(|e| error!("Unable to read clip metadata: {:?}", e))
+		.ok()
+}
+
+/// Loudness of the clip at `path`, from the cache in storage if it has not
+/// changed since it was measured, otherwise measuring it now. Returns `None`
+/// if the clip is silent or could not be measured.
+///
+/// Shared clips are stored by their path in [`CLIP_PATH`], and the clips of a
+/// guild by their path in [`RESOURCE_PATH`].
+pub async fn clip_loudness(storage: &(dyn Storage + Send + Sync), path: &Path) -> Option<f32> {
+	let clip = match path.strip_prefix(&*CLIP_PATH) {
+		Ok(clip) => clip,
+		Err(_) if path.starts_with(&*GUILD_CLIP_PATH) => path.strip_prefix(*RESOURCE_PATH).ok()?,
+		Err(_) => return None,
+	}
+	.to_string_lossy();
+	let modified = modified(path)?;
+
+	let cached = storage
+		.get_clip_loudness(&clip)
+		.await
+		.inspect_err(|e| error!("Unable to retrieve clip loudness: {:?}", e))
+		.ok()
+		.flatten();
+
+	// storage keeps the time to the microsecond
+	let micros = |time: SystemTime| {
+		time.duration_since(SystemTime::UNIX_EPOCH)
+			.unwrap_or_default()
+			.as_micros()
+	};
+
+	if let Some(cached) = cached
+		&& micros(cached.modified) == micros(modified)
+	{
+		return cached.loudness;
+	}
+
+	debug!("Measuring loudness of {}", clip);
+
+	let owned = path.to_owned();
+
+	let loudness = tokio::task::spawn_blocking(move || analyse(&owned))
+		.await
+		.inspect_err(|e| error!("Unable to join loudness task: {:?}", e))
+		.ok()?
+		.inspect_err(|e| error!("Unable to measure loudness of {}: {:?}", clip, e))
+		.ok()?;
+
+	let entry = ClipLoudness { loudness, modified };
+
+	let _ = storage
+		.set_clip_loudness(&clip, &entry)
+		.await
+		.inspect_err(|e| error!("Unable to save clip loudness: {:?}", e));
+
+	loudness
+}
+
+/// Normalize a clip about to be played in a guild, if the guild has
+/// normalization on. A local clip at `path` uses the loudness it was indexed
+/// with, while any other clip is normalized as it plays.
+pub async fn prepare_clip(
+	state: &BotState,
+	guild_id: GuildId,
+	path: Option<&Path>,
+	input: Input,
+) -> Input {
+	let normalize = guild_filters(state, guild_id)
+		.await
+		.read()
+		.is_ok_and(|filters| filters.normalize);
+
+	if !normalize {
+		return input;
+	}
+
+	let loudness = match path {
+		Some(path) => {
+			let storage = state.data.read().await.clone_expect::<StorageKey>();
+			clip_loudness(&*storage, path).await
+		}
+		None => None,
+	};
+
+	filter::normalize_clip(input, loudness)
+}
+
+/// Measure the loudness of every clip that has not been measured since it last
+/// changed, so clips play normalized straight away.
+pub async fn index_clips(storage: Arc<dyn Storage + Send + Sync>) {
+	let clips = clip_iter(None).collect::<Vec<_>>();
+
+	info!("Indexing loudness of {} clips", clips.len());
+
+	for clip in clips {
+		clip_loudness(&*storage, &CLIP_PATH.join(clip)).await;
+	}
+
+	info!("Finished indexing clip loudness");
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	/// interleaved stereo sine wave
+	fn sine(sample_rate: u32, frequency: f32, amplitude: f32, seconds: f32) -> Vec<f32> {
+		(0..(sample_rate as f32 * seconds) as usize)
+			.map(|i| amplitude * (2.0 * PI * frequency * i as f32 / sample_rate as f32).sin())
+			.flat_map(|x| [x, x])
+			.collect()
+	}
+
+	#[test]
+	fn meter_reference_tone() {
+		// a 1kHz sine at -18 dBFS in both channels is -18 LUFS
+		let mut meter = Meter::new(48000, 2);
+		meter.push(&sine(48000, 1000.0, 10f32.powf(-18.0 / 20.0), 5.0));
+
+		let loudness = meter.loudness().expect("No loudness measured");
+
+		assert!((loudness + 18.0).abs() < 0.2, "{loudness}");
+	}
+
+	#[test]
+	fn meter_gates_silence() {
+		let mut meter = Meter::new(44100, 2);
+		meter.push(&sine(44100, 1000.0, 10f32.powf(-18.0 / 20.0), 3.0));
+		meter.push(&vec![0.0; 44100 * 2 * 10]);
+
+		let loudness = meter.loudness().expect("No loudness measured");
+
+		// blocks overlapping the end of the tone are a little quieter
+		assert!((loudness + 18.0).abs() < 0.5, "{loudness}");
+	}
+
+	#[test]
+	fn running_normalizer_reaches_target() {
+		let mut normalizer = Normalizer::new(48000, 2, None);
+
+		let mut samples = sine(48000, 1000.0, 10f32.powf(-30.0 / 20.0), 20.0);
+		normalizer.process(&mut samples);
+
+		let mut meter = Meter::new(48000, 2);
+		meter.push(&samples[samples.len() - 48000 * 2 * 5..]);
+
+		let loudness = meter.loudness().expect("No loudness measured");
+
+		assert!((loudness - TARGET_LOUDNESS).abs() < 0.5, "{loudness}");
+	}
+}
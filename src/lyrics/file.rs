@@ -0,0 +1,130 @@
+//! Lyrics read from a directory of text files.
+
+use async_trait::async_trait;
+
+use tracing::debug;
+
+use std::path::PathBuf;
+
+use crate::audio::levenshtein_rank;
+
+use super::{Lyrics, LyricsProvider, LyricsQuery, Result};
+
+/// Lyrics stored as `<artist> - <title>.txt` files in a directory, or just
+/// `<title>.txt` when the artist is unknown.
+pub struct LocalLyrics {
+	dir: PathBuf,
+}
+
+impl LocalLyrics {
+	pub fn new(dir: PathBuf) -> Self {
+		Self { dir }
+	}
+}
+
+#[async_trait]
+impl LyricsProvider for LocalLyrics {
+	/// Read the file whose name is closest to the query, allowing a few
+	/// mistakes for every word searched.
+	async fn lyrics(&self, query: &LyricsQuery) -> Result<Option<Lyrics>> {
+		let search = match &query.artist {
+			Some(artist) => format!("{} - {}", artist, query.title),
+			None => query.title.clone(),
+		}
+		.to_lowercase();
+
+		let mut entries = tokio::fs::read_dir(&self.dir).await?;
+		let mut best = None;
+
+		while let Some(entry) = entries.next_entry().await? {
+			let path = entry.path();
+
+			if path.extension().is_none_or(|e| e != "txt") {
+				continue;
+			}
+
+			let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+				continue;
+			};
+
+			let rank = levenshtein_rank(search.as_bytes(), name.to_lowercase().as_bytes());
+
+			if best.as_ref().is_none_or(|(best, _, _)| rank < *best) {
+				best = Some((rank, name.to_owned(), path));
+			}
+		}
+
+		let Some(((distance, _, _), name, path)) = best else {
+			return Ok(None);
+		};
+
+		debug!("Closest lyrics to {:?} are {:?}", search, name);
+
+		if distance as usize > search.split_whitespace().count() {
+			return Ok(None);
+		}
+
+		let (artist, title) = match name.split_once(" - ") {
+			Some((artist, title)) => (Some(artist.to_owned()), title.to_owned()),
+			None => (None, name),
+		};
+
+		Ok(Some(Lyrics {
+			title,
+			artist,
+			text: tokio::fs::read_to_string(path).await?,
+		}))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[tokio::test]
+	async fn local_lyrics() {
+		let dir = std::env::temp_dir().join(format!("utf-nate-lyrics-{}", std::process::id()));
+
+		tokio::fs::create_dir_all(&dir)
+			.await
+			.expect("Unable to create directory");
+		tokio::fs::write(dir.join("Nate - Unicode Song.txt"), "one\ntwo\n")
+			.await
+			.expect("Unable to write lyrics");
+		tokio::fs::write(dir.join("Other Song.txt"), "three\n")
+			.await
+			.expect("Unable to write lyrics");
+
+		let provider = LocalLyrics::new(dir.clone());
+
+		let found = provider
+			.lyrics(&LyricsQuery {
+				title: "unicode song".to_owned(),
+				artist: Some("Nate".to_owned()),
+			})
+			.await
+			.expect("Unable to look up lyrics");
+
+		let missing = provider
+			.lyrics(&LyricsQuery {
+				title: "something else entirely".to_owned(),
+				artist: None,
+			})
+			.await
+			.expect("Unable to look up lyrics");
+
+		tokio::fs::remove_dir_all(&dir)
+			.await
+			.expect("Unable to remove directory");
+
+		assert_eq!(
+			found,
+			Some(Lyrics {
+				title: "Unicode Song".to_owned(),
+				artist: Some("Nate".to_owned()),
+				text: "one\ntwo\n".to_owned(),
+			})
+		);
+		assert_eq!(missing, None);
+	}
+}
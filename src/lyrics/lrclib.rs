@@ -0,0 +1,106 @@
+//! Lyrics from the [LRCLIB API](https://lrclib.net/docs), which needs no key.
+
+use async_trait::async_trait;
+
+use serde::Deserialize;
+
+use crate::REQWEST_CLIENT;
+
+use super::{Lyrics, LyricsProvider, LyricsQuery, Result};
+
+/// Lyrics searched for on LRCLIB.
+pub struct Lrclib {
+	/// Url every request is made under.
+	api_url: String,
+}
+
+impl Lrclib {
+	pub fn new(api_url: String) -> Self {
+		Self { api_url }
+	}
+}
+
+pub fn default_api_url() -> String {
+	"https://lrclib.net/api".to_owned()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+	track_name: String,
+	artist_name: String,
+	plain_lyrics: Option<String>,
+}
+
+#[async_trait]
+impl LyricsProvider for Lrclib {
+	/// Search by title and artist when the artist is known, or by the title
+	/// alone as free text, taking the first result with lyrics.
+	async fn lyrics(&self, query: &LyricsQuery) -> Result<Option<Lyrics>> {
+		let request = REQWEST_CLIENT.get(format!("{}/search", self.api_url));
+
+		let request = match &query.artist {
+			Some(artist) => request.query(&[
+				("track_name", query.title.as_str()),
+				("artist_name", artist.as_str()),
+			]),
+			None => request.query(&[("q", query.title.as_str())]),
+		};
+
+		let records: Vec<Record> = request.send().await?.error_for_status()?.json().await?;
+
+		Ok(records.into_iter().find_map(|record| {
+			Some(Lyrics {
+				text: record.plain_lyrics.filter(|l| !l.trim().is_empty())?,
+				title: record.track_name,
+				artist: Some(record.artist_name),
+			})
+		}))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::util::mock::MockServer;
+
+	#[tokio::test]
+	async fn first_with_lyrics() {
+		let base_url = MockServer::new()
+			.fixture_with_query("/api/search", "artist_name=Nate", "lrclib/search.json")
+			.start()
+			.await;
+
+		let provider = Lrclib::new(format!("{}/api", base_url));
+
+		let found = provider
+			.lyrics(&LyricsQuery {
+				title: "Unicode Song".to_owned(),
+				artist: Some("Nate".to_owned()),
+			})
+			.await
+			.expect("Unable to look up lyrics");
+
+		// results with no lyrics, or only blank ones, are skipped
+		assert_eq!(
+			found,
+			Some(Lyrics {
+				title: "Unicode Song".to_owned(),
+				artist: Some("Nate".to_owned()),
+				text: "one\ntwo".to_owned(),
+			})
+		);
+
+		// searched by title alone, which the fixture doesn't match
+		assert!(
+			provider
+				.lyrics(&LyricsQuery {
+					title: "Unicode Song".to_owned(),
+					artist: None,
+				})
+				.await
+				.is_err()
+		);
+	}
+}
@@ -0,0 +1,93 @@
+//! Look up the lyrics of songs from a [`LyricsProvider`].
+//!
+//! Lyrics come from [LRCLIB] unless [`crate::configuration::Config::lyrics`]
+//! sets a directory of lyrics files, see [`file::LocalLyrics`].
+//!
+//! [LRCLIB]: https://lrclib.net
+pub mod file;
+pub mod lrclib;
+
+use async_trait::async_trait;
+
+use regex::Regex;
+
+use songbird::input::AuxMetadata;
+
+use thiserror::Error;
+
+use std::sync::{Arc, LazyLock};
+
+use crate::CONFIG;
+
+/// Bracketed parts of a title, like "(Official Video)".
+static TITLE_EXTRAS: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new(r"\s*(\([^)]*\)|\[[^\]]*\])").unwrap());
+
+#[derive(Debug, Error)]
+pub enum Error {
+	#[error("failed while fetching lyrics: {0}")]
+	Reqwest(#[from] reqwest::Error),
+	#[error("failed while reading lyrics: {0}")]
+	Io(#[from] std::io::Error),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Song to look up the lyrics of.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LyricsQuery {
+	/// Title of the song, or whatever was searched for if the song is unknown.
+	pub title: String,
+
+	/// Artist of the song, if known.
+	pub artist: Option<String>,
+}
+
+impl LyricsQuery {
+	/// Query the lyrics of a track from its metadata, or `None` if it has no
+	/// title.
+	///
+	/// Titles often carry extras like "(Official Video)", which are left out
+	/// as they rarely match.
+	pub fn from_metadata(metadata: &AuxMetadata) -> Option<Self> {
+		let title = TITLE_EXTRAS.replace_all(metadata.title.as_deref()?, "");
+		let title = title.trim();
+
+		(!title.is_empty()).then(|| Self {
+			title: title.to_owned(),
+			artist: metadata.artist.clone(),
+		})
+	}
+}
+
+/// Lyrics found for a song.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lyrics {
+	/// Title of the song the lyrics are for, which may differ from the query.
+	pub title: String,
+	pub artist: Option<String>,
+
+	/// Lyrics as plain text, with a line per line of the song.
+	pub text: String,
+}
+
+/// Somewhere to look up lyrics from.
+#[async_trait]
+pub trait LyricsProvider {
+	/// Find the lyrics of the song best matching `query`, or `None` if there
+	/// are none.
+	async fn lyrics(&self, query: &LyricsQuery) -> Result<Option<Lyrics>>;
+}
+
+/// Create the provider set by the configuration.
+pub fn provider() -> Arc<dyn LyricsProvider + Send + Sync> {
+	match &CONFIG.lyrics {
+		Some(dir) => Arc::new(file::LocalLyrics::new(dir.clone())),
+		None => Arc::new(lrclib::Lrclib::new(
+			CONFIG
+				.lrclib_url
+				.clone()
+				.unwrap_or_else(lrclib::default_api_url),
+		)),
+	}
+}
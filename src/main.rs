@@ -1,18 +1,27 @@
 mod audio;
+mod audio_cache;
+mod cache;
 mod commands;
 mod configuration;
 mod data;
 #[cfg(feature = "http-interface")]
 mod encrypt;
+mod filter;
 mod handler;
 #[cfg(feature = "http-interface")]
 mod http;
 mod interaction;
+mod library;
+mod loudness;
+mod lyrics;
 mod parser;
 mod persistence;
+mod prefetch;
+mod resolver;
 mod spotify;
 mod util;
 mod youtube;
+mod ytdl;
 
 use clap::Parser;
 
@@ -35,7 +44,7 @@ use serenity::prelude::RwLock;
 use songbird::serenity::SerenityInit;
 
 use configuration::Config;
-use data::{Keys, VoiceGuilds, VoiceUserCache};
+use data::{Crossfades, GuildFilters, Keys, LyricsKey, SkipVotes, VoiceGuilds, VoiceUserCache};
 use handler::Handler;
 use interaction::reregister;
 use util::{Framework, read_toml};
@@ -94,7 +103,7 @@ const GATEWAY_INTENTS: GatewayIntents = GatewayIntents::GUILD_MESSAGES
 struct StorageKey;
 
 impl serenity::prelude::TypeMapKey for StorageKey {
-	type Value = Box<dyn Storage + Send + Sync>;
+	type Value = Arc<dyn Storage + Send + Sync>;
 }
 
 /// Key for [`ring::aead::LessSafeKey`] for encryption purposes.
@@ -163,6 +172,9 @@ async fn main() {
 		audio::warn_exact_name_finds_different_clip();
 	}
 
+	// index the music library in the background, as it reads every file
+	tokio::spawn(library::tracks());
+
 	// read keys file
 	let keys_path = "keys.toml";
 	let keys: Keys = match read_toml(keys_path) {
@@ -219,6 +231,18 @@ async fn main() {
 		if !OPT.no_bot {
 			let mut join_set = JoinSet::<Result<(), ProcessError>>::new();
 
+			let storage: Arc<dyn Storage + Send + Sync> = Arc::new(db_pool);
+
+			// measure clips in the background, so they can be normalized
+			join_set.spawn({
+				let storage = storage.clone();
+
+				async move {
+					loudness::index_clips(storage).await;
+					Ok(())
+				}
+			});
+
 			info!("Config: {:#?}", *CONFIG);
 
 			// create a framework to process message commands
@@ -226,8 +250,12 @@ async fn main() {
 				.event_handler(Handler::default())
 				.type_map_insert::<VoiceUserCache>(Default::default())
 				.type_map_insert::<VoiceGuilds>(Default::default())
+				.type_map_insert::<SkipVotes>(Default::default())
+				.type_map_insert::<GuildFilters>(Default::default())
+				.type_map_insert::<Crossfades>(Default::default())
+				.type_map_insert::<LyricsKey>(lyrics::provider())
 				.type_map_insert::<Keys>(Arc::new(RwLock::new(keys)))
-				.type_map_insert::<StorageKey>(Box::new(db_pool))
+				.type_map_insert::<StorageKey>(storage)
 				.register_songbird_from_config(songbird::Config::default().preallocated_tracks(5))
 				.framework(
 					Framework::builder()
@@ -281,10 +309,62 @@ async fn main() {
 				info!("Starting HTTP server");
 
 				let app = axum::Router::new()
+					.form_route(autoplay::poise::autoplay, autoplay::http::autoplay)
+					.form_route(cache::poise::cache, cache::http::cache)
+					.route(
+						"/cache/stats",
+						get(|| async { form_endpoint(cache::poise::cache_stats) }),
+					)
+					.route("/cache/stats/run", get(cache::http::cache_stats))
+					.route(
+						"/cache/clear",
+						get(|| async { form_endpoint(cache::poise::cache_clear) }),
+					)
+					.route("/cache/clear/run", get(cache::http::cache_clear))
+					.form_route(crossfade::poise::crossfade, crossfade::http::crossfade)
+					.form_route(dj::poise::djrole, dj::http::djrole)
+					.form_route(dj::poise::skipthreshold, dj::http::skipthreshold)
+					.form_route(duck::poise::duck, duck::http::duck)
 					.form_route(external::poise::cmd, external::http::cmd)
 					.form_route(external::poise::cmdlist, external::http::cmdlist)
+					.form_route(fairqueue::poise::fairqueue, fairqueue::http::fairqueue)
+					.form_route(fairqueue::poise::queuelimit, fairqueue::http::queuelimit)
+					.form_route(history::poise::history, history::http::history)
+					.form_route(history::poise::replay, history::http::replay)
 					.form_route(join::poise::summon, join::http::summon)
 					.form_route(join::poise::banish, join::http::banish)
+					.form_route(lyrics::poise::lyrics, lyrics::http::lyrics)
+					.form_route(filter::poise::filter, filter::http::filter)
+					.route(
+						"/filter/bassboost",
+						get(|| async { form_endpoint(filter::poise::filter_bassboost) }),
+					)
+					.route("/filter/bassboost/run", get(filter::http::filter_bassboost))
+					.route(
+						"/filter/nightcore",
+						get(|| async { form_endpoint(filter::poise::filter_nightcore) }),
+					)
+					.route("/filter/nightcore/run", get(filter::http::filter_nightcore))
+					.route(
+						"/filter/speed",
+						get(|| async { form_endpoint(filter::poise::filter_speed) }),
+					)
+					.route("/filter/speed/run", get(filter::http::filter_speed))
+					.route(
+						"/filter/pitch",
+						get(|| async { form_endpoint(filter::poise::filter_pitch) }),
+					)
+					.route("/filter/pitch/run", get(filter::http::filter_pitch))
+					.route(
+						"/filter/normalize",
+						get(|| async { form_endpoint(filter::poise::filter_normalize) }),
+					)
+					.route("/filter/normalize/run", get(filter::http::filter_normalize))
+					.route(
+						"/filter/reset",
+						get(|| async { form_endpoint(filter::poise::filter_reset) }),
+					)
+					.route("/filter/reset/run", get(filter::http::filter_reset))
 					.form_route(herald::poise::intro, herald::http::intro)
 					.form_route(herald::poise::introbot, herald::http::introbot)
 					.form_route(herald::poise::outro, herald::http::outro)
@@ -292,15 +372,39 @@ async fn main() {
 					.form_route(play::poise::play, play::http::play)
 					.form_route(play::poise::playnext, play::http::playnext)
 					.form_route(play::poise::playnow, play::http::playnow)
+					.route(
+						"/playlist/save",
+						get(|| async { form_endpoint(playlist::poise::playlist_save) }),
+					)
+					.route("/playlist/save/run", get(playlist::http::playlist_save))
+					.route(
+						"/playlist/load",
+						get(|| async { form_endpoint(playlist::poise::playlist_load) }),
+					)
+					.route("/playlist/load/run", get(playlist::http::playlist_load))
+					.route(
+						"/playlist/list",
+						get(|| async { form_endpoint(playlist::poise::playlist_list) }),
+					)
+					.route("/playlist/list/run", get(playlist::http::playlist_list))
+					.route(
+						"/playlist/delete",
+						get(|| async { form_endpoint(playlist::poise::playlist_delete) }),
+					)
+					.route("/playlist/delete/run", get(playlist::http::playlist_delete))
 					.form_route(queue::poise::stop, queue::http::stop)
 					.form_route(queue::poise::skip, queue::http::skip)
 					.form_route(queue::poise::pause, queue::http::pause)
 					.form_route(queue::poise::unpause, queue::http::unpause)
 					.form_route(queue::poise::queue, queue::http::queue)
+					.form_route(queue::poise::nowplaying, queue::http::nowplaying)
 					.form_route(queue::poise::shuffle, queue::http::shuffle)
 					.form_route(queue::poise::shufflenow, queue::http::shufflenow)
 					.form_route(queue::poise::r#loop, queue::http::r#loop)
 					.form_route(queue::poise::r#move, queue::http::r#move)
+					.form_route(queue::poise::seek, queue::http::seek)
+					.form_route(queue::poise::forward, queue::http::forward)
+					.form_route(queue::poise::rewind, queue::http::rewind)
 					.route(
 						"/volume/get",
 						get(|| async { form_endpoint(voice::poise::volume_get) }),
@@ -322,7 +426,9 @@ async fn main() {
 					)
 					.route("/volume/now/run", get(voice::http::volume_now))
 					.form_route(unicode::poise::unicode, unicode::http::unicode)
+					.form_route(upload::poise::upload, upload::http::upload)
 					.form_route(roll::poise::roll, roll::http::roll)
+					.form_route(search::poise::search, search::http::search)
 					.route("/token", get(token::http::token))
 					.fallback_service(ServeDir::new("resources/web"))
 					.with_state(state);
@@ -381,5 +487,10 @@ async fn init_database(
 		info!("Data tables created");
 	}
 
+	db_pool
+		.migrate()
+		.await
+		.inspect_err(|e| error!("Error migrating tables: {e}"))?;
+
 	Ok(db_pool)
 }
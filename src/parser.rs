@@ -8,16 +8,19 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use std::ops::RangeInclusive;
+use std::time::Duration;
 
 use nom::{
 	Finish, IResult, Parser, ToUsize,
 	branch::alt,
-	character::complete::{char as cchar, multispace0, u64 as cu64},
-	combinator::{all_consuming, map},
-	multi::separated_list0,
-	sequence::{delimited, separated_pair},
+	character::complete::{char as cchar, multispace0, one_of, u64 as cu64},
+	combinator::{all_consuming, map, map_opt},
+	multi::{many1, separated_list0, separated_list1},
+	sequence::{delimited, pair, separated_pair},
 };
 
+use crate::util::write_duration;
+
 /// Represents a selection of several ranges of values.
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[repr(transparent)]
@@ -92,6 +95,48 @@ impl std::str::FromStr for Selection<usize> {
 	}
 }
 
+/// A length of time, such as a position in a track, parsed from either a
+/// clock style time (`1:23:45`) or units (`1h23m45s`). See [`duration`].
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "String")]
+#[repr(transparent)]
+pub struct DurationArg(pub Duration);
+
+/// Error representing an error while parsing a [`DurationArg`].
+#[derive(Debug, Error)]
+#[error("expected a time such as 1:23:45, 90s, or 1m30s")]
+pub struct ParseDurationError(#[from] nom::error::Error<String>);
+
+impl std::str::FromStr for DurationArg {
+	type Err = ParseDurationError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		duration(s).finish().map(|v| Self(v.1)).map_err(|e| {
+			nom::error::Error {
+				input: e.input.to_string(),
+				code: e.code,
+			}
+			.into()
+		})
+	}
+}
+
+impl TryFrom<String> for DurationArg {
+	type Error = ParseDurationError;
+
+	fn try_from(s: String) -> Result<Self, Self::Error> {
+		s.parse()
+	}
+}
+
+impl From<DurationArg> for String {
+	fn from(d: DurationArg) -> Self {
+		let mut s = String::new();
+		write_duration(&mut s, d.0).unwrap();
+		s
+	}
+}
+
 impl<T> IntoIterator for NumOrRange<T>
 where
 	RangeInclusive<T>: Iterator<Item = T>,
@@ -147,6 +192,46 @@ pub fn selection(input: &str) -> IResult<&str, Selection<usize>> {
 	.parse(input)
 }
 
+/// [`nom`] style parser for a clock style time in seconds, as `s`, `m:ss`,
+/// or `h:mm:ss`. Minutes and seconds after the first part must be under 60.
+pub fn clock_secs(input: &str) -> IResult<&str, u64> {
+	map_opt(separated_list1(cchar(':'), cu64), |parts| match parts[..] {
+		[s] => Some(s),
+		[m, s] if s < 60 => m.checked_mul(60)?.checked_add(s),
+		[h, m, s] if m < 60 && s < 60 => h.checked_mul(3600)?.checked_add(m * 60 + s),
+		_ => None,
+	})
+	.parse(input)
+}
+
+/// [`nom`] style parser for a time in seconds written with units, such as
+/// `90s`, `1m30s`, or `1h2m3s`.
+pub fn unit_secs(input: &str) -> IResult<&str, u64> {
+	map_opt(many1(pair(cu64, one_of("hms"))), |parts| {
+		parts.into_iter().try_fold(0u64, |acc, (n, unit)| {
+			let scale = match unit {
+				'h' => 3600,
+				'm' => 60,
+				_ => 1,
+			};
+
+			acc.checked_add(n.checked_mul(scale)?)
+		})
+	})
+	.parse(input)
+}
+
+/// [`nom`] style parser for a [`Duration`] written as either a clock time or
+/// with units. See [`clock_secs`] and [`unit_secs`]. Must consume the entire
+/// input.
+pub fn duration(input: &str) -> IResult<&str, Duration> {
+	all_consuming(map(
+		delimited(multispace0, alt((unit_secs, clock_secs)), multispace0),
+		Duration::from_secs,
+	))
+	.parse(input)
+}
+
 #[cfg(test)]
 mod test {
 	use itertools::Itertools;
@@ -222,4 +307,35 @@ mod test {
 				assert_eq!(target, sel.1.0[..])
 			});
 	}
+
+	#[test]
+	fn duration_clock() {
+		for (s, secs) in [
+			("0", 0),
+			("90", 90),
+			("1:30", 90),
+			("01:02:03", 3723),
+			(" 1:23:45 ", 5025),
+		] {
+			let d = duration(s).expect("Error parsing");
+
+			assert_eq!(Duration::from_secs(secs), d.1)
+		}
+	}
+
+	#[test]
+	fn duration_units() {
+		for (s, secs) in [("90s", 90), ("1m30s", 90), ("2m", 120), ("1h2m3s", 3723)] {
+			let d = duration(s).expect("Error parsing");
+
+			assert_eq!(Duration::from_secs(secs), d.1)
+		}
+	}
+
+	#[test]
+	fn duration_invalid() {
+		for s in ["", "1:60", "1:2:3:4", "1m30", "s", "-1"] {
+			assert!(duration(s).is_err(), "{s:?} should not parse")
+		}
+	}
 }
@@ -1,20 +1,56 @@
 use std::fs::read_to_string;
 use std::path::PathBuf;
 use std::sync::LazyLock;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 
-use serenity::all::{GuildId, UserId};
+use serenity::all::{GuildId, RoleId, UserId};
 use sqlx::{AnyExecutor, Database, Decode, Encode, FromRow, IntoArguments, Type};
 
 use crate::RESOURCE_PATH;
+use crate::filter::Filters;
 use crate::util::Conv;
 
-use super::{Storage, StorageError};
+use super::{
+	ClipLoudness, HistoryEntry, PlaylistTrack, QueueLimits, QueuedTrack, Storage, StorageError,
+};
 
 /// Path to shared directory for database scripts.
 pub static DB_PATH: LazyLock<PathBuf> = LazyLock::new(|| RESOURCE_PATH.join("database/"));
 
+/// Columns added to tables after they were first created, as the table, the
+/// column, and the column definition. Tables created by older versions are
+/// missing these, so they are added by [`Storage::migrate`] when absent.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+	("guild_config", "autoplay", "boolean"),
+	("guild_config", "dj_role", "bigint"),
+	(
+		"guild_config",
+		"skip_threshold",
+		"bigint check (skip_threshold > 0 and skip_threshold <= 100)",
+	),
+	("guild_config", "fair_queue", "boolean"),
+	("guild_config", "user_track_limit", "bigint"),
+	("guild_config", "user_duration_limit_ms", "bigint"),
+	("guild_config", "filter_bass_boost", "double precision"),
+	("guild_config", "filter_rate", "double precision"),
+	("guild_config", "filter_speed", "double precision"),
+	("guild_config", "filter_pitch", "double precision"),
+	("guild_config", "filter_normalize", "boolean"),
+	(
+		"guild_config",
+		"crossfade_ms",
+		"bigint check (crossfade_ms >= 0)",
+	),
+	(
+		"guild_config",
+		"duck_level",
+		"double precision check (duck_level >= 0.0 and duck_level <= 1.0)",
+	),
+	("guild_queue", "requested_by", "bigint"),
+];
+
 #[async_trait]
 impl Storage for sqlx::Pool<sqlx::Any> {
 	async fn first_time_setup(&self) -> Result<(), StorageError> {
@@ -23,6 +59,26 @@ impl Storage for sqlx::Pool<sqlx::Any> {
 		Ok(())
 	}
 
+	async fn migrate(&self) -> Result<(), StorageError> {
+		// neither postgres nor sqlite can list columns the same way, so just try selecting them
+		for (table, column, definition) in ADDED_COLUMNS {
+			let exists = sqlx::query(&format!("select {column} from {table} where 1 = 0"))
+				.execute(self)
+				.await
+				.is_ok();
+
+			if !exists {
+				sqlx::query(&format!(
+					"alter table {table} add column {column} {definition}"
+				))
+				.execute(self)
+				.await?;
+			}
+		}
+
+		Ok(())
+	}
+
 	async fn get_intro(&self, user_id: UserId) -> Result<Option<String>, StorageError> {
 		get_by_id(self, &read_query("get-intro.sql")?, user_id.conv::<i64>()).await
 	}
@@ -133,6 +189,438 @@ impl Storage for sqlx::Pool<sqlx::Any> {
 		)
 		.await
 	}
+
+	async fn get_autoplay(&self, guild_id: GuildId) -> Result<Option<bool>, StorageError> {
+		// the row may exist for other settings, with autoplay left null
+		get_by_id::<_, _, Option<i64>>(
+			self,
+			&read_query("get-autoplay.sql")?,
+			guild_id.conv::<i64>(),
+		)
+		.await
+		.map(|v| v.flatten().map(|v| v != 0))
+	}
+
+	async fn set_autoplay(&self, guild_id: GuildId, autoplay: bool) -> Result<(), StorageError> {
+		set_by_id(
+			self,
+			&read_query("set-autoplay.sql")?,
+			guild_id.conv::<i64>(),
+			autoplay,
+		)
+		.await
+	}
+
+	async fn get_fair_queue(&self, guild_id: GuildId) -> Result<Option<bool>, StorageError> {
+		get_by_id::<_, _, Option<i64>>(
+			self,
+			&read_query("get-fair-queue.sql")?,
+			guild_id.conv::<i64>(),
+		)
+		.await
+		.map(|v| v.flatten().map(|v| v != 0))
+	}
+
+	async fn set_fair_queue(&self, guild_id: GuildId, fair: bool) -> Result<(), StorageError> {
+		set_by_id(
+			self,
+			&read_query("set-fair-queue.sql")?,
+			guild_id.conv::<i64>(),
+			fair,
+		)
+		.await
+	}
+
+	async fn get_queue_limits(&self, guild_id: GuildId) -> Result<QueueLimits, StorageError> {
+		let row: Option<(Option<i64>, Option<i64>)> =
+			sqlx::query_as(&read_query("get-queue-limits.sql")?)
+				.bind(guild_id.conv::<i64>())
+				.fetch_optional(self)
+				.await?;
+
+		let (tracks, duration_ms) = row.unwrap_or_default();
+
+		Ok(QueueLimits {
+			tracks: tracks.map(|t| t as usize),
+			duration: duration_ms.map(|ms| Duration::from_millis(ms as u64)),
+		})
+	}
+
+	async fn set_queue_limits(
+		&self,
+		guild_id: GuildId,
+		limits: &QueueLimits,
+	) -> Result<(), StorageError> {
+		sqlx::query(&read_query("set-queue-limits.sql")?)
+			.bind(guild_id.conv::<i64>())
+			.bind(limits.tracks.map(|t| t as i64))
+			.bind(limits.duration.map(|d| d.as_millis() as i64))
+			.execute(self)
+			.await
+			.map_err(Into::into)
+			.and_then(|query| match query.rows_affected() {
+				0 => Err(StorageError::NoRowsChanged),
+				_ => Ok(()),
+			})
+	}
+
+	async fn get_filters(&self, guild_id: GuildId) -> Result<Option<Filters>, StorageError> {
+		type Row = (
+			Option<f64>,
+			Option<f64>,
+			Option<f64>,
+			Option<f64>,
+			Option<i64>,
+		);
+
+		let row: Option<Row> = sqlx::query_as(&read_query("get-filters.sql")?)
+			.bind(guild_id.conv::<i64>())
+			.fetch_optional(self)
+			.await?;
+
+		// the row may exist for other settings, with the filters left null
+		Ok(row.and_then(|(bass_boost, rate, speed, pitch, normalize)| {
+			Some(Filters {
+				bass_boost: bass_boost? as f32,
+				rate: rate? as f32,
+				speed: speed? as f32,
+				pitch: pitch? as f32,
+				normalize: normalize.is_some_and(|n| n != 0),
+			})
+		}))
+	}
+
+	async fn set_filters(&self, guild_id: GuildId, filters: &Filters) -> Result<(), StorageError> {
+		sqlx::query(&read_query("set-filters.sql")?)
+			.bind(guild_id.conv::<i64>())
+			.bind(filters.bass_boost as f64)
+			.bind(filters.rate as f64)
+			.bind(filters.speed as f64)
+			.bind(filters.pitch as f64)
+			.bind(filters.normalize)
+			.execute(self)
+			.await
+			.map_err(Into::into)
+			.and_then(|query| match query.rows_affected() {
+				0 => Err(StorageError::NoRowsChanged),
+				_ => Ok(()),
+			})
+	}
+
+	async fn get_clip_loudness(&self, clip: &str) -> Result<Option<ClipLoudness>, StorageError> {
+		let row: Option<(Option<f64>, i64)> = sqlx::query_as(&read_query("get-clip-loudness.sql")?)
+			.bind(clip)
+			.fetch_optional(self)
+			.await?;
+
+		Ok(row.map(|(loudness, modified_at)| ClipLoudness {
+			loudness: loudness.map(|l| l as f32),
+			modified: SystemTime::UNIX_EPOCH + Duration::from_micros(modified_at as u64),
+		}))
+	}
+
+	async fn set_clip_loudness(
+		&self,
+		clip: &str,
+		loudness: &ClipLoudness,
+	) -> Result<(), StorageError> {
+		let modified_at = loudness
+			.modified
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.unwrap_or_default();
+
+		sqlx::query(&read_query("set-clip-loudness.sql")?)
+			.bind(clip)
+			.bind(loudness.loudness.map(|l| l as f64))
+			.bind(modified_at.as_micros() as i64)
+			.execute(self)
+			.await?;
+
+		Ok(())
+	}
+
+	async fn get_dj_role(&self, guild_id: GuildId) -> Result<Option<RoleId>, StorageError> {
+		get_by_id::<_, _, Option<i64>>(
+			self,
+			&read_query("get-dj-role.sql")?,
+			guild_id.conv::<i64>(),
+		)
+		.await
+		.map(|v| v.flatten().map(|v| RoleId::new(v as u64)))
+	}
+
+	async fn set_dj_role(
+		&self,
+		guild_id: GuildId,
+		role: Option<RoleId>,
+	) -> Result<(), StorageError> {
+		set_by_id(
+			self,
+			&read_query("set-dj-role.sql")?,
+			guild_id.conv::<i64>(),
+			role.map(|r| r.conv::<i64>()),
+		)
+		.await
+	}
+
+	async fn get_skip_threshold(&self, guild_id: GuildId) -> Result<Option<u8>, StorageError> {
+		get_by_id::<_, _, Option<i64>>(
+			self,
+			&read_query("get-skip-threshold.sql")?,
+			guild_id.conv::<i64>(),
+		)
+		.await
+		.map(|v| v.flatten().map(|v| v as u8))
+	}
+
+	async fn set_skip_threshold(&self, guild_id: GuildId, percent: u8) -> Result<(), StorageError> {
+		set_by_id(
+			self,
+			&read_query("set-skip-threshold.sql")?,
+			guild_id.conv::<i64>(),
+			percent as i64,
+		)
+		.await
+	}
+
+	async fn get_crossfade(&self, guild_id: GuildId) -> Result<Option<Duration>, StorageError> {
+		get_by_id::<_, _, Option<i64>>(
+			self,
+			&read_query("get-crossfade.sql")?,
+			guild_id.conv::<i64>(),
+		)
+		.await
+		.map(|v| v.flatten().map(|ms| Duration::from_millis(ms as u64)))
+	}
+
+	async fn set_crossfade(
+		&self,
+		guild_id: GuildId,
+		crossfade: Duration,
+	) -> Result<(), StorageError> {
+		set_by_id(
+			self,
+			&read_query("set-crossfade.sql")?,
+			guild_id.conv::<i64>(),
+			crossfade.as_millis() as i64,
+		)
+		.await
+	}
+
+	async fn get_duck_level(&self, guild_id: GuildId) -> Result<Option<f32>, StorageError> {
+		get_by_id::<_, _, Option<f64>>(
+			self,
+			&read_query("get-duck-level.sql")?,
+			guild_id.conv::<i64>(),
+		)
+		.await
+		.map(|v| v.flatten().map(|level| level as f32))
+	}
+
+	async fn set_duck_level(&self, guild_id: GuildId, level: f32) -> Result<(), StorageError> {
+		set_by_id(
+			self,
+			&read_query("set-duck-level.sql")?,
+			guild_id.conv::<i64>(),
+			level as f64,
+		)
+		.await
+	}
+
+	async fn get_queue(&self, guild_id: GuildId) -> Result<Vec<QueuedTrack>, StorageError> {
+		let rows: Vec<(String, Option<String>, Option<i64>, i64, Option<i64>)> =
+			sqlx::query_as(&read_query("get-queue.sql")?)
+				.bind(guild_id.conv::<i64>())
+				.fetch_all(self)
+				.await?;
+
+		Ok(rows
+			.into_iter()
+			.map(
+				|(source_url, title, requested_by, position_ms, loops)| QueuedTrack {
+					source_url,
+					title,
+					requester: requested_by.map(|id| UserId::new(id as u64)),
+					position: Duration::from_millis(position_ms as u64),
+					loops: loops.map(|l| l as usize),
+				},
+			)
+			.collect())
+	}
+
+	async fn set_queue(
+		&self,
+		guild_id: GuildId,
+		queue: &[QueuedTrack],
+	) -> Result<(), StorageError> {
+		let clear = read_query("clear-queue.sql")?;
+		let insert = read_query("add-queue-track.sql")?;
+
+		let mut transaction = self.begin().await?;
+
+		sqlx::query(&clear)
+			.bind(guild_id.conv::<i64>())
+			.execute(&mut *transaction)
+			.await?;
+
+		for (i, track) in queue.iter().enumerate() {
+			sqlx::query(&insert)
+				.bind(guild_id.conv::<i64>())
+				.bind(i as i64)
+				.bind(track.source_url.as_str())
+				.bind(track.title.as_deref())
+				.bind(track.requester.map(|id| id.conv::<i64>()))
+				.bind(track.position.as_millis() as i64)
+				.bind(track.loops.map(|l| l as i64))
+				.execute(&mut *transaction)
+				.await?;
+		}
+
+		transaction.commit().await?;
+
+		Ok(())
+	}
+
+	async fn add_history(
+		&self,
+		guild_id: GuildId,
+		entry: &HistoryEntry,
+	) -> Result<(), StorageError> {
+		let queued_at = entry
+			.queued_at
+			.duration_since(SystemTime::UNIX_EPOCH)
+			.unwrap_or_default();
+
+		sqlx::query(&read_query("add-history.sql")?)
+			.bind(guild_id.conv::<i64>())
+			.bind(queued_at.as_micros() as i64)
+			.bind(entry.source_url.as_str())
+			.bind(entry.title.as_deref())
+			.execute(self)
+			.await?;
+
+		Ok(())
+	}
+
+	async fn get_history(
+		&self,
+		guild_id: GuildId,
+		limit: usize,
+	) -> Result<Vec<HistoryEntry>, StorageError> {
+		let rows: Vec<(String, Option<String>, i64)> =
+			sqlx::query_as(&read_query("get-history.sql")?)
+				.bind(guild_id.conv::<i64>())
+				.bind(limit as i64)
+				.fetch_all(self)
+				.await?;
+
+		Ok(rows
+			.into_iter()
+			.map(|(source_url, title, queued_at)| HistoryEntry {
+				source_url,
+				title,
+				queued_at: SystemTime::UNIX_EPOCH + Duration::from_micros(queued_at as u64),
+			})
+			.collect())
+	}
+
+	async fn save_playlist(
+		&self,
+		guild_id: GuildId,
+		name: &str,
+		tracks: &[PlaylistTrack],
+	) -> Result<(), StorageError> {
+		let delete_tracks = read_query("delete-playlist-tracks.sql")?;
+		let delete = read_query("delete-playlist.sql")?;
+		let add = read_query("add-playlist.sql")?;
+		let add_track = read_query("add-playlist-track.sql")?;
+
+		let mut transaction = self.begin().await?;
+
+		for sql in [&delete_tracks, &delete, &add] {
+			sqlx::query(sql)
+				.bind(guild_id.conv::<i64>())
+				.bind(name)
+				.execute(&mut *transaction)
+				.await?;
+		}
+
+		for (i, track) in tracks.iter().enumerate() {
+			sqlx::query(&add_track)
+				.bind(guild_id.conv::<i64>())
+				.bind(name)
+				.bind(i as i64)
+				.bind(track.source_url.as_str())
+				.bind(track.title.as_deref())
+				.execute(&mut *transaction)
+				.await?;
+		}
+
+		transaction.commit().await?;
+
+		Ok(())
+	}
+
+	async fn get_playlist(
+		&self,
+		guild_id: GuildId,
+		name: &str,
+	) -> Result<Option<Vec<PlaylistTrack>>, StorageError> {
+		let rows: Vec<(String, Option<String>)> = sqlx::query_as(&read_query("get-playlist.sql")?)
+			.bind(guild_id.conv::<i64>())
+			.bind(name)
+			.fetch_all(self)
+			.await?;
+
+		// playlists are never saved empty, so no tracks means no playlist
+		Ok((!rows.is_empty()).then(|| {
+			rows.into_iter()
+				.map(|(source_url, title)| PlaylistTrack { source_url, title })
+				.collect()
+		}))
+	}
+
+	async fn list_playlists(
+		&self,
+		guild_id: GuildId,
+	) -> Result<Vec<(String, usize)>, StorageError> {
+		let rows: Vec<(String, i64)> = sqlx::query_as(&read_query("list-playlists.sql")?)
+			.bind(guild_id.conv::<i64>())
+			.fetch_all(self)
+			.await?;
+
+		Ok(rows
+			.into_iter()
+			.map(|(name, count)| (name, count as usize))
+			.collect())
+	}
+
+	async fn delete_playlist(&self, guild_id: GuildId, name: &str) -> Result<(), StorageError> {
+		let delete_tracks = read_query("delete-playlist-tracks.sql")?;
+		let delete = read_query("delete-playlist.sql")?;
+
+		let mut transaction = self.begin().await?;
+
+		sqlx::query(&delete_tracks)
+			.bind(guild_id.conv::<i64>())
+			.bind(name)
+			.execute(&mut *transaction)
+			.await?;
+
+		let deleted = sqlx::query(&delete)
+			.bind(guild_id.conv::<i64>())
+			.bind(name)
+			.execute(&mut *transaction)
+			.await?;
+
+		if deleted.rows_affected() == 0 {
+			return Err(StorageError::NoRowsChanged);
+		}
+
+		transaction.commit().await?;
+
+		Ok(())
+	}
 }
 
 /// Generic implementation to get a single value by using an id.
@@ -7,13 +7,16 @@
 pub mod database;
 
 use std::fmt;
+use std::time::{Duration, SystemTime};
 
 use async_trait::async_trait;
 
-use serenity::model::id::{GuildId, UserId};
+use serenity::model::id::{GuildId, RoleId, UserId};
 
 use thiserror::Error;
 
+use crate::filter::Filters;
+
 #[derive(Debug, Error)]
 pub enum StorageError {
 	IoError(#[from] std::io::Error),
@@ -27,6 +30,71 @@ impl fmt::Display for StorageError {
 	}
 }
 
+/// A track from a guild's queue, with enough information to fetch it again
+/// after the queue is lost.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueuedTrack {
+	/// Url which the track can be fetched from again.
+	pub source_url: String,
+
+	/// Title of the track, if known.
+	pub title: Option<String>,
+
+	/// User who requested the track, or `None` if the bot queued it itself.
+	pub requester: Option<UserId>,
+
+	/// Position that playback had reached in the track.
+	pub position: Duration,
+
+	/// Number of loops remaining, or `None` if the track loops forever.
+	pub loops: Option<usize>,
+}
+
+/// Limits on what each user can have in a guild's queue at once.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueueLimits {
+	/// Number of tracks each user can have queued, or `None` for no limit.
+	pub tracks: Option<usize>,
+
+	/// Total length of the tracks each user can have queued, or `None` for no
+	/// limit.
+	pub duration: Option<Duration>,
+}
+
+/// Loudness measured for a clip, to normalize it without measuring it again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipLoudness {
+	/// Integrated loudness in LUFS, or `None` if the clip is silent.
+	pub loudness: Option<f32>,
+
+	/// When the clip was last modified as it was measured, so the loudness can
+	/// be measured again if it changes.
+	pub modified: SystemTime,
+}
+
+/// A track that was queued in a guild, for looking back at what has played.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HistoryEntry {
+	/// Url which the track can be fetched from again.
+	pub source_url: String,
+
+	/// Title of the track, if known.
+	pub title: Option<String>,
+
+	/// When the track was queued.
+	pub queued_at: SystemTime,
+}
+
+/// A track saved in a named playlist.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaylistTrack {
+	/// Url which the track can be fetched from again.
+	pub source_url: String,
+
+	/// Title of the track, if known.
+	pub title: Option<String>,
+}
+
 /// Generic trait that can be implemented for the storage.
 ///
 /// It is designed to be shared, thus uses a shared reference. You can always use a Mutex or
@@ -38,6 +106,11 @@ pub trait Storage {
 	/// create tables in a database or initialize a file.
 	async fn first_time_setup(&self) -> Result<(), StorageError>;
 
+	/// Bring storage set up by an older version of the bot up to date, for example by adding
+	/// columns to existing tables. This is called every time the bot starts, after
+	/// [`first_time_setup`] if that is called, so it must do nothing when already up to date.
+	async fn migrate(&self) -> Result<(), StorageError>;
+
 	/// Get the intro for a user. This will return exactly the value set using [`set_intro`].
 	async fn get_intro(&self, user_id: UserId) -> Result<Option<String>, StorageError>;
 
@@ -86,6 +159,148 @@ pub trait Storage {
 	/// This is different from audio clips that the bot stores (which should use
 	/// [`set_volume_clip`]).
 	async fn set_volume_play(&self, guild_id: GuildId, volume: f32) -> Result<(), StorageError>;
+
+	/// Get whether a related track should be queued when the queue of a guild runs out. Returns
+	/// whatever was set using [`set_autoplay`].
+	async fn get_autoplay(&self, guild_id: GuildId) -> Result<Option<bool>, StorageError>;
+
+	/// Set whether a related track should be queued when the queue of a guild runs out, which can
+	/// later be retrieved with [`get_autoplay`].
+	async fn set_autoplay(&self, guild_id: GuildId, autoplay: bool) -> Result<(), StorageError>;
+
+	/// Get whether tracks queued in a guild should take turns by requester. Returns whatever was
+	/// set using [`set_fair_queue`].
+	async fn get_fair_queue(&self, guild_id: GuildId) -> Result<Option<bool>, StorageError>;
+
+	/// Set whether tracks queued in a guild should take turns by requester, which can later be
+	/// retrieved with [`get_fair_queue`].
+	async fn set_fair_queue(&self, guild_id: GuildId, fair: bool) -> Result<(), StorageError>;
+
+	/// Get the limits on what each user can queue in a guild. Returns whatever was set using
+	/// [`set_queue_limits`], or no limits if nothing was set.
+	async fn get_queue_limits(&self, guild_id: GuildId) -> Result<QueueLimits, StorageError>;
+
+	/// Set the limits on what each user can queue in a guild, which can later be retrieved with
+	/// [`get_queue_limits`].
+	async fn set_queue_limits(
+		&self,
+		guild_id: GuildId,
+		limits: &QueueLimits,
+	) -> Result<(), StorageError>;
+
+	/// Get the audio filters applied to tracks played in a guild. Returns whatever filters were
+	/// set using [`set_filters`].
+	async fn get_filters(&self, guild_id: GuildId) -> Result<Option<Filters>, StorageError>;
+
+	/// Set the audio filters applied to tracks played in a guild, which can later be retrieved
+	/// with [`get_filters`].
+	async fn set_filters(&self, guild_id: GuildId, filters: &Filters) -> Result<(), StorageError>;
+
+	/// Get how long tracks in a guild's queue crossfade for. Returns whatever was set using
+	/// [`set_crossfade`].
+	async fn get_crossfade(&self, guild_id: GuildId) -> Result<Option<Duration>, StorageError>;
+
+	/// Set how long tracks in a guild's queue crossfade for, which can later be retrieved with
+	/// [`get_crossfade`].
+	async fn set_crossfade(
+		&self,
+		guild_id: GuildId,
+		crossfade: Duration,
+	) -> Result<(), StorageError>;
+
+	/// Get the volume tracks in a guild's queue are lowered to while clips play. Returns whatever
+	/// level was set using [`set_duck_level`].
+	async fn get_duck_level(&self, guild_id: GuildId) -> Result<Option<f32>, StorageError>;
+
+	/// Set the volume tracks in a guild's queue are lowered to while clips play, which can later
+	/// be retrieved with [`get_duck_level`].
+	async fn set_duck_level(&self, guild_id: GuildId, level: f32) -> Result<(), StorageError>;
+
+	/// Get the loudness measured for a clip, by its path within the clip directory. Returns
+	/// whatever was set using [`set_clip_loudness`].
+	async fn get_clip_loudness(&self, clip: &str) -> Result<Option<ClipLoudness>, StorageError>;
+
+	/// Set the loudness measured for a clip, by its path within the clip directory, which can
+	/// later be retrieved with [`get_clip_loudness`].
+	async fn set_clip_loudness(
+		&self,
+		clip: &str,
+		loudness: &ClipLoudness,
+	) -> Result<(), StorageError>;
+
+	/// Get the role allowed to control the whole queue of a guild. Returns whatever role was set
+	/// using [`set_dj_role`].
+	async fn get_dj_role(&self, guild_id: GuildId) -> Result<Option<RoleId>, StorageError>;
+
+	/// Set the role allowed to control the whole queue of a guild, which can later be retrieved
+	/// with [`get_dj_role`]. Setting `None` lets anyone control the queue.
+	async fn set_dj_role(
+		&self,
+		guild_id: GuildId,
+		role: Option<RoleId>,
+	) -> Result<(), StorageError>;
+
+	/// Get the percentage of listeners that must vote to skip a track. Returns whatever
+	/// percentage was set using [`set_skip_threshold`].
+	async fn get_skip_threshold(&self, guild_id: GuildId) -> Result<Option<u8>, StorageError>;
+
+	/// Set the percentage of listeners that must vote to skip a track, which can later be
+	/// retrieved with [`get_skip_threshold`].
+	async fn set_skip_threshold(&self, guild_id: GuildId, percent: u8) -> Result<(), StorageError>;
+
+	/// Get the queue saved for a guild using [`set_queue`], in queue order.
+	///
+	/// Returns an empty list if no queue has been saved.
+	async fn get_queue(&self, guild_id: GuildId) -> Result<Vec<QueuedTrack>, StorageError>;
+
+	/// Replace the saved queue for a guild, which can later be retrieved with [`get_queue`].
+	///
+	/// Saving an empty queue clears it.
+	async fn set_queue(&self, guild_id: GuildId, queue: &[QueuedTrack])
+	-> Result<(), StorageError>;
+
+	/// Record a track that was queued in a guild, which can later be retrieved with
+	/// [`get_history`].
+	async fn add_history(
+		&self,
+		guild_id: GuildId,
+		entry: &HistoryEntry,
+	) -> Result<(), StorageError>;
+
+	/// Get up to `limit` of the most recent tracks recorded with [`add_history`], most recent
+	/// first.
+	async fn get_history(
+		&self,
+		guild_id: GuildId,
+		limit: usize,
+	) -> Result<Vec<HistoryEntry>, StorageError>;
+
+	/// Save a named playlist for a guild, replacing any playlist with the same name. It can later
+	/// be retrieved with [`get_playlist`].
+	async fn save_playlist(
+		&self,
+		guild_id: GuildId,
+		name: &str,
+		tracks: &[PlaylistTrack],
+	) -> Result<(), StorageError>;
+
+	/// Get the tracks of a playlist saved using [`save_playlist`], in order.
+	///
+	/// Returns `None` if no playlist has that name.
+	async fn get_playlist(
+		&self,
+		guild_id: GuildId,
+		name: &str,
+	) -> Result<Option<Vec<PlaylistTrack>>, StorageError>;
+
+	/// List the name and number of tracks of every playlist saved for a guild, sorted by name.
+	async fn list_playlists(&self, guild_id: GuildId)
+	-> Result<Vec<(String, usize)>, StorageError>;
+
+	/// Delete a playlist saved using [`save_playlist`].
+	///
+	/// Returns [`StorageError::NoRowsChanged`] if no playlist has that name.
+	async fn delete_playlist(&self, guild_id: GuildId, name: &str) -> Result<(), StorageError>;
 }
 
 #[cfg(test)]
@@ -107,6 +322,7 @@ mod test {
 			.unwrap();
 
 		pool.first_time_setup().await.unwrap();
+		pool.migrate().await.unwrap();
 
 		Box::new(pool)
 	}
@@ -159,6 +375,129 @@ mod test {
 		assert_eq!(get, None)
 	}
 
+	#[tokio::test]
+	async fn get_autoplay_unset() {
+		let db = pool().await;
+
+		let get = db.get_autoplay(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_fair_queue_unset() {
+		let db = pool().await;
+
+		let get = db.get_fair_queue(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_queue_limits_unset() {
+		let db = pool().await;
+
+		let get = db.get_queue_limits(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, QueueLimits::default())
+	}
+
+	#[tokio::test]
+	async fn get_filters_unset() {
+		let db = pool().await;
+
+		let get = db.get_filters(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_clip_loudness_unset() {
+		let db = pool().await;
+
+		let get = db.get_clip_loudness("a/b.mp3").await.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_dj_role_unset() {
+		let db = pool().await;
+
+		let get = db.get_dj_role(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_skip_threshold_unset() {
+		let db = pool().await;
+
+		let get = db
+			.get_skip_threshold(GuildId::new(1))
+			.await
+			.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_crossfade_unset() {
+		let db = pool().await;
+
+		let get = db.get_crossfade(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_duck_level_unset() {
+		let db = pool().await;
+
+		let get = db.get_duck_level(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn get_queue_unset() {
+		let db = pool().await;
+
+		let get = db.get_queue(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, vec![])
+	}
+
+	#[tokio::test]
+	async fn get_history_unset() {
+		let db = pool().await;
+
+		let get = db.get_history(GuildId::new(1), 10).await.expect(ERROR_GET);
+
+		assert_eq!(get, vec![])
+	}
+
+	#[tokio::test]
+	async fn get_playlist_unset() {
+		let db = pool().await;
+
+		let get = db
+			.get_playlist(GuildId::new(1), "test")
+			.await
+			.expect(ERROR_GET);
+
+		assert_eq!(get, None)
+	}
+
+	#[tokio::test]
+	async fn list_playlists_unset() {
+		let db = pool().await;
+
+		let get = db.list_playlists(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, vec![])
+	}
+
 	#[tokio::test]
 	async fn set_get_intro() {
 		let db = pool().await;
@@ -215,6 +554,30 @@ mod test {
 		assert_eq!(get, Some(set));
 	}
 
+	#[tokio::test]
+	async fn set_get_clip_loudness() {
+		let db = pool().await;
+
+		for set in [
+			ClipLoudness {
+				loudness: Some(-14.5),
+				modified: SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000),
+			},
+			ClipLoudness {
+				loudness: None,
+				modified: SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_000),
+			},
+		] {
+			db.set_clip_loudness("a/b.mp3", &set)
+				.await
+				.expect(ERROR_SET);
+
+			let get = db.get_clip_loudness("a/b.mp3").await.expect(ERROR_GET);
+
+			assert_eq!(get, Some(set));
+		}
+	}
+
 	#[tokio::test]
 	async fn set_get_volume_play() {
 		let db = pool().await;
@@ -229,4 +592,284 @@ mod test {
 
 		assert_eq!(get, Some(set));
 	}
+
+	#[tokio::test]
+	async fn set_get_autoplay() {
+		let db = pool().await;
+
+		for set in [true, false] {
+			db.set_autoplay(GuildId::new(1), set)
+				.await
+				.expect(ERROR_SET);
+
+			let get = db.get_autoplay(GuildId::new(1)).await.expect(ERROR_GET);
+
+			assert_eq!(get, Some(set));
+		}
+	}
+
+	#[tokio::test]
+	async fn set_get_fair_queue() {
+		let db = pool().await;
+
+		for set in [true, false] {
+			db.set_fair_queue(GuildId::new(1), set)
+				.await
+				.expect(ERROR_SET);
+
+			let get = db.get_fair_queue(GuildId::new(1)).await.expect(ERROR_GET);
+
+			assert_eq!(get, Some(set));
+		}
+	}
+
+	#[tokio::test]
+	async fn set_get_queue_limits() {
+		let db = pool().await;
+
+		let set = QueueLimits {
+			tracks: Some(10),
+			duration: Some(Duration::from_secs(3600)),
+		};
+
+		db.set_queue_limits(GuildId::new(1), &set)
+			.await
+			.expect(ERROR_SET);
+
+		let get = db.get_queue_limits(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, set);
+	}
+
+	#[tokio::test]
+	async fn set_get_filters() {
+		let db = pool().await;
+
+		let set = Filters {
+			bass_boost: 6.0,
+			rate: 1.25,
+			speed: 0.75,
+			pitch: -2.0,
+			normalize: true,
+		};
+
+		db.set_filters(GuildId::new(1), &set)
+			.await
+			.expect(ERROR_SET);
+
+		let get = db.get_filters(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, Some(set));
+	}
+
+	#[tokio::test]
+	async fn set_get_dj_role() {
+		let db = pool().await;
+
+		for set in [Some(RoleId::new(2)), None] {
+			db.set_dj_role(GuildId::new(1), set).await.expect(ERROR_SET);
+
+			let get = db.get_dj_role(GuildId::new(1)).await.expect(ERROR_GET);
+
+			assert_eq!(get, set);
+		}
+	}
+
+	#[tokio::test]
+	async fn set_get_skip_threshold() {
+		let db = pool().await;
+
+		let set = 75;
+
+		db.set_skip_threshold(GuildId::new(1), set)
+			.await
+			.expect(ERROR_SET);
+
+		let get = db
+			.get_skip_threshold(GuildId::new(1))
+			.await
+			.expect(ERROR_GET);
+
+		assert_eq!(get, Some(set));
+	}
+
+	#[tokio::test]
+	async fn set_get_crossfade() {
+		let db = pool().await;
+
+		let set = Duration::from_millis(4500);
+
+		db.set_crossfade(GuildId::new(1), set)
+			.await
+			.expect(ERROR_SET);
+
+		let get = db.get_crossfade(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, Some(set));
+	}
+
+	#[tokio::test]
+	async fn set_get_duck_level() {
+		let db = pool().await;
+
+		let set = 0.25;
+
+		db.set_duck_level(GuildId::new(1), set)
+			.await
+			.expect(ERROR_SET);
+
+		let get = db.get_duck_level(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, Some(set));
+	}
+
+	#[tokio::test]
+	async fn set_get_queue() {
+		let db = pool().await;
+
+		let set = vec![
+			QueuedTrack {
+				source_url: "https://youtu.be/k2mFvwDTTt0".to_owned(),
+				title: Some("test".to_owned()),
+				requester: Some(UserId::new(2)),
+				position: Duration::from_millis(1234),
+				loops: None,
+			},
+			QueuedTrack {
+				source_url: "https://open.spotify.com/track/009bpReJuXgCv8G2MkJ5Y1".to_owned(),
+				title: None,
+				requester: None,
+				position: Duration::ZERO,
+				loops: Some(2),
+			},
+		];
+
+		db.set_queue(GuildId::new(1), &set).await.expect(ERROR_SET);
+
+		let get = db.get_queue(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, set);
+
+		db.set_queue(GuildId::new(1), &[]).await.expect(ERROR_SET);
+
+		let get = db.get_queue(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, vec![]);
+	}
+
+	#[tokio::test]
+	async fn add_get_history() {
+		let db = pool().await;
+
+		let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+		let entries = (0..3)
+			.map(|i| HistoryEntry {
+				source_url: format!("https://youtu.be/{i}"),
+				title: Some(i.to_string()),
+				queued_at: start + Duration::from_secs(i),
+			})
+			.collect::<Vec<_>>();
+
+		for entry in &entries {
+			db.add_history(GuildId::new(1), entry)
+				.await
+				.expect(ERROR_SET);
+		}
+
+		let get = db.get_history(GuildId::new(1), 2).await.expect(ERROR_GET);
+
+		assert_eq!(get, vec![entries[2].clone(), entries[1].clone()]);
+	}
+
+	#[tokio::test]
+	async fn save_get_playlist() {
+		let db = pool().await;
+
+		let set = vec![
+			PlaylistTrack {
+				source_url: "https://youtu.be/k2mFvwDTTt0".to_owned(),
+				title: Some("test".to_owned()),
+			},
+			PlaylistTrack {
+				source_url: "https://open.spotify.com/track/009bpReJuXgCv8G2MkJ5Y1".to_owned(),
+				title: None,
+			},
+		];
+
+		db.save_playlist(GuildId::new(1), "b", &set)
+			.await
+			.expect(ERROR_SET);
+		db.save_playlist(GuildId::new(1), "a", &set[..1])
+			.await
+			.expect(ERROR_SET);
+
+		let get = db
+			.get_playlist(GuildId::new(1), "b")
+			.await
+			.expect(ERROR_GET);
+
+		assert_eq!(get, Some(set.clone()));
+
+		let list = db.list_playlists(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(list, vec![("a".to_owned(), 1), ("b".to_owned(), 2)]);
+
+		// saving again replaces the playlist
+		db.save_playlist(GuildId::new(1), "b", &set[1..])
+			.await
+			.expect(ERROR_SET);
+
+		let get = db
+			.get_playlist(GuildId::new(1), "b")
+			.await
+			.expect(ERROR_GET);
+
+		assert_eq!(get, Some(set[1..].to_vec()));
+	}
+
+	#[tokio::test]
+	async fn delete_playlist() {
+		let db = pool().await;
+
+		let set = vec![PlaylistTrack {
+			source_url: "https://youtu.be/k2mFvwDTTt0".to_owned(),
+			title: None,
+		}];
+
+		db.save_playlist(GuildId::new(1), "test", &set)
+			.await
+			.expect(ERROR_SET);
+
+		db.delete_playlist(GuildId::new(1), "test")
+			.await
+			.expect(ERROR_SET);
+
+		let get = db
+			.get_playlist(GuildId::new(1), "test")
+			.await
+			.expect(ERROR_GET);
+
+		assert_eq!(get, None);
+
+		assert!(matches!(
+			db.delete_playlist(GuildId::new(1), "test").await,
+			Err(StorageError::NoRowsChanged)
+		));
+	}
+
+	#[tokio::test]
+	async fn migrate_twice() {
+		let db = pool().await;
+
+		db.migrate().await.expect("Error while migrating again");
+
+		db.set_autoplay(GuildId::new(1), true)
+			.await
+			.expect(ERROR_SET);
+
+		let get = db.get_autoplay(GuildId::new(1)).await.expect(ERROR_GET);
+
+		assert_eq!(get, Some(true));
+	}
 }
@@ -0,0 +1,506 @@
+//! Prepare queued tracks before they play, so that there is no gap between
+//! songs while yt-dlp runs.
+//!
+//! Queued tracks are wrapped in [`ComposePrefetch`], and while a track plays
+//! the next few in the queue are created ahead of time with
+//! [`Compose::create_async`], see [`prefetch_queue`]. The number of tracks is
+//! set by [`crate::configuration::PrefetchConfig::depth`].
+//!
+//! Streamed audio is also read ahead into memory by [`ReadAhead`], which
+//! starts as soon as the track is created. All read ahead audio together is
+//! kept under [`crate::configuration::PrefetchConfig::memory`].
+
+use async_trait::async_trait;
+
+use songbird::input::{AudioStream, AudioStreamError, AuxMetadata, Compose, Input};
+use songbird::tracks::TrackQueue;
+
+use symphonia::core::io::MediaSource;
+
+use tokio::sync::Mutex as AsyncMutex;
+
+use tracing::{debug, error};
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::CONFIG;
+use crate::data::TrackData;
+
+/// Most bytes read ahead of a single stream.
+const READ_AHEAD_LEN: usize = 8 * 1024 * 1024;
+
+/// Bytes read from a stream at once while reading ahead.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// How long reading ahead waits before checking for room again, when the
+/// memory cap is reached by other streams.
+const WAIT_FOR_ROOM: Duration = Duration::from_millis(250);
+
+/// Bytes read ahead by every [`ReadAhead`] together.
+static BUFFERED: AtomicUsize = AtomicUsize::new(0);
+
+/// Created stream of a track, or what creates it if it has not been created.
+struct Slot {
+	compose: Box<dyn Compose>,
+	stream: Option<AudioStream<Box<dyn MediaSource>>>,
+
+	/// Whether the track has already been prefetched, even if it failed, so
+	/// that it is only tried once.
+	prefetched: bool,
+}
+
+/// Handle to create a queued track ahead of time, which is kept with its
+/// [`TrackData`].
+#[derive(Clone)]
+pub struct Prefetch {
+	slot: Arc<AsyncMutex<Slot>>,
+}
+
+impl fmt::Debug for Prefetch {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Prefetch").finish_non_exhaustive()
+	}
+}
+
+impl Prefetch {
+	/// Create the track in the background, unless it already has been.
+	pub fn start(&self) {
+		let slot = self.slot.clone();
+
+		tokio::spawn(async move {
+			let mut slot = slot.lock().await;
+
+			if slot.prefetched {
+				return;
+			}
+
+			slot.prefetched = true;
+
+			debug!("Prefetching queued track");
+
+			// a failure is tried again once the track plays
+			match slot.compose.create_async().await {
+				Ok(stream) => slot.stream = Some(stream),
+				Err(e) => error!("Unable to prefetch track: {:?}", e),
+			}
+		});
+	}
+}
+
+/// Wraps a [`Compose`] so that it can be created ahead of time with a
+/// [`Prefetch`], handing over the stream created then when it is played.
+pub struct ComposePrefetch {
+	slot: Arc<AsyncMutex<Slot>>,
+	create_async: bool,
+}
+
+#[async_trait]
+impl Compose for ComposePrefetch {
+	fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+		let mut slot = self.slot.blocking_lock();
+
+		match slot.stream.take() {
+			Some(stream) => Ok(stream),
+			None => slot.compose.create(),
+		}
+	}
+
+	async fn create_async(
+		&mut self,
+	) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+		// waits for a prefetch that has already started
+		let mut slot = self.slot.lock().await;
+
+		match slot.stream.take() {
+			Some(stream) => Ok(stream),
+			None => slot.compose.create_async().await,
+		}
+	}
+
+	fn should_create_async(&self) -> bool {
+		self.create_async
+	}
+
+	async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+		self.slot.lock().await.compose.aux_metadata().await
+	}
+}
+
+/// Wrap `input` so that it can be created ahead of time with the returned
+/// [`Prefetch`].
+///
+/// Only inputs which have not been created yet can be prefetched, so any other
+/// input is returned unchanged, without a [`Prefetch`].
+pub fn wrap(input: Input) -> (Input, Option<Prefetch>) {
+	match input {
+		Input::Lazy(compose) => {
+			let create_async = compose.should_create_async();
+
+			let slot = Arc::new(AsyncMutex::new(Slot {
+				compose,
+				stream: None,
+				prefetched: false,
+			}));
+
+			let prefetch = Prefetch { slot: slot.clone() };
+
+			(
+				Input::Lazy(Box::new(ComposePrefetch { slot, create_async })),
+				Some(prefetch),
+			)
+		}
+		input => (input, None),
+	}
+}
+
+/// Prefetch the tracks after the current one in `queue`, up to the configured
+/// depth.
+pub fn prefetch_queue(queue: &TrackQueue) {
+	for handle in queue
+		.current_queue()
+		.iter()
+		.skip(1)
+		.take(CONFIG.prefetch.depth)
+	{
+		if let Some(prefetch) = &handle.data::<TrackData>().prefetch {
+			prefetch.start();
+		}
+	}
+}
+
+/// Most bytes read ahead of all streams together, as configured.
+fn memory_cap() -> usize {
+	CONFIG.prefetch.memory * 1024 * 1024
+}
+
+/// State shared between a [`ReadAhead`] and the thread reading for it.
+struct Shared {
+	/// Stream being read ahead of, which is locked while reading from it.
+	inner: Mutex<Box<dyn MediaSource>>,
+
+	/// Bytes read from `inner` which have not been read from the
+	/// [`ReadAhead`] yet.
+	buffer: Mutex<VecDeque<u8>>,
+
+	/// Notified when bytes are taken from `buffer`, or it is closed.
+	room: Condvar,
+
+	closed: AtomicBool,
+}
+
+impl Shared {
+	fn buffer(&self) -> MutexGuard<'_, VecDeque<u8>> {
+		self.buffer.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	fn inner(&self) -> MutexGuard<'_, Box<dyn MediaSource>> {
+		self.inner.lock().unwrap_or_else(|e| e.into_inner())
+	}
+
+	/// Take bytes from the front of `buffer` into `buf`.
+	fn take(&self, buffer: &mut VecDeque<u8>, buf: &mut [u8]) -> usize {
+		let len = buffer.read(buf).unwrap_or_default();
+
+		BUFFERED.fetch_sub(len, Ordering::Relaxed);
+		self.room.notify_all();
+
+		len
+	}
+
+	/// Read ahead of the stream until it ends or the [`ReadAhead`] is dropped.
+	fn read_ahead(&self) {
+		let mut chunk = vec![0; CHUNK_LEN];
+
+		while !self.closed.load(Ordering::Relaxed) {
+			{
+				let buffer = self.buffer();
+
+				if buffer.len() + CHUNK_LEN > READ_AHEAD_LEN
+					|| BUFFERED.load(Ordering::Relaxed) + CHUNK_LEN > memory_cap()
+				{
+					let _ = self.room.wait_timeout(buffer, WAIT_FOR_ROOM);
+					continue;
+				}
+			}
+
+			// the stream stays locked until the bytes are buffered, so that
+			// reads straight from the stream cannot skip ahead of them
+			let mut inner = self.inner();
+
+			let len = match inner.read(&mut chunk) {
+				Ok(0) => break,
+				Ok(len) => len,
+				Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+				// reading straight from the stream reports the error
+				Err(_) => break,
+			};
+
+			// checked again under the lock, since once the ReadAhead is dropped
+			// nothing would take these bytes off the count
+			let mut buffer = self.buffer();
+
+			if self.closed.load(Ordering::Relaxed) {
+				break;
+			}
+
+			buffer.extend(&chunk[..len]);
+			BUFFERED.fetch_add(len, Ordering::Relaxed);
+		}
+	}
+}
+
+/// Wraps a [`MediaSource`] so that it is read ahead into memory by another
+/// thread, smoothing over a slow network.
+pub struct ReadAhead {
+	shared: Arc<Shared>,
+}
+
+impl ReadAhead {
+	pub fn new(inner: Box<dyn MediaSource>) -> Self {
+		let shared = Arc::new(Shared {
+			inner: Mutex::new(inner),
+			buffer: Mutex::new(VecDeque::new()),
+			room: Condvar::new(),
+			closed: AtomicBool::new(false),
+		});
+
+		let thread_shared = shared.clone();
+
+		std::thread::Builder::new()
+			.name("read-ahead".to_owned())
+			.spawn(move || thread_shared.read_ahead())
+			.expect("Unable to start reading ahead");
+
+		Self { shared }
+	}
+}
+
+impl Read for ReadAhead {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		{
+			let mut buffer = self.shared.buffer();
+
+			if !buffer.is_empty() {
+				return Ok(self.shared.take(&mut buffer, buf));
+			}
+		}
+
+		// nothing buffered, so wait for any read in progress to finish
+		let mut inner = self.shared.inner();
+		let mut buffer = self.shared.buffer();
+
+		if !buffer.is_empty() {
+			return Ok(self.shared.take(&mut buffer, buf));
+		}
+
+		drop(buffer);
+
+		inner.read(buf)
+	}
+}
+
+impl Seek for ReadAhead {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let mut inner = self.shared.inner();
+		let mut buffer = self.shared.buffer();
+
+		// the stream is ahead of the reader by what is buffered
+		let pos = match pos {
+			SeekFrom::Current(offset) => SeekFrom::Current(offset - buffer.len() as i64),
+			pos => pos,
+		};
+
+		let position = inner.seek(pos)?;
+
+		BUFFERED.fetch_sub(buffer.len(), Ordering::Relaxed);
+		buffer.clear();
+		self.shared.room.notify_all();
+
+		Ok(position)
+	}
+}
+
+impl MediaSource for ReadAhead {
+	// these may block, such as for songbird's async adapter, so they are only
+	// asked for by the reader rather than when created
+	fn is_seekable(&self) -> bool {
+		self.shared.inner().is_seekable()
+	}
+
+	fn byte_len(&self) -> Option<u64> {
+		self.shared.inner().byte_len()
+	}
+}
+
+impl Drop for ReadAhead {
+	fn drop(&mut self) {
+		self.shared.closed.store(true, Ordering::Relaxed);
+
+		let mut buffer = self.shared.buffer();
+		BUFFERED.fetch_sub(buffer.len(), Ordering::Relaxed);
+		buffer.clear();
+
+		self.shared.room.notify_all();
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use std::io::Cursor;
+	use std::sync::atomic::AtomicUsize;
+	use std::sync::mpsc::{self, Receiver, SyncSender};
+
+	/// Held by tests which check [`BUFFERED`], which every stream counts towards.
+	static BUFFERED_LOCK: Mutex<()> = Mutex::new(());
+
+	fn bytes() -> Vec<u8> {
+		(0..3 * CHUNK_LEN + 123).map(|i| (i % 251) as u8).collect()
+	}
+
+	#[test]
+	fn read_ahead_matches_source() {
+		let _lock = BUFFERED_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+		let bytes = bytes();
+
+		let mut source = ReadAhead::new(Box::new(Cursor::new(bytes.clone())));
+
+		// let it read ahead
+		std::thread::sleep(Duration::from_millis(50));
+
+		let mut start = vec![0; 1000];
+		source.read_exact(&mut start).unwrap();
+		assert_eq!(start, bytes[..1000]);
+
+		assert_eq!(source.seek(SeekFrom::Current(500)).unwrap(), 1500);
+
+		let mut rest = Vec::new();
+		source.read_to_end(&mut rest).unwrap();
+		assert_eq!(rest, bytes[1500..]);
+
+		assert_eq!(source.seek(SeekFrom::Start(10)).unwrap(), 10);
+
+		let mut again = vec![0; 10];
+		source.read_exact(&mut again).unwrap();
+		assert_eq!(again, bytes[10..20]);
+	}
+
+	/// blocks each read until released, and reports when it is dropped
+	struct Gated {
+		reading: SyncSender<()>,
+		release: Mutex<Receiver<()>>,
+		dropped: SyncSender<()>,
+	}
+
+	impl Read for Gated {
+		fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+			let _ = self.reading.send(());
+			let _ = self.release.lock().unwrap().recv();
+
+			buf.fill(1);
+			Ok(buf.len())
+		}
+	}
+
+	impl Seek for Gated {
+		fn seek(&mut self, _: SeekFrom) -> io::Result<u64> {
+			Err(io::ErrorKind::Unsupported.into())
+		}
+	}
+
+	impl MediaSource for Gated {
+		fn is_seekable(&self) -> bool {
+			false
+		}
+
+		fn byte_len(&self) -> Option<u64> {
+			None
+		}
+	}
+
+	impl Drop for Gated {
+		fn drop(&mut self) {
+			let _ = self.dropped.send(());
+		}
+	}
+
+	#[test]
+	fn dropped_mid_read() {
+		let _lock = BUFFERED_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+		let (reading, is_reading) = mpsc::sync_channel(1);
+		let (release, released) = mpsc::sync_channel(1);
+		let (dropped, is_dropped) = mpsc::sync_channel(1);
+
+		let source = ReadAhead::new(Box::new(Gated {
+			reading,
+			release: Mutex::new(released),
+			dropped,
+		}));
+
+		is_reading.recv().unwrap();
+		drop(source);
+		release.send(()).unwrap();
+
+		// the stream is dropped once reading ahead stops
+		is_dropped.recv_timeout(Duration::from_secs(5)).unwrap();
+		assert_eq!(BUFFERED.load(Ordering::Relaxed), 0);
+	}
+
+	/// counts how many times it is created
+	struct Counted(Arc<AtomicUsize>);
+
+	#[async_trait]
+	impl Compose for Counted {
+		fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+			Err(AudioStreamError::Unsupported)
+		}
+
+		async fn create_async(
+			&mut self,
+		) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+			self.0.fetch_add(1, Ordering::Relaxed);
+
+			Ok(AudioStream {
+				input: Box::new(Cursor::new(bytes())),
+				hint: None,
+			})
+		}
+
+		fn should_create_async(&self) -> bool {
+			true
+		}
+	}
+
+	#[tokio::test]
+	async fn prefetched_once() {
+		let created = Arc::new(AtomicUsize::new(0));
+
+		let (input, prefetch) = wrap(Input::Lazy(Box::new(Counted(created.clone()))));
+		let prefetch = prefetch.unwrap();
+
+		prefetch.start();
+		prefetch.start();
+
+		// let the prefetch run
+		tokio::time::sleep(Duration::from_millis(50)).await;
+
+		let Input::Lazy(mut compose) = input else {
+			panic!("Expected a lazy input");
+		};
+
+		compose.create_async().await.unwrap();
+		assert_eq!(created.load(Ordering::Relaxed), 1);
+
+		// created again when the prefetched stream is used up, such as to seek
+		compose.create_async().await.unwrap();
+		assert_eq!(created.load(Ordering::Relaxed), 2);
+	}
+}
@@ -0,0 +1,60 @@
+//! Local clips, found by searching with [`search_clips`] or by their exact
+//! name.
+
+use rand::seq::IteratorRandom;
+
+use songbird::input::{AuxMetadata, File};
+
+use std::ffi::OsString;
+
+use crate::audio::{
+	AudioError, ComposeWithMetadata, SearchSource, SourceInfo, get_clip, search_clips,
+};
+
+use super::{SourceQuery, SourceResolver, async_trait};
+
+/// Resolves [`SearchSource::Local`] searches to a random best match, and
+/// queries without a search location to the clip with that exact name.
+pub struct ClipResolver;
+
+#[async_trait]
+impl SourceResolver for ClipResolver {
+	fn name(&self) -> &'static str {
+		"clip"
+	}
+
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError> {
+		let clip_name = if query.is_search_in(Some(SearchSource::Local)) {
+			search_clips(query.guild_id, query.loc.as_ref())
+				.into_iter()
+				.choose(&mut rand::rng())
+				.ok_or(AudioError::NotFound)?
+		} else if query.is_search_in(None) {
+			// try to get an exact match on the clip, else fail
+			OsString::from(query.loc)
+		} else {
+			return Ok(None);
+		};
+
+		let clip = get_clip(query.guild_id, &clip_name).ok_or(AudioError::NotFound)?;
+
+		let title = clip_name.to_string_lossy().into_owned();
+
+		let compose = ComposeWithMetadata::new(
+			File::new(clip.clone()),
+			AuxMetadata {
+				title: Some(title.clone()),
+				..Default::default()
+			},
+		);
+
+		Ok(Some(SourceInfo {
+			title: Some(title),
+			url: None,
+			count: 1,
+			duration: None,
+			clip: Some(clip.into()),
+			inputs: Box::new(std::iter::once_with(|| compose.into())),
+		}))
+	}
+}
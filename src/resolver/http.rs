@@ -0,0 +1,57 @@
+//! Audio files at any other url, which are streamed as they are.
+
+use songbird::input::{AuxMetadata, Compose, HttpRequest};
+
+use tracing::error;
+
+use crate::REQWEST_CLIENT;
+use crate::audio::{AudioError, ComposeWithMetadata, SourceInfo};
+
+use super::{SourceQuery, SourceResolver, async_trait};
+
+/// Resolves any url as an audio file, named after the last segment of its
+/// path.
+pub struct HttpResolver;
+
+#[async_trait]
+impl SourceResolver for HttpResolver {
+	fn name(&self) -> &'static str {
+		"http"
+	}
+
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError> {
+		let Some(url) = query.url() else {
+			return Ok(None);
+		};
+
+		let loc = query.loc;
+
+		// arbitrary audio file url
+		let title = url
+			.path_segments()
+			.map(|mut p| p.nth_back(0).unwrap().to_string());
+
+		let mut compose = ComposeWithMetadata::new(
+			HttpRequest::new(REQWEST_CLIENT.clone(), loc.to_string()),
+			AuxMetadata {
+				title: title.clone(),
+				source_url: Some(loc.to_string()),
+				..Default::default()
+			},
+		);
+
+		let aux_metadata = compose
+			.aux_metadata()
+			.await
+			.inspect_err(|e| error!("Error getting metadata: {:?}", e))?;
+
+		Ok(Some(SourceInfo {
+			title,
+			url: Some(loc.to_string()),
+			count: 1,
+			duration: aux_metadata.duration,
+			clip: None,
+			inputs: Box::new(std::iter::once_with(|| compose.into())),
+		}))
+	}
+}
@@ -0,0 +1,43 @@
+//! Tracks in the local music library, see [`crate::library`].
+
+use crate::audio::{AudioError, ComposeWithMetadata, SearchSource, SourceInfo};
+use crate::library;
+
+use super::{SourceQuery, SourceResolver, async_trait};
+
+/// Resolves [`SearchSource::Library`] searches which match a track in the
+/// library, leaving the rest for [`super::search::SearchResolver`].
+pub struct LibraryResolver;
+
+#[async_trait]
+impl SourceResolver for LibraryResolver {
+	fn name(&self) -> &'static str {
+		"library"
+	}
+
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError> {
+		if !query.is_search_in(Some(SearchSource::Library)) {
+			return Ok(None);
+		}
+
+		let track = match library::get(query.loc).await {
+			Some(track) => Some(track),
+			None => library::search(query.loc).await,
+		};
+
+		let Some(track) = track else {
+			return Ok(None);
+		};
+
+		let compose = ComposeWithMetadata::from(track);
+
+		Ok(Some(SourceInfo {
+			title: Some(track.display_title()),
+			url: None,
+			count: 1,
+			duration: track.duration,
+			clip: None,
+			inputs: Box::new(std::iter::once_with(|| compose.into())),
+		}))
+	}
+}
@@ -0,0 +1,295 @@
+//! Turn what was asked to be played into a [`SourceInfo`] using a
+//! [`SourceResolver`] for each kind of source.
+//!
+//! Resolvers are tried in order, and the first to recognise the query resolves
+//! it. The order can be changed with [`crate::configuration::Config::sources`],
+//! though a resolver which accepts any url, like [`http::HttpResolver`], should
+//! come after those for particular sites.
+pub mod clip;
+pub mod http;
+pub mod library;
+pub mod search;
+pub mod spotify;
+pub mod youtube;
+pub mod ytdl;
+
+use async_trait::async_trait;
+
+use regex::Regex;
+
+use reqwest::Url;
+
+use serenity::model::id::GuildId;
+
+use tracing::warn;
+
+use std::sync::LazyLock;
+
+use crate::CONFIG;
+use crate::audio::{AudioError, SearchSource, SourceInfo, is_url};
+use crate::data::{ArcRw, Keys};
+
+/// Every resolver, in the order they are tried.
+pub static RESOLVERS: LazyLock<Vec<Box<dyn SourceResolver + Send + Sync>>> =
+	LazyLock::new(|| resolvers(CONFIG.sources.as_deref().unwrap_or_default()));
+
+/// What was asked to be played, with the context it was asked in.
+#[derive(Clone)]
+pub struct SourceQuery<'a> {
+	pub keys: ArcRw<Keys>,
+
+	/// Guild the query was made in, whose clips are found before the shared
+	/// clips.
+	pub guild_id: Option<GuildId>,
+
+	/// Url or search term.
+	pub loc: &'a str,
+
+	/// Whether more than a single source can be resolved.
+	pub allow_playlist: bool,
+
+	/// Where to search when `loc` is not a url.
+	pub search_location: Option<SearchSource>,
+}
+
+impl SourceQuery<'_> {
+	/// Parse the query as a url, or `None` if it is not one.
+	pub fn url(&self) -> Option<Url> {
+		is_url(self.loc)
+			.then(|| Url::parse(self.loc).ok())
+			.flatten()
+	}
+
+	/// Parse the query as a url if its host matches `host`.
+	pub fn url_with_host(&self, host: &Regex) -> Option<Url> {
+		self.url()
+			.filter(|url| url.host_str().is_some_and(|h| host.is_match(h)))
+	}
+
+	/// Check if the query is a search in `location`, rather than a url.
+	pub fn is_search_in(&self, location: Option<SearchSource>) -> bool {
+		!is_url(self.loc) && self.search_location == location
+	}
+}
+
+/// Somewhere audio can be played from.
+#[async_trait]
+pub trait SourceResolver {
+	/// Name of the resolver, used to order resolvers in the configuration.
+	fn name(&self) -> &'static str;
+
+	/// Resolve `query`, or `None` if it is not for this resolver so that the
+	/// next resolver can try.
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError>;
+}
+
+/// Create every resolver, in the order they are tried when none are
+/// configured.
+fn default_resolvers() -> Vec<Box<dyn SourceResolver + Send + Sync>> {
+	vec![
+		Box::new(youtube::YoutubeResolver),
+		Box::new(spotify::SpotifyResolver),
+		Box::new(ytdl::YtdlResolver),
+		Box::new(http::HttpResolver),
+		Box::new(library::LibraryResolver),
+		Box::new(search::SearchResolver),
+		Box::new(clip::ClipResolver),
+	]
+}
+
+/// Create every resolver, with those named in `order` first in that order,
+/// followed by the rest in their default order.
+///
+/// Unknown names are logged and ignored.
+pub fn resolvers(order: &[String]) -> Vec<Box<dyn SourceResolver + Send + Sync>> {
+	let mut rest = default_resolvers();
+	let mut ordered = Vec::with_capacity(rest.len());
+
+	for name in order {
+		match rest.iter().position(|r| r.name() == name) {
+			Some(i) => ordered.push(rest.remove(i)),
+			None => warn!("Unknown or repeated source {:?}", name),
+		}
+	}
+
+	ordered.append(&mut rest);
+
+	ordered
+}
+
+/// Resolve `query` with the first resolver in `resolvers` which recognises it.
+///
+/// Queries no resolver recognises are an [`AudioError::UnsupportedUrl`] if
+/// they are a url, and an [`AudioError::NotFound`] otherwise.
+pub async fn resolve(
+	resolvers: &[Box<dyn SourceResolver + Send + Sync>],
+	query: &SourceQuery<'_>,
+) -> Result<SourceInfo, AudioError> {
+	for resolver in resolvers {
+		if let Some(info) = resolver.resolve(query).await? {
+			return Ok(info);
+		}
+	}
+
+	Err(if is_url(query.loc) {
+		AudioError::UnsupportedUrl
+	} else {
+		AudioError::NotFound
+	})
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use songbird::input::Input;
+
+	use std::io::Read;
+	use std::path::PathBuf;
+	use std::sync::Arc;
+
+	use crate::audio::{CLIP_PATH, clip_iter};
+	use crate::data::{DatabaseKeys, DiscordApi};
+	use crate::util::mock::MockServer;
+
+	/// keys without any apis set up
+	fn keys() -> ArcRw<Keys> {
+		Arc::new(
+			Keys {
+				database: DatabaseKeys {
+					connect_string: String::new(),
+				},
+				discord: DiscordApi {
+					application_id: 0,
+					token: String::new(),
+				},
+				youtube: None,
+				spotify: None,
+			}
+			.into(),
+		)
+	}
+
+	fn query(
+		keys: ArcRw<Keys>,
+		loc: &str,
+		search_location: Option<SearchSource>,
+	) -> SourceQuery<'_> {
+		SourceQuery {
+			keys,
+			guild_id: None,
+			loc,
+			allow_playlist: true,
+			search_location,
+		}
+	}
+
+	#[test]
+	fn resolver_order() {
+		let names = |order: &[&str]| {
+			resolvers(&order.iter().map(|s| s.to_string()).collect::<Vec<_>>())
+				.iter()
+				.map(|r| r.name())
+				.collect::<Vec<_>>()
+		};
+
+		assert_eq!(
+			names(&[]),
+			[
+				"youtube", "spotify", "ytdl", "http", "library", "search", "clip"
+			]
+		);
+		assert_eq!(
+			names(&["clip", "nonsense", "http", "clip"]),
+			[
+				"clip", "http", "youtube", "spotify", "ytdl", "library", "search"
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn resolve_sites() {
+		let resolvers = resolvers(&[]);
+
+		let result = resolve(
+			&resolvers,
+			&query(keys(), "https://www.youtube.com/watch?v=k2mFvwDTTt0", None),
+		)
+		.await;
+		assert!(matches!(result, Err(AudioError::YoutubeApiKey)));
+
+		let result = resolve(
+			&resolvers,
+			&query(
+				keys(),
+				"https://open.spotify.com/track/009bpReJuXgCv8G2MkJ5Y1",
+				None,
+			),
+		)
+		.await;
+		assert!(matches!(result, Err(AudioError::Spotify)));
+
+		let result = resolve(
+			&resolvers,
+			&query(keys(), "https://soundcloud.com/forss", None),
+		)
+		.await;
+		assert!(matches!(result, Err(AudioError::UnsupportedUrl)));
+
+		let result = resolve(&resolvers, &query(keys(), "not a clip at all", None)).await;
+		assert!(matches!(result, Err(AudioError::NotFound)));
+	}
+
+	#[tokio::test]
+	async fn resolve_clip() {
+		let clip = clip_iter(None)
+			.map(PathBuf::from)
+			.find(|path| path.extension().is_some_and(|e| e == "mp3" || e == "wav"))
+			.expect("No clips found")
+			.with_extension("");
+		let loc = clip.to_string_lossy();
+
+		let info = resolve(&resolvers(&[]), &query(keys(), &loc, None))
+			.await
+			.unwrap();
+
+		assert_eq!(info.title.as_deref(), Some(&*loc));
+		assert!(info.clip.is_some_and(|path| path.starts_with(&*CLIP_PATH)));
+	}
+
+	#[tokio::test]
+	async fn resolve_http() {
+		let path = clip_iter(None)
+			.map(|clip| CLIP_PATH.join(clip))
+			.find(|path| path.extension().is_some_and(|e| e == "mp3"))
+			.expect("No mp3 clips found");
+		let body = std::fs::read(&path).unwrap();
+
+		let base_url = MockServer::new()
+			.body("/audio/clip.mp3", "audio/mpeg", body.clone())
+			.start()
+			.await;
+		let url = format!("{}/audio/clip.mp3", base_url);
+
+		let mut info = resolve(&resolvers(&[]), &query(keys(), &url, None))
+			.await
+			.unwrap();
+
+		assert_eq!(info.title.as_deref(), Some("clip.mp3"));
+		assert_eq!(info.url.as_deref(), Some(&*url));
+		assert_eq!(info.count, 1);
+
+		let Some(Input::Lazy(mut compose)) = info.inputs.next() else {
+			panic!("Expected a lazy input");
+		};
+
+		let mut stream = compose.create_async().await.unwrap().input;
+
+		let mut read = Vec::new();
+		tokio::task::spawn_blocking(move || stream.read_to_end(&mut read).map(|_| read))
+			.await
+			.unwrap()
+			.map(|read| assert_eq!(read, body))
+			.unwrap();
+	}
+}
@@ -0,0 +1,45 @@
+//! Searches on YouTube, which play the first match.
+
+use songbird::input::Compose;
+
+use tracing::error;
+
+use crate::audio::{AudioError, SearchSource, SourceInfo};
+use crate::youtube::compose_yt_search;
+
+use super::{SourceQuery, SourceResolver, async_trait};
+
+/// Resolves [`SearchSource::Youtube`] searches, and
+/// [`SearchSource::Library`] searches which found nothing in the library.
+pub struct SearchResolver;
+
+#[async_trait]
+impl SourceResolver for SearchResolver {
+	fn name(&self) -> &'static str {
+		"search"
+	}
+
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError> {
+		if !query.is_search_in(Some(SearchSource::Youtube))
+			&& !query.is_search_in(Some(SearchSource::Library))
+		{
+			return Ok(None);
+		}
+
+		let mut compose = compose_yt_search(query.loc.to_string());
+
+		let aux_metadata = compose
+			.aux_metadata()
+			.await
+			.inspect_err(|e| error!("Error getting metadata: {:?}", e))?;
+
+		Ok(Some(SourceInfo {
+			title: aux_metadata.title,
+			url: aux_metadata.source_url,
+			count: 1,
+			duration: aux_metadata.duration,
+			clip: None,
+			inputs: Box::new(std::iter::once_with(|| compose.into())),
+		}))
+	}
+}
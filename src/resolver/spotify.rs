@@ -0,0 +1,262 @@
+//! Tracks, albums, playlists, artists and podcasts on Spotify.
+//!
+//! Spotify does not stream its audio, so each track is searched for on YouTube
+//! from its metadata.
+
+use futures::TryStreamExt;
+
+use itertools::Itertools;
+
+use regex::Regex;
+
+use reqwest::Url;
+
+use songbird::input::Input;
+
+use tracing::error;
+
+use std::sync::LazyLock;
+
+use crate::audio::{AudioError, ComposeWithMetadata, SourceInfo};
+use crate::spotify;
+
+use super::{SourceQuery, SourceResolver, async_trait};
+
+/// Regular expression which matches the host portion of a url if the host is spotify.
+static SPOTIFY_HOST: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new("^open\\.spotify\\.com").unwrap());
+
+/// Most episodes of a show which are queued at once, starting from the latest.
+const MAX_SHOW_EPISODES: usize = 50;
+
+/// Resolves urls with a host matching [`SPOTIFY_HOST`].
+pub struct SpotifyResolver;
+
+#[async_trait]
+impl SourceResolver for SpotifyResolver {
+	fn name(&self) -> &'static str {
+		"spotify"
+	}
+
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError> {
+		match query.url_with_host(&SPOTIFY_HOST) {
+			Some(url) => resolve_url(query, &url).await.map(Some),
+			None => Ok(None),
+		}
+	}
+}
+
+async fn resolve_url(query: &SourceQuery<'_>, url: &Url) -> Result<SourceInfo, AudioError> {
+	let mut path_segments = url.path_segments().ok_or(AudioError::UnsupportedUrl)?;
+
+	let client = query
+		.keys
+		.write()
+		.await
+		.spotify
+		.as_mut()
+		.ok_or(AudioError::Spotify)?
+		.client()
+		.await
+		.map_err(|_| AudioError::Spotify)?;
+
+	match path_segments.next().ok_or(AudioError::UnsupportedUrl)? {
+		"track" => {
+			// spotify single song
+			let track_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
+
+			let track = spotify::track(&client, track_id)
+				.await
+				.inspect_err(|e| error!("Error reading spotify track: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?;
+
+			let compose = ComposeWithMetadata::from(&track);
+
+			Ok(SourceInfo {
+				title: Some(track.name),
+				url: Some(query.loc.to_string()),
+				count: 1,
+				duration: None,
+				clip: None,
+				inputs: Box::new(std::iter::once_with(|| compose.into())),
+			})
+		}
+		"playlist" => {
+			// spotify playlist
+			if !query.allow_playlist {
+				return Err(AudioError::PlaylistNotAllowed);
+			}
+
+			let playlist_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
+
+			// First try the api
+			let playlist = match spotify::playlist(&client, playlist_id).await {
+				Ok(v) => v,
+				Err(e) => {
+					// Fall back to parsing an embed in case spotify does not display that
+					// playlist on the api
+					spotify::scrape::playlist(&client, playlist_id)
+						.await
+						.inspect_err(|e_scrape| {
+							error!("Error reading spotify playlist: {:?}", e);
+							error!("Error scraping spotify playlist: {:?}", e_scrape);
+						})
+						.map_err(|_| AudioError::Spotify)?
+				}
+			};
+
+			let items = spotify::into_stream(&client, playlist.tracks)
+				.map_ok(|playlist_tracks| playlist_tracks.items)
+				.try_concat()
+				.await
+				.inspect_err(|e| error!("Error in spotify data api for playlist items: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?;
+
+			Ok(SourceInfo {
+				title: Some(playlist.name),
+				url: Some(query.loc.to_string()),
+				count: items.len(),
+				duration: None,
+				clip: None,
+				inputs: Box::new(
+					items
+						.into_iter()
+						.map(|t| Input::from(ComposeWithMetadata::from(&t.track))),
+				),
+			})
+		}
+		"album" => {
+			// spotify playlist
+			if !query.allow_playlist {
+				return Err(AudioError::PlaylistNotAllowed);
+			}
+
+			let album_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
+
+			let album = spotify::album(&client, album_id)
+				.await
+				.inspect_err(|e| error!("Error reading spotify album: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?;
+
+			let items = spotify::into_stream(&client, album.tracks)
+				.map_ok(|album_tracks| album_tracks.items)
+				.try_concat()
+				.await
+				.inspect_err(|e| error!("Error in spotify data api for album items: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?;
+
+			Ok(SourceInfo {
+				title: Some(album.name),
+				url: Some(query.loc.to_string()),
+				count: items.len(),
+				duration: None,
+				clip: None,
+				inputs: Box::new(
+					items
+						.into_iter()
+						.map(|track| Input::from(ComposeWithMetadata::from(&track))),
+				),
+			})
+		}
+		"artist" => {
+			// spotify artist top tracks
+			if !query.allow_playlist {
+				return Err(AudioError::PlaylistNotAllowed);
+			}
+
+			let artist_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
+
+			let tracks = spotify::artist_top_tracks(&client, artist_id)
+				.await
+				.inspect_err(|e| error!("Error reading spotify artist: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?;
+
+			let artist = tracks
+				.iter()
+				.flat_map(|track| &track.artists)
+				.find(|artist| artist.id == artist_id)
+				.map(|artist| format!("Top tracks by {}", artist.name));
+
+			Ok(SourceInfo {
+				title: artist,
+				url: Some(query.loc.to_string()),
+				count: tracks.len(),
+				duration: None,
+				clip: None,
+				inputs: Box::new(
+					tracks
+						.into_iter()
+						.map(|track| Input::from(ComposeWithMetadata::from(&track))),
+				),
+			})
+		}
+		"show" => {
+			// spotify podcast
+			if !query.allow_playlist {
+				return Err(AudioError::PlaylistNotAllowed);
+			}
+
+			let show_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
+
+			let show = spotify::show(&client, show_id)
+				.await
+				.inspect_err(|e| error!("Error reading spotify show: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?;
+
+			let summary = spotify::api::ShowSummary {
+				name: show.name.clone(),
+				publisher: show.publisher,
+			};
+
+			let episodes = spotify::show_episodes(&client, show.episodes, MAX_SHOW_EPISODES)
+				.await
+				.inspect_err(|e| error!("Error in spotify data api for show episodes: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?
+				.into_iter()
+				.map(|episode| spotify::api::Episode {
+					show: Some(summary.clone()),
+					..episode
+				})
+				.collect_vec();
+
+			Ok(SourceInfo {
+				title: Some(show.name),
+				url: Some(query.loc.to_string()),
+				count: episodes.len(),
+				duration: Some(
+					episodes
+						.iter()
+						.map(|episode| std::time::Duration::from_millis(episode.duration_ms))
+						.sum(),
+				),
+				clip: None,
+				inputs: Box::new(
+					episodes
+						.into_iter()
+						.map(|episode| Input::from(ComposeWithMetadata::from(&episode))),
+				),
+			})
+		}
+		"episode" => {
+			// spotify podcast episode
+			let episode_id = path_segments.next().ok_or(AudioError::UnsupportedUrl)?;
+
+			let episode = spotify::episode(&client, episode_id)
+				.await
+				.inspect_err(|e| error!("Error reading spotify episode: {:?}", e))
+				.map_err(|_| AudioError::Spotify)?;
+
+			let compose = ComposeWithMetadata::from(&episode);
+
+			Ok(SourceInfo {
+				title: Some(episode.name),
+				url: Some(query.loc.to_string()),
+				count: 1,
+				duration: Some(std::time::Duration::from_millis(episode.duration_ms)),
+				clip: None,
+				inputs: Box::new(std::iter::once_with(|| compose.into())),
+			})
+		}
+		_ => Err(AudioError::UnsupportedUrl),
+	}
+}
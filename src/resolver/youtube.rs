@@ -0,0 +1,144 @@
+//! Videos and playlists on YouTube, looked up with the data api.
+
+use futures::TryStreamExt;
+
+use regex::Regex;
+
+use songbird::input::Input;
+
+use tracing::{debug, error};
+
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use crate::audio::{AudioError, ComposeWithMetadata, SourceInfo};
+use crate::youtube;
+
+use super::{SourceQuery, SourceResolver, async_trait};
+
+/// Regular expression which matches the host portion of a url if the host is youtube.
+static YOUTUBE_HOST: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new("^([^.]*\\.)?(youtube\\.com|youtu.be)").unwrap());
+
+/// Resolves urls with a host matching [`YOUTUBE_HOST`].
+pub struct YoutubeResolver;
+
+#[async_trait]
+impl SourceResolver for YoutubeResolver {
+	fn name(&self) -> &'static str {
+		"youtube"
+	}
+
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError> {
+		let Some(url) = query.url_with_host(&YOUTUBE_HOST) else {
+			return Ok(None);
+		};
+
+		let loc = query.loc;
+		let path = url.path();
+
+		let youtube_api = query
+			.keys
+			.read()
+			.await
+			.youtube
+			.clone()
+			.ok_or(AudioError::YoutubeApiKey)?;
+
+		if path == "/playlist" {
+			// youtube playlist
+			if !query.allow_playlist {
+				return Err(AudioError::PlaylistNotAllowed);
+			}
+
+			let id = url
+				.query_pairs()
+				.filter(|(key, _)| key == "list")
+				.map(|(_, value)| value)
+				.next()
+				.ok_or_else(|| {
+					debug!("Missing \"list\" in query parameters: {}", url);
+					AudioError::UnsupportedUrl
+				})?;
+
+			let playlist = youtube::playlist(&youtube_api, &id)
+				.await
+				.inspect_err(|e| error!("Error in youtube data api for playlists: {:?}", e))
+				.map_err(AudioError::YoutubeApi)?
+				.ok_or_else(|| {
+					error!("No playlist found");
+					AudioError::NotFound
+				})?;
+
+			let videos = youtube::playlist_items(&youtube_api, &id)
+				.map_ok(|items| {
+					youtube::videos(
+						&youtube_api,
+						items.into_iter().map(|item| item.content_details.video_id),
+					)
+				})
+				// concurrently hit videos api after paged playlist items
+				// no gains were seen after 3 concurrent, 4 is just in case
+				.try_buffered(4)
+				.try_concat()
+				.await
+				.inspect_err(|e| error!("Error in youtube data api for playlist items: {:?}", e))
+				.map_err(AudioError::YoutubeApi)?;
+
+			let count = videos.len();
+
+			Ok(Some(SourceInfo {
+				title: Some(playlist.snippet.title),
+				url: Some(loc.to_string()),
+				count,
+				duration: videos
+					.iter()
+					.map(|video| video.content_details.duration.to_std())
+					.try_fold(std::time::Duration::ZERO, |acc, opt| opt.map(|x| acc + x)),
+				clip: None,
+				inputs: Box::new(
+					videos
+						.into_iter()
+						.map(|item| Input::from(ComposeWithMetadata::from(item))),
+				),
+			}))
+		} else {
+			// single youtube video
+			let id = if path == "/watch" {
+				url.query_pairs()
+					.filter(|(key, _)| key == "v")
+					.map(|(_, value)| value)
+					.next()
+					.ok_or_else(|| {
+						debug!("Missing \"v\" in query parameters: {}", url);
+						AudioError::UnsupportedUrl
+					})?
+			} else {
+				Cow::Borrowed(&path[1..])
+			};
+
+			let video = youtube::video(&youtube_api, &id)
+				.await
+				.inspect_err(|e| error!("Youtube video error: {:?}", e))
+				.map_err(AudioError::YoutubeApi)?
+				.ok_or_else(|| {
+					error!("No video found with id {:?}", id);
+					AudioError::NotFound
+				})?;
+
+			let duration = video.content_details.duration.to_std();
+			let title = video.snippet.title.clone();
+
+			let compose = ComposeWithMetadata::from(video);
+
+			Ok(Some(SourceInfo {
+				title: Some(title),
+				url: Some(loc.to_string()),
+				count: 1,
+				duration,
+				clip: None,
+				inputs: Box::new(std::iter::once_with(|| compose.into())),
+			}))
+		}
+	}
+}
@@ -0,0 +1,139 @@
+//! Tracks, sets and albums on SoundCloud and Bandcamp, read with yt-dlp.
+
+use itertools::Itertools;
+
+use regex::Regex;
+
+use reqwest::Url;
+
+use songbird::input::Input;
+
+use tracing::error;
+
+use std::sync::LazyLock;
+
+use crate::audio::{AudioError, ComposeWithMetadata, SourceInfo};
+use crate::ytdl;
+
+use super::{SourceQuery, SourceResolver, async_trait};
+
+/// Regular expression which matches the host portion of a url if the host is soundcloud.
+static SOUNDCLOUD_HOST: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new("^((www|m)\\.)?soundcloud\\.com$").unwrap());
+
+/// Regular expression which matches the host portion of a url if the host is bandcamp.
+static BANDCAMP_HOST: LazyLock<Regex> =
+	LazyLock::new(|| Regex::new("^[^.]+\\.bandcamp\\.com$").unwrap());
+
+/// Resolves urls with a host matching [`SOUNDCLOUD_HOST`] or [`BANDCAMP_HOST`].
+pub struct YtdlResolver;
+
+#[async_trait]
+impl SourceResolver for YtdlResolver {
+	fn name(&self) -> &'static str {
+		"ytdl"
+	}
+
+	async fn resolve(&self, query: &SourceQuery<'_>) -> Result<Option<SourceInfo>, AudioError> {
+		let Some(url) = query
+			.url_with_host(&SOUNDCLOUD_HOST)
+			.or_else(|| query.url_with_host(&BANDCAMP_HOST))
+		else {
+			return Ok(None);
+		};
+
+		let loc = query.loc;
+
+		let playlist = is_playlist_page(&url).ok_or(AudioError::UnsupportedUrl)?;
+
+		// soundcloud set or bandcamp album
+		if playlist && !query.allow_playlist {
+			return Err(AudioError::PlaylistNotAllowed);
+		}
+
+		let entries = ytdl::entries(loc, playlist)
+			.await
+			.inspect_err(|e| error!("Error reading {}: {:?}", loc, e))
+			.map_err(AudioError::Ytdl)?;
+
+		let title = match entries.first() {
+			Some(entry) if playlist => entry.playlist_title.clone(),
+			Some(entry) => entry.title.clone(),
+			None => return Err(AudioError::NotFound),
+		};
+
+		Ok(Some(SourceInfo {
+			title,
+			url: Some(loc.to_string()),
+			count: entries.len(),
+			duration: entries
+				.iter()
+				.map(ytdl::Entry::duration)
+				.try_fold(std::time::Duration::ZERO, |acc, opt| opt.map(|x| acc + x)),
+			clip: None,
+			inputs: Box::new(
+				entries
+					.into_iter()
+					.map(|entry| Input::from(ComposeWithMetadata::from(entry))),
+			),
+		}))
+	}
+}
+
+/// Check if a soundcloud or bandcamp `url` is for a set or album, rather than a
+/// single track. Returns `None` for any other page, such as a profile.
+fn is_playlist_page(url: &Url) -> Option<bool> {
+	let segments = url.path_segments()?.filter(|s| !s.is_empty()).collect_vec();
+
+	if SOUNDCLOUD_HOST.is_match(url.host_str()?) {
+		match segments[..] {
+			[_, "sets", _, ..] => Some(true),
+			[
+				_,
+				"tracks" | "albums" | "likes" | "reposts" | "popular-tracks",
+			] => None,
+			[_, _, ..] => Some(false),
+			_ => None,
+		}
+	} else {
+		match segments[..] {
+			["album", _] => Some(true),
+			["track", _] => Some(false),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn playlist_pages() {
+		let pages = [
+			("https://soundcloud.com/forss/flickermood", Some(false)),
+			("https://soundcloud.com/forss/sets/soulhack", Some(true)),
+			("https://m.soundcloud.com/forss/sets/soulhack/", Some(true)),
+			("https://soundcloud.com/forss/likes", None),
+			("https://soundcloud.com/forss", None),
+			(
+				"https://macklemore.bandcamp.com/track/downtown",
+				Some(false),
+			),
+			(
+				"https://macklemore.bandcamp.com/album/the-heist",
+				Some(true),
+			),
+			("https://macklemore.bandcamp.com/music", None),
+		];
+
+		for (url, playlist) in pages {
+			assert_eq!(
+				is_playlist_page(&Url::parse(url).unwrap()),
+				playlist,
+				"{}",
+				url
+			);
+		}
+	}
+}
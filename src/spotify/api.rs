@@ -20,41 +20,41 @@ impl<T, F> Response<T, F> {
 	}
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Streamable<T> {
 	pub next: Option<String>,
 	#[serde(flatten)]
 	pub rest: T,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Playlist {
 	pub name: String,
 	pub tracks: Streamable<PlaylistTracks>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct PlaylistTracks {
 	pub items: Vec<PlaylistTracksItem>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct PlaylistTracksItem {
 	pub track: Track,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Album {
 	pub name: String,
 	pub tracks: Streamable<AlbumTracks>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct AlbumTracks {
 	pub items: Vec<Track>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Track {
 	pub id: String,
 	pub name: String,
@@ -62,8 +62,41 @@ pub struct Track {
 	pub artists: Vec<Artist>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Artist {
 	pub id: String,
 	pub name: String,
 }
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct TopTracks {
+	pub tracks: Vec<Track>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Show {
+	pub name: String,
+	pub publisher: String,
+	pub episodes: Streamable<ShowEpisodes>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShowEpisodes {
+	/// Episodes of the show, which are null when unavailable in the market.
+	pub items: Vec<Option<Episode>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Episode {
+	pub id: String,
+	pub name: String,
+	pub duration_ms: u64,
+	/// Show the episode is from, which is left out when listed by its show.
+	pub show: Option<ShowSummary>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShowSummary {
+	pub name: String,
+	pub publisher: String,
+}
@@ -1,15 +1,15 @@
 //! Structures and functions to authenticate and access the [Spotify API]
 //!
 //! In order to access the API, you first need a credentials struct
-//! [`SpotifyApi`]. Then you can use some functions to access the API like
-//! [`track`].
+//! [`SpotifyApi`], which gives a [`SpotifyClient`]. Then you can use some
+//! functions to access the API like [`track`].
 //!
 //! [Spotify API]: https://developer.spotify.com/documentation/web-api/reference/#/
 
 pub mod api;
 pub mod scrape;
 
-use futures::{Stream, StreamExt};
+use futures::{Stream, StreamExt, TryStreamExt};
 use itertools::Itertools;
 
 use tracing::debug;
@@ -20,11 +20,48 @@ use serde::Deserialize;
 
 use songbird::input::{AuxMetadata, YoutubeDl};
 
+use std::sync::LazyLock;
 use std::time::{Duration, Instant};
 
+use crate::cache::{ApiCache, CacheStats};
 use crate::{REQWEST_CLIENT, audio::ComposeWithMetadata, youtube::compose_yt_search_with_meta};
 
-use api::{Album, Playlist, Response, Streamable, Track};
+use api::{Album, Episode, Playlist, Response, Show, ShowEpisodes, Streamable, TopTracks, Track};
+
+/// Market that artist top tracks, shows and episodes are looked up in, as they
+/// are only available in some countries.
+const MARKET: &str = "US";
+
+/// How long lookups which rarely change are cached for.
+const LONG_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long lookups which are often edited, like playlists, are cached for.
+const SHORT_TTL: Duration = Duration::from_secs(60 * 60);
+
+static TRACKS: LazyLock<ApiCache<Track>> =
+	LazyLock::new(|| ApiCache::new("Spotify tracks", LONG_TTL));
+static ALBUMS: LazyLock<ApiCache<Album>> =
+	LazyLock::new(|| ApiCache::new("Spotify albums", LONG_TTL));
+static PLAYLISTS: LazyLock<ApiCache<Playlist>> =
+	LazyLock::new(|| ApiCache::new("Spotify playlists", SHORT_TTL));
+static TOP_TRACKS: LazyLock<ApiCache<Vec<Track>>> =
+	LazyLock::new(|| ApiCache::new("Spotify artists", LONG_TTL));
+static SHOWS: LazyLock<ApiCache<Show>> =
+	LazyLock::new(|| ApiCache::new("Spotify shows", SHORT_TTL));
+static EPISODES: LazyLock<ApiCache<Episode>> =
+	LazyLock::new(|| ApiCache::new("Spotify episodes", LONG_TTL));
+
+/// Statistics of the caches in front of the API.
+pub fn cache_stats() -> Vec<CacheStats> {
+	vec![
+		TRACKS.stats(),
+		ALBUMS.stats(),
+		PLAYLISTS.stats(),
+		TOP_TRACKS.stats(),
+		SHOWS.stats(),
+		EPISODES.stats(),
+	]
+}
 
 /// Information required to connect to the Spotify API.
 ///
@@ -40,6 +77,40 @@ pub struct SpotifyApi {
 	pub client_secret: String,
 	#[serde(skip)]
 	pub token: Option<SpotifyToken>,
+
+	/// Urls requests are made under, which only need changing to use
+	/// something other than the real API.
+	#[serde(default)]
+	pub urls: SpotifyUrls,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SpotifyUrls {
+	/// Url of the web API.
+	pub api: String,
+	/// Url tokens are requested from.
+	pub accounts: String,
+	/// Url of embedded players, which are scraped when the API has no playlist.
+	pub embed: String,
+}
+
+impl Default for SpotifyUrls {
+	fn default() -> Self {
+		Self {
+			api: "https://api.spotify.com/v1".to_owned(),
+			accounts: "https://accounts.spotify.com".to_owned(),
+			embed: "https://open.spotify.com/embed".to_owned(),
+		}
+	}
+}
+
+/// Everything needed to make requests to the API, which can be kept without
+/// borrowing the [`SpotifyApi`] it came from.
+#[derive(Clone, Debug)]
+pub struct SpotifyClient {
+	pub urls: SpotifyUrls,
+	pub access_token: String,
 }
 
 #[derive(Debug, Error)]
@@ -65,6 +136,16 @@ impl SpotifyApi {
 		}
 		Ok(self.token.as_ref().unwrap())
 	}
+
+	/// Get a client with a valid token, refreshing it if needed.
+	pub async fn client(&mut self) -> Result<SpotifyClient> {
+		let access_token = self.get_token().await?.access_token.clone();
+
+		Ok(SpotifyClient {
+			urls: self.urls.clone(),
+			access_token,
+		})
+	}
 }
 
 /// Expiring token for the spotify API. Required to connect, but must be
@@ -85,7 +166,7 @@ impl SpotifyToken {
 		debug!("Fetching spotify token...");
 
 		let response = REQWEST_CLIENT
-			.post("https://accounts.spotify.com/api/token")
+			.post(format!("{}/api/token", api.urls.accounts))
 			.basic_auth(&api.client_id, Some(&api.client_secret))
 			.header("content-type", "application/x-www-form-urlencoded")
 			.body("grant_type=client_credentials")
@@ -145,9 +226,34 @@ impl From<&Track> for ComposeWithMetadata<YoutubeDl<'static>> {
 	}
 }
 
+impl From<&Episode> for ComposeWithMetadata<YoutubeDl<'static>> {
+	/// Convert a spotify podcast episode into a youtube search that songbird
+	/// can use, as spotify does not serve the audio itself.
+	fn from(episode: &Episode) -> Self {
+		let show = episode.show.as_ref();
+
+		compose_yt_search_with_meta(
+			format!(
+				"{} {}",
+				episode.name,
+				show.map(|s| s.name.as_str()).unwrap_or_default()
+			),
+			AuxMetadata {
+				title: Some(episode.name.clone()),
+				artist: show.map(|s| s.publisher.clone()),
+				album: show.map(|s| s.name.clone()),
+				duration: Some(Duration::from_millis(episode.duration_ms)),
+				source_url: Some(format!("https://open.spotify.com/episode/{}", episode.id)),
+
+				..Default::default()
+			},
+		)
+	}
+}
+
 /// Convert Streamable response into Stream
 pub fn into_stream<'t, T>(
-	token: &'t str,
+	client: &'t SpotifyClient,
 	streamable: Streamable<T>,
 ) -> impl Stream<Item = Result<T>> + 't
 where
@@ -160,7 +266,7 @@ where
 				let response = async {
 					REQWEST_CLIENT
 						.get(next)
-						.bearer_auth(token)
+						.bearer_auth(&client.access_token)
 						.send()
 						.await?
 						.json::<Response<Streamable<T>, _>>()
@@ -184,47 +290,279 @@ where
 /// Fetch and parse a playlist from the spotify API.
 ///
 /// See <https://developer.spotify.com/documentation/web-api/reference/#/operations/get-playlist>
-pub async fn playlist(token: &str, playlist_id: &str) -> Result<Playlist> {
-	REQWEST_CLIENT
-		.get(format!(
-			"https://api.spotify.com/v1/playlists/{}",
-			playlist_id
-		))
-		.bearer_auth(token)
-		.send()
-		.await?
-		.json::<Response<Playlist, _>>()
-		.await?
-		.into_result()
-		.map_err(Error::Api)
+pub async fn playlist(client: &SpotifyClient, playlist_id: &str) -> Result<Playlist> {
+	PLAYLISTS
+		.get_or_fetch(playlist_id, async {
+			REQWEST_CLIENT
+				.get(format!("{}/playlists/{}", client.urls.api, playlist_id))
+				.bearer_auth(&client.access_token)
+				.send()
+				.await?
+				.json::<Response<Playlist, _>>()
+				.await?
+				.into_result()
+				.map_err(Error::Api)
+		})
+		.await
 }
 
 /// Fetch and parse an album from the spotify API.
 ///
 /// See <https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-album>
-pub async fn album(token: &str, album_id: &str) -> Result<Album> {
-	REQWEST_CLIENT
-		.get(format!("https://api.spotify.com/v1/albums/{}", album_id))
-		.bearer_auth(token)
-		.send()
-		.await?
-		.json::<Response<Album, _>>()
-		.await?
-		.into_result()
-		.map_err(Error::Api)
+pub async fn album(client: &SpotifyClient, album_id: &str) -> Result<Album> {
+	ALBUMS
+		.get_or_fetch(album_id, async {
+			REQWEST_CLIENT
+				.get(format!("{}/albums/{}", client.urls.api, album_id))
+				.bearer_auth(&client.access_token)
+				.send()
+				.await?
+				.json::<Response<Album, _>>()
+				.await?
+				.into_result()
+				.map_err(Error::Api)
+		})
+		.await
 }
 
 /// Fetch and parse a track from the spotify API.
 ///
 /// See <https://developer.spotify.com/documentation/web-api/reference/#/operations/get-track>
-pub async fn track(token: &str, track_id: &str) -> Result<Track> {
-	REQWEST_CLIENT
-		.get(format!("https://api.spotify.com/v1/tracks/{}", track_id))
-		.bearer_auth(token)
-		.send()
-		.await?
-		.json::<Response<Track, _>>()
-		.await?
-		.into_result()
-		.map_err(Error::Api)
+pub async fn track(client: &SpotifyClient, track_id: &str) -> Result<Track> {
+	TRACKS
+		.get_or_fetch(track_id, async {
+			REQWEST_CLIENT
+				.get(format!("{}/tracks/{}", client.urls.api, track_id))
+				.bearer_auth(&client.access_token)
+				.send()
+				.await?
+				.json::<Response<Track, _>>()
+				.await?
+				.into_result()
+				.map_err(Error::Api)
+		})
+		.await
+}
+
+/// Fetch and parse the most popular tracks of an artist from the spotify API.
+///
+/// See <https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-artists-top-tracks>
+pub async fn artist_top_tracks(client: &SpotifyClient, artist_id: &str) -> Result<Vec<Track>> {
+	TOP_TRACKS
+		.get_or_fetch(artist_id, async {
+			REQWEST_CLIENT
+				.get(format!(
+					"{}/artists/{}/top-tracks",
+					client.urls.api, artist_id
+				))
+				.query(&[("market", MARKET)])
+				.bearer_auth(&client.access_token)
+				.send()
+				.await?
+				.json::<Response<TopTracks, _>>()
+				.await?
+				.into_result()
+				.map(|top| top.tracks)
+				.map_err(Error::Api)
+		})
+		.await
+}
+
+/// Fetch and parse a podcast show from the spotify API.
+///
+/// See <https://developer.spotify.com/documentation/web-api/reference/#/operations/get-a-show>
+pub async fn show(client: &SpotifyClient, show_id: &str) -> Result<Show> {
+	SHOWS
+		.get_or_fetch(show_id, async {
+			REQWEST_CLIENT
+				.get(format!("{}/shows/{}", client.urls.api, show_id))
+				.query(&[("market", MARKET)])
+				.bearer_auth(&client.access_token)
+				.send()
+				.await?
+				.json::<Response<Show, _>>()
+				.await?
+				.into_result()
+				.map_err(Error::Api)
+		})
+		.await
+}
+
+/// Collect the first `max` available episodes listed by a show, only fetching
+/// as many pages as needed, as a show can have years of episodes.
+pub async fn show_episodes(
+	client: &SpotifyClient,
+	episodes: Streamable<ShowEpisodes>,
+	max: usize,
+) -> Result<Vec<Episode>> {
+	let mut pages = std::pin::pin!(into_stream(client, episodes));
+	let mut episodes = Vec::new();
+
+	while episodes.len() < max
+		&& let Some(page) = pages.try_next().await?
+	{
+		episodes.extend(page.items.into_iter().flatten());
+	}
+
+	episodes.truncate(max);
+
+	Ok(episodes)
+}
+
+/// Fetch and parse a podcast episode from the spotify API.
+///
+/// See <https://developer.spotify.com/documentation/web-api/reference/#/operations/get-an-episode>
+pub async fn episode(client: &SpotifyClient, episode_id: &str) -> Result<Episode> {
+	EPISODES
+		.get_or_fetch(episode_id, async {
+			REQWEST_CLIENT
+				.get(format!("{}/episodes/{}", client.urls.api, episode_id))
+				.query(&[("market", MARKET)])
+				.bearer_auth(&client.access_token)
+				.send()
+				.await?
+				.json::<Response<Episode, _>>()
+				.await?
+				.into_result()
+				.map_err(Error::Api)
+		})
+		.await
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::util::mock::{MockServer, mock_keys};
+
+	async fn mock_client(server: MockServer) -> SpotifyClient {
+		let base_url = server
+			.fixture("/api/token", "spotify/token.json")
+			.start()
+			.await;
+
+		mock_keys(&base_url)
+			.write()
+			.await
+			.spotify
+			.as_mut()
+			.unwrap()
+			.client()
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn album_tracks_pages() {
+		let base_url = MockServer::new()
+			.fixture("/api/token", "spotify/token.json")
+			.fixture("/v1/albums/mockalbum", "spotify/album.json")
+			.fixture("/v1/albums/mockalbum/tracks", "spotify/album-tracks.json")
+			.start()
+			.await;
+
+		let client = mock_keys(&base_url)
+			.write()
+			.await
+			.spotify
+			.as_mut()
+			.unwrap()
+			.client()
+			.await
+			.unwrap();
+
+		assert_eq!(client.access_token, "mock-token");
+
+		let album = album(&client, "mockalbum").await.unwrap();
+
+		let tracks: Vec<_> = into_stream(&client, album.tracks)
+			.map_ok(|album_tracks| album_tracks.items)
+			.try_concat()
+			.await
+			.unwrap();
+
+		assert_eq!(
+			tracks.iter().map(|track| track.id.as_str()).collect_vec(),
+			["mocktrack01", "mocktrack02", "mocktrack03"]
+		);
+	}
+
+	#[tokio::test]
+	async fn artist_top_tracks_of_artist() {
+		let client = mock_client(MockServer::new().fixture_with_query(
+			"/v1/artists/mockartist/top-tracks",
+			"market=US",
+			"spotify/artist-top-tracks.json",
+		))
+		.await;
+
+		let tracks = artist_top_tracks(&client, "mockartist").await.unwrap();
+
+		assert_eq!(
+			tracks.iter().map(|track| track.id.as_str()).collect_vec(),
+			["mocktrack02", "mocktrack01"]
+		);
+	}
+
+	#[tokio::test]
+	async fn show_episodes_pages() {
+		let client = mock_client(
+			MockServer::new()
+				.fixture("/v1/shows/mockpaged", "spotify/show.json")
+				.fixture("/v1/shows/mockshow/episodes", "spotify/show-episodes.json"),
+		)
+		.await;
+
+		// shows are cached with links to the server, so each test uses its own
+		let show = show(&client, "mockpaged").await.unwrap();
+
+		assert_eq!(show.name, "Mock Show");
+		assert_eq!(show.publisher, "Mock Publisher");
+
+		// unavailable episodes are skipped
+		let episodes = show_episodes(&client, show.episodes, 10).await.unwrap();
+
+		assert_eq!(
+			episodes
+				.iter()
+				.map(|episode| episode.id.as_str())
+				.collect_vec(),
+			["mockepisode03", "mockepisode02", "mockepisode01"]
+		);
+	}
+
+	#[tokio::test]
+	async fn show_episodes_capped() {
+		// the next page 404s, so this only passes if it is never fetched
+		let client =
+			mock_client(MockServer::new().fixture("/v1/shows/mockcapped", "spotify/show.json"))
+				.await;
+
+		let show = show(&client, "mockcapped").await.unwrap();
+		let episodes = show_episodes(&client, show.episodes, 1).await.unwrap();
+
+		assert_eq!(
+			episodes
+				.iter()
+				.map(|episode| episode.id.as_str())
+				.collect_vec(),
+			["mockepisode03"]
+		);
+	}
+
+	#[tokio::test]
+	async fn episode_with_show() {
+		let client = mock_client(
+			MockServer::new().fixture("/v1/episodes/mockepisode01", "spotify/episode.json"),
+		)
+		.await;
+
+		let episode = episode(&client, "mockepisode01").await.unwrap();
+		let metadata = ComposeWithMetadata::from(&episode).aux_metadata;
+
+		assert_eq!(metadata.title.as_deref(), Some("Mock Episode 1"));
+		assert_eq!(metadata.artist.as_deref(), Some("Mock Publisher"));
+		assert_eq!(metadata.album.as_deref(), Some("Mock Show"));
+		assert_eq!(metadata.duration, Some(Duration::from_millis(1801000)));
+	}
 }
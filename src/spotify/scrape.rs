@@ -5,8 +5,8 @@ use std::sync::LazyLock;
 
 use crate::REQWEST_CLIENT;
 
-use super::Result;
 use super::api::{Artist, Playlist, PlaylistTracks, PlaylistTracksItem, Streamable, Track};
+use super::{Result, SpotifyClient};
 
 static PLAYLIST_EMBED_JSON: LazyLock<Regex> = LazyLock::new(|| {
 	RegexBuilder::new(
@@ -34,12 +34,9 @@ pub enum Error {
 	TrackArtists,
 }
 
-pub async fn playlist(playlist_id: &str) -> Result<Playlist> {
+pub async fn playlist(client: &SpotifyClient, playlist_id: &str) -> Result<Playlist> {
 	let embed = REQWEST_CLIENT
-		.get(format!(
-			"https://open.spotify.com/embed/playlist/{}",
-			playlist_id
-		))
+		.get(format!("{}/playlist/{}", client.urls.embed, playlist_id))
 		.send()
 		.await?
 		.text()
@@ -0,0 +1,205 @@
+//! Stand-in server for tests, which answers requests with fixtures so that code
+//! using external APIs can be tested without network access.
+//!
+//! Fixtures are read from [`FIXTURE_PATH`], and any `{base_url}` in them is
+//! replaced with the url of the server, for responses which link to more pages.
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+
+use crate::RESOURCE_PATH;
+use crate::data::{ArcRw, DatabaseKeys, DiscordApi, Keys};
+use crate::spotify::{SpotifyApi, SpotifyUrls};
+use crate::youtube::YoutubeApi;
+
+/// Path to the fixtures served by [`MockServer`].
+pub static FIXTURE_PATH: LazyLock<PathBuf> = LazyLock::new(|| RESOURCE_PATH.join("fixtures/"));
+
+/// Response to requests for a path.
+struct Route {
+	path: String,
+
+	/// Text the query has to contain for the route to match.
+	query: Option<String>,
+
+	status: u16,
+	content_type: &'static str,
+	body: Vec<u8>,
+}
+
+/// Server answering each request with the first route matching it, or a 404
+/// if none match.
+#[derive(Default)]
+pub struct MockServer {
+	routes: Vec<Route>,
+}
+
+impl MockServer {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Answer requests for `path` with the json `fixture`.
+	pub fn fixture(self, path: &str, fixture: &str) -> Self {
+		self.route(path, None, 200, fixture)
+	}
+
+	/// Answer requests for `path` with the json `fixture`, only if the query
+	/// contains `query`.
+	pub fn fixture_with_query(self, path: &str, query: &str, fixture: &str) -> Self {
+		self.route(path, Some(query), 200, fixture)
+	}
+
+	/// Answer requests for `path` with the json `fixture` as an error.
+	pub fn error(self, path: &str, status: u16, fixture: &str) -> Self {
+		self.route(path, None, status, fixture)
+	}
+
+	/// Answer requests for `path` with a `body` of any type.
+	pub fn body(mut self, path: &str, content_type: &'static str, body: Vec<u8>) -> Self {
+		self.routes.push(Route {
+			path: path.to_owned(),
+			query: None,
+			status: 200,
+			content_type,
+			body,
+		});
+
+		self
+	}
+
+	fn route(mut self, path: &str, query: Option<&str>, status: u16, fixture: &str) -> Self {
+		let body = std::fs::read(FIXTURE_PATH.join(fixture))
+			.unwrap_or_else(|e| panic!("Unable to read fixture {:?}: {:?}", fixture, e));
+
+		self.routes.push(Route {
+			path: path.to_owned(),
+			query: query.map(ToOwned::to_owned),
+			status,
+			content_type: if fixture.ends_with(".html") {
+				"text/html"
+			} else {
+				"application/json"
+			},
+			body,
+		});
+
+		self
+	}
+
+	/// Start serving on a local port, returning the base url of the server.
+	pub async fn start(self) -> String {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+		let routes = Arc::new(
+			self.routes
+				.into_iter()
+				.map(|route| Route {
+					body: replace_base_url(route.body, &base_url),
+					..route
+				})
+				.collect::<Vec<_>>(),
+		);
+
+		tokio::spawn(async move {
+			while let Ok((mut stream, _)) = listener.accept().await {
+				let routes = routes.clone();
+
+				tokio::spawn(async move {
+					let Some(target) = read_target(&mut stream).await else {
+						return;
+					};
+
+					let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+
+					let route = routes.iter().find(|route| {
+						route.path == path
+							&& route
+								.query
+								.as_ref()
+								.is_none_or(|q| query.contains(q.as_str()))
+					});
+
+					let (status, content_type, body) = match route {
+						Some(route) => (route.status, route.content_type, &route.body[..]),
+						None => (404, "text/plain", &b"not found"[..]),
+					};
+
+					let head = format!(
+						"HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+						status,
+						content_type,
+						body.len()
+					);
+
+					let _ = stream.write_all(head.as_bytes()).await;
+					let _ = stream.write_all(body).await;
+				});
+			}
+		});
+
+		base_url
+	}
+}
+
+/// Read the head of a request, returning the path and query requested.
+async fn read_target(stream: &mut tokio::net::TcpStream) -> Option<String> {
+	let mut request = Vec::new();
+	let mut buf = [0; 1024];
+
+	while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+		let read = stream.read(&mut buf).await.ok()?;
+
+		if read == 0 {
+			return None;
+		}
+
+		request.extend_from_slice(&buf[..read]);
+	}
+
+	let request = String::from_utf8_lossy(&request);
+
+	request.split(' ').nth(1).map(ToOwned::to_owned)
+}
+
+fn replace_base_url(body: Vec<u8>, base_url: &str) -> Vec<u8> {
+	match String::from_utf8(body) {
+		Ok(text) => text.replace("{base_url}", base_url).into_bytes(),
+		Err(e) => e.into_bytes(),
+	}
+}
+
+/// Keys with the youtube and spotify apis pointed at a [`MockServer`] at
+/// `base_url`.
+pub fn mock_keys(base_url: &str) -> ArcRw<Keys> {
+	Arc::new(
+		Keys {
+			database: DatabaseKeys {
+				connect_string: String::new(),
+			},
+			discord: DiscordApi {
+				application_id: 0,
+				token: String::new(),
+			},
+			youtube: Some(YoutubeApi {
+				key: "mock".to_owned(),
+				api_url: format!("{}/youtube/v3", base_url),
+			}),
+			spotify: Some(SpotifyApi {
+				client_id: "mock".to_owned(),
+				client_secret: "mock".to_owned(),
+				token: None,
+				urls: SpotifyUrls {
+					api: format!("{}/v1", base_url),
+					accounts: base_url.to_owned(),
+					embed: format!("{}/embed", base_url),
+				},
+			}),
+		}
+		.into(),
+	)
+}
@@ -5,9 +5,11 @@
 //! ([`sandboxed_join`]), and some functions to help responding to commands.
 
 mod conv;
+#[cfg(test)]
+pub mod mock;
 mod respond;
 
-pub use respond::{Respond, Response};
+pub use respond::{Respond, Response, change_page};
 
 pub use conv::Conv;
 
@@ -26,6 +28,8 @@ use std::fmt::Debug;
 use std::path::{Component, Path, PathBuf};
 use std::time::Duration;
 
+use crate::audio::is_url;
+
 pub type Data = ();
 pub type Command = poise::Command<Data, CommandError>;
 pub type CommandError = Box<dyn std::error::Error + Send + Sync>;
@@ -150,8 +154,8 @@ pub fn write_track(
 	let title = meta.title.as_deref().unwrap_or("Unknown");
 
 	match &meta.source_url {
-		Some(url) => write!(write, " [{title}]({url})")?,
-		None => write!(write, " {title}")?,
+		Some(url) if is_url(url) => write!(write, " [{title}]({url})")?,
+		_ => write!(write, " {title}")?,
 	}
 
 	if let Some(duration) = meta.duration {
@@ -1,21 +1,67 @@
+use dashmap::DashMap;
+
+use fxhash::FxBuildHasher as BuildHasher;
+
 use poise::{Context, CreateReply, ReplyHandle};
 
-use serenity::builder::{CreateEmbed, CreateMessage};
-use serenity::http::Http;
+use serenity::builder::{
+	CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponse,
+	CreateInteractionResponseMessage, CreateMessage, EditMessage,
+};
+use serenity::collector::ComponentInteractionCollector;
+use serenity::http::{CacheHttp, Http};
+use serenity::model::application::ComponentInteraction;
 use serenity::model::channel::Message;
 use serenity::model::colour::Color;
-use serenity::model::id::ChannelId;
+use serenity::model::id::{ChannelId, MessageId};
 use serenity::prelude::SerenityError;
 
+use tracing::error;
+
 use std::fmt::{Debug, Display};
 use std::future::Future;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
 
 const OK_COLOR: Color = Color::from_rgb(127, 255, 127);
 const ERR_COLOR: Color = Color::from_rgb(255, 127, 127);
 
+/// Maximum length of a page of text. This is well below the 4096 characters
+/// allowed in an embed description, so that the title and fields still fit in
+/// the 6000 characters allowed in a whole embed.
+const PAGE_LEN: usize = 2048;
+
+/// How long the page buttons of a reply keep working after the last press.
+const PAGE_TIMEOUT: Duration = Duration::from_secs(600);
+
+const PREV_ID: &str = "page_prev";
+const NEXT_ID: &str = "page_next";
+
+/// Pages of the messages sent straight to a channel, whose buttons are handled
+/// by [`change_page`] as there is no command to collect the presses under.
+static CHANNEL_PAGES: LazyLock<DashMap<MessageId, ChannelPages, BuildHasher>> =
+	LazyLock::new(Default::default);
+
+/// Pages of a message sent to a channel, see [`CHANNEL_PAGES`].
+struct ChannelPages {
+	embeds: Vec<CreateEmbed>,
+	page: usize,
+	last_press: Instant,
+}
+
 #[derive(Clone, Debug)]
 pub struct Response {
 	pub text: String,
+
+	/// Title shown above the text.
+	pub title: Option<String>,
+
+	/// Fields shown after the text, as the name, value, and whether the field
+	/// is inline.
+	pub fields: Vec<(String, String, bool)>,
+
+	/// Url of an image shown beside the text.
+	pub thumbnail: Option<String>,
 }
 
 impl Display for Response {
@@ -26,44 +72,151 @@ impl Display for Response {
 
 impl From<String> for Response {
 	fn from(text: String) -> Self {
-		Self { text }
+		Self {
+			text,
+			title: None,
+			fields: Vec::new(),
+			thumbnail: None,
+		}
 	}
 }
 
 impl From<&str> for Response {
 	fn from(text: &str) -> Self {
-		Self {
-			text: text.to_string(),
-		}
+		text.to_string().into()
 	}
 }
 
 impl Response {
-	fn embed_color(self, color: Color, create: CreateEmbed) -> CreateEmbed {
-		// TODO see if this constant is defined somewhere
-		if self.text.len() <= 4096 {
-			create.color(color).description(self.text)
-		} else {
-			create
-				.color(ERR_COLOR)
-				.description("The response was too long and cannot be displayed")
-		}
+	/// Set the title to show above the text.
+	pub fn title(mut self, title: impl Into<String>) -> Self {
+		self.title = Some(title.into());
+		self
 	}
 
-	fn embed_err(self, create: CreateEmbed) -> CreateEmbed {
-		self.embed_color(ERR_COLOR, create)
+	/// Add a field to show after the text.
+	pub fn field(
+		mut self,
+		name: impl Into<String>,
+		value: impl Into<String>,
+		inline: bool,
+	) -> Self {
+		self.fields.push((name.into(), value.into(), inline));
+		self
 	}
 
-	fn embed_ok(self, create: CreateEmbed) -> CreateEmbed {
-		self.embed_color(OK_COLOR, create)
+	/// Set the url of an image to show beside the text.
+	pub fn thumbnail(mut self, url: impl Into<String>) -> Self {
+		self.thumbnail = Some(url.into());
+		self
 	}
 
-	fn embed(result: Result<Response, Response>, create: CreateEmbed) -> CreateEmbed {
-		match result {
-			Ok(response) => response.embed_ok(create),
-			Err(response) => response.embed_err(create),
+	/// Split the text into pages of at most [`PAGE_LEN`] bytes, breaking
+	/// between lines where possible.
+	///
+	/// A code block split across pages is closed at the end of one page and
+	/// opened again at the start of the next, so every page renders alone.
+	pub fn pages(&self) -> Vec<String> {
+		const FENCE: &str = "```";
+
+		let mut pages = Vec::new();
+		let mut rest = self.text.as_str();
+		let mut fence: Option<String> = None;
+
+		loop {
+			let prefix = fence.as_ref().map(|f| f.clone() + "\n").unwrap_or_default();
+
+			if prefix.len() + rest.len() <= PAGE_LEN {
+				pages.push(prefix + rest);
+				break pages;
+			}
+
+			// leave room to close a code block at the end of the page
+			let mut split = rest.floor_char_boundary(PAGE_LEN - prefix.len() - FENCE.len() - 1);
+
+			if let Some(i) = rest[..split].rfind('\n')
+				&& i > 0
+			{
+				split = i + 1;
+			}
+
+			let (page, next) = rest.split_at(split);
+			rest = next;
+
+			for line in page.lines().map(str::trim) {
+				if line.starts_with(FENCE) {
+					fence = match fence {
+						Some(_) => None,
+						None => Some(line.to_owned()),
+					};
+				}
+			}
+
+			let mut page = prefix + page.trim_end_matches('\n');
+
+			if fence.is_some() {
+				page = page + "\n" + FENCE;
+			}
+
+			pages.push(page);
 		}
 	}
+
+	/// Create an embed for every page of the response.
+	fn embeds(result: Result<Response, Response>) -> Vec<CreateEmbed> {
+		let (color, response) = match result {
+			Ok(response) => (OK_COLOR, response),
+			Err(response) => (ERR_COLOR, response),
+		};
+
+		let pages = response.pages();
+		let count = pages.len();
+
+		pages
+			.into_iter()
+			.enumerate()
+			.map(|(i, page)| {
+				let mut create = CreateEmbed::new().color(color).description(page);
+
+				if let Some(title) = &response.title {
+					create = create.title(title);
+				}
+
+				if let Some(url) = &response.thumbnail {
+					create = create.thumbnail(url);
+				}
+
+				if count > 1 {
+					create =
+						create.footer(CreateEmbedFooter::new(format!("Page {}/{}", i + 1, count)));
+				}
+
+				if i + 1 == count {
+					create = create.fields(response.fields.clone());
+				}
+
+				create
+			})
+			.collect()
+	}
+
+	/// Create a reply with the first page of the response as an embed, which
+	/// can also be used to edit an earlier reply.
+	pub fn reply(result: Result<Response, Response>) -> CreateReply {
+		let embed = Self::embeds(result).swap_remove(0);
+
+		CreateReply::default().embed(embed)
+	}
+}
+
+/// Buttons to move between the pages of a reply, on page `page` of `count`.
+fn page_buttons(page: usize, count: usize) -> Vec<CreateActionRow> {
+	vec![CreateActionRow::Buttons(vec![
+		CreateButton::new(PREV_ID).emoji('◀').disabled(page == 0),
+		CreateButton::new(NEXT_ID)
+			.emoji('▶')
+			.disabled(page + 1 == count),
+	])]
 }
 
 #[allow(dead_code)]
@@ -107,7 +260,9 @@ impl<R: Respond + Send + Sync> Respond for Option<R> {
 	}
 }
 
-impl<H: AsRef<Http> + Send + Sync> Respond for (H, ChannelId) {
+/// The first page is sent with the same buttons as a reply, which are handled
+/// by [`change_page`] since there is no command to collect the presses under.
+impl<H: AsRef<Http> + Clone + Send + Sync + 'static> Respond for (H, ChannelId) {
 	type Value = Message;
 	type Error = SerenityError;
 
@@ -115,17 +270,94 @@ impl<H: AsRef<Http> + Send + Sync> Respond for (H, ChannelId) {
 		&self,
 		result: Result<Response, Response>,
 	) -> Result<Self::Value, Self::Error> {
-		self.0
+		let embeds = Response::embeds(result);
+		let count = embeds.len();
+
+		let mut create = CreateMessage::new().embed(embeds[0].clone());
+
+		if count > 1 {
+			create = create.components(page_buttons(0, count));
+		}
+
+		let message = self
+			.0
 			.as_ref()
-			.send_message(
-				self.1,
-				vec![],
-				&CreateMessage::new().embed(Response::embed(result, CreateEmbed::new())),
-			)
-			.await
+			.send_message(self.1, vec![], &create)
+			.await?;
+
+		if count == 1 {
+			return Ok(message);
+		}
+
+		let message_id = message.id;
+
+		CHANNEL_PAGES.insert(
+			message_id,
+			ChannelPages {
+				embeds,
+				page: 0,
+				last_press: Instant::now(),
+			},
+		);
+
+		let http = self.0.clone();
+		let channel_id = self.1;
+
+		// remove the buttons once they haven't been pressed for a while
+		tokio::spawn(async move {
+			while let Some(deadline) = CHANNEL_PAGES
+				.get(&message_id)
+				.map(|pages| pages.last_press + PAGE_TIMEOUT)
+				.filter(|deadline| *deadline > Instant::now())
+			{
+				tokio::time::sleep(deadline - Instant::now()).await;
+			}
+
+			CHANNEL_PAGES.remove(&message_id);
+
+			let _ = channel_id
+				.edit_message(
+					http.as_ref(),
+					message_id,
+					EditMessage::new().components(vec![]),
+				)
+				.await
+				.inspect_err(|e| error!("Unable to remove page buttons: {:?}", e));
+		});
+
+		Ok(message)
 	}
 }
 
+/// Change the page of a message sent straight to a channel when one of its
+/// page buttons is pressed. Presses on any other message are left alone.
+pub async fn change_page(http: impl CacheHttp, press: &ComponentInteraction) {
+	let update = {
+		let Some(mut pages) = CHANNEL_PAGES.get_mut(&press.message.id) else {
+			return;
+		};
+
+		let count = pages.embeds.len();
+
+		pages.page = match press.data.custom_id.as_str() {
+			PREV_ID => pages.page.saturating_sub(1),
+			NEXT_ID => (pages.page + 1).min(count - 1),
+			_ => return,
+		};
+
+		pages.last_press = Instant::now();
+
+		CreateInteractionResponseMessage::new()
+			.embed(pages.embeds[pages.page].clone())
+			.components(page_buttons(pages.page, count))
+	};
+
+	let _ = press
+		.create_response(http, CreateInteractionResponse::UpdateMessage(update))
+		.await
+		.inspect_err(|e| error!("Unable to change page: {:?}", e));
+}
+
 impl<'a, U, E> Respond for Context<'a, U, E>
 where
 	U: Sync,
@@ -137,7 +369,94 @@ where
 		&self,
 		result: Result<Response, Response>,
 	) -> Result<Self::Value, Self::Error> {
-		self.send(CreateReply::default().embed(Response::embed(result, CreateEmbed::new())))
-			.await
+		let mut embeds = Response::embeds(result);
+		let count = embeds.len();
+
+		if count == 1 {
+			return self
+				.send(CreateReply::default().embed(embeds.remove(0)))
+				.await;
+		}
+
+		let reply = self
+			.send(
+				CreateReply::default()
+					.embed(embeds[0].clone())
+					.components(page_buttons(0, count)),
+			)
+			.await?;
+
+		let message = reply.message().await?.into_owned();
+		let ctx = self.serenity_context().clone();
+
+		// page through in the background, so that the command can finish
+		tokio::spawn(async move {
+			let mut page: usize = 0;
+
+			while let Some(press) = ComponentInteractionCollector::new(&ctx)
+				.message_id(message.id)
+				.timeout(PAGE_TIMEOUT)
+				.await
+			{
+				page = match press.data.custom_id.as_str() {
+					PREV_ID => page.saturating_sub(1),
+					NEXT_ID => (page + 1).min(count - 1),
+					_ => continue,
+				};
+
+				let update = CreateInteractionResponseMessage::new()
+					.embed(embeds[page].clone())
+					.components(page_buttons(page, count));
+
+				if let Err(e) = press
+					.create_response(&ctx, CreateInteractionResponse::UpdateMessage(update))
+					.await
+				{
+					error!("Unable to change page: {:?}", e);
+					break;
+				}
+			}
+
+			let _ = message
+				.channel_id
+				.edit_message(&ctx, message.id, EditMessage::new().components(vec![]))
+				.await
+				.inspect_err(|e| error!("Unable to remove page buttons: {:?}", e));
+		});
+
+		Ok(reply)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn pages_split_lines() {
+		let line = "a".repeat(99);
+		let response = Response::from(vec![line.as_str(); 50].join("\n"));
+
+		let pages = response.pages();
+
+		assert_eq!(pages.len(), 3);
+		assert!(pages.iter().all(|p| p.len() <= PAGE_LEN));
+		assert!(pages.iter().flat_map(|p| p.lines()).all(|l| l == line));
+		assert_eq!(pages.iter().flat_map(|p| p.lines()).count(), 50);
+	}
+
+	#[test]
+	fn pages_code_block() {
+		let response = Response::from(format!("```\n{}\n```", "abc\n".repeat(1000)));
+
+		let pages = response.pages();
+
+		assert!(pages.len() > 1);
+		assert!(pages.iter().all(|p| p.len() <= PAGE_LEN));
+		assert!(
+			pages
+				.iter()
+				.all(|p| p.starts_with("```\n") && p.ends_with("\n```"))
+		);
 	}
 }
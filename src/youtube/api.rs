@@ -34,14 +34,14 @@ pub struct PageInfo {
 	pub results_per_page: u64,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Playlist {
 	pub id: String,
 	pub snippet: PlaylistSnippet,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PlaylistSnippet {
 	pub title: String,
@@ -84,13 +84,25 @@ pub struct VideoList {
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+	pub id: SearchResultId,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultId {
+	pub video_id: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Video {
 	pub id: String,
 	pub snippet: VideoSnippet,
 	pub content_details: VideoContentDetails,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoSnippet {
 	pub title: String,
@@ -98,13 +110,13 @@ pub struct VideoSnippet {
 	pub thumbnails: Thumbnails,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoContentDetails {
 	pub duration: iso8601_duration::Duration,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Thumbnails {
 	pub default: Option<Thumbnail>,
@@ -114,7 +126,7 @@ pub struct Thumbnails {
 	pub maxres: Option<Thumbnail>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Thumbnail {
 	pub url: String,
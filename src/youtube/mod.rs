@@ -5,7 +5,7 @@
 //! [YouTube API]: https://developers.google.com/youtube/v3/docs
 pub mod api;
 
-use futures::Stream;
+use futures::{Stream, TryStreamExt};
 use itertools::Itertools;
 
 use serde::{Deserialize, Serialize};
@@ -15,16 +15,38 @@ use songbird::input::{AuxMetadata, YoutubeDl};
 
 use thiserror::Error;
 
-use api::{List, Playlist, PlaylistItem, Response, Video};
+use api::{List, Playlist, PlaylistItem, Response, SearchResult, Video};
 
+use std::collections::HashMap;
 use std::fmt::Display;
+use std::sync::LazyLock;
+use std::time::Duration;
 
 use crate::REQWEST_CLIENT;
 use crate::audio::ComposeWithMetadata;
+use crate::cache::{ApiCache, CacheStats};
+
+/// Videos by [`cache_key`], which rarely change once uploaded.
+static VIDEOS: LazyLock<ApiCache<Video>> =
+	LazyLock::new(|| ApiCache::new("Youtube videos", Duration::from_secs(24 * 60 * 60)));
+
+/// Playlists by [`cache_key`], kept for less time since they are edited more
+/// often.
+static PLAYLISTS: LazyLock<ApiCache<Playlist>> =
+	LazyLock::new(|| ApiCache::new("Youtube playlists", Duration::from_secs(60 * 60)));
 
 #[derive(Clone, Deserialize)]
 pub struct YoutubeApi {
 	pub key: String,
+
+	/// Url every request is made under, which only needs changing to use
+	/// something other than the real API.
+	#[serde(default = "default_api_url")]
+	pub api_url: String,
+}
+
+fn default_api_url() -> String {
+	"https://www.googleapis.com/youtube/v3".to_owned()
 }
 
 #[derive(Debug, Error)]
@@ -33,10 +55,23 @@ pub enum Error {
 	Reqwest(#[from] reqwest::Error),
 	#[error("failed to parse data: {0}")]
 	Api(serde_json::Value),
+	#[error("no results found")]
+	NotFound,
 }
 
 pub type Result<T> = core::result::Result<T, Error>;
 
+/// Key of `id` in the caches, which are shared by every [`YoutubeApi`], so that
+/// an API under another url does not see its results.
+fn cache_key(api: &YoutubeApi, id: &str) -> String {
+	format!("{} {}", api.api_url, id)
+}
+
+/// Statistics of the caches in front of the API.
+pub fn cache_stats() -> Vec<CacheStats> {
+	vec![VIDEOS.stats(), PLAYLISTS.stats()]
+}
+
 pub fn compose_yt_url(
 	uri: String,
 	mut aux_metadata: AuxMetadata,
@@ -124,21 +159,21 @@ pub fn compose_yt_search_with_meta(
 	inner(search, metadata)
 }
 
-pub fn stream_paged<'a, T, Q>(
-	url: &'static str,
-	query: Q,
-) -> impl Stream<Item = Result<Vec<T>>> + 'a
+pub fn stream_paged<'a, T, Q>(url: String, query: Q) -> impl Stream<Item = Result<Vec<T>>> + 'a
 where
 	for<'de> T: Deserialize<'de>,
 	Q: Serialize + Copy + 'a,
 {
-	futures::stream::unfold(Some(None), move |page_token| async move {
-		if page_token.is_none() {
-			None
-		} else {
+	futures::stream::unfold(Some(None), move |page_token| {
+		let url = url.clone();
+
+		async move {
+			// the last page has no next page token
+			let page_token = page_token?;
+
 			let response = async {
 				REQWEST_CLIENT
-					.get(url)
+					.get(&url)
 					.query(&query)
 					.query(&page_token.map(|next| [("pageToken", next)]))
 					.send()
@@ -159,29 +194,36 @@ where
 }
 
 pub async fn playlist(api: &YoutubeApi, playlist_id: &str) -> Result<Option<Playlist>> {
-	Ok(REQWEST_CLIENT
-		.get("https://www.googleapis.com/youtube/v3/playlists")
-		.query(&[
-			("key", api.key.as_ref()),
-			("part", "snippet"),
-			("id", playlist_id),
-		])
-		.send()
-		.await?
-		.json::<Response<List<Playlist>, _>>()
-		.await?
-		.into_result()
-		.map_err(Error::Api)?
-		.items
-		.drain(..)
-		.next())
+	let playlist = PLAYLISTS
+		.get_or_fetch(&cache_key(api, playlist_id), async {
+			REQWEST_CLIENT
+				.get(format!("{}/playlists", api.api_url))
+				.query(&[
+					("key", api.key.as_ref()),
+					("part", "snippet"),
+					("id", playlist_id),
+				])
+				.send()
+				.await?
+				.json::<Response<List<Playlist>, _>>()
+				.await?
+				.into_result()
+				.map_err(Error::Api)?
+				.items
+				.drain(..)
+				.next()
+				.ok_or(Error::NotFound)
+		})
+		.await;
+
+	found(playlist)
 }
 
 pub fn playlist_items<'a>(
 	api: &'a YoutubeApi,
 	playlist_id: &'a str,
 ) -> impl Stream<Item = Result<Vec<PlaylistItem>>> + 'a {
-	let url = "https://youtube.googleapis.com/youtube/v3/playlistItems";
+	let url = format!("{}/playlistItems", api.api_url);
 	let query = [
 		("key", api.key.as_ref()),
 		("part", "contentDetails,snippet"),
@@ -193,46 +235,199 @@ pub fn playlist_items<'a>(
 }
 
 pub async fn video(api: &YoutubeApi, video_id: &str) -> Result<Option<Video>> {
-	Ok(REQWEST_CLIENT
-		.get("https://www.googleapis.com/youtube/v3/videos")
-		.query(&[
-			("key", api.key.as_ref()),
-			("part", "contentDetails,snippet"),
-			("id", video_id),
-		])
-		.send()
-		.await?
-		.json::<Response<List<Video>, _>>()
-		.await?
-		.into_result()
-		.map_err(Error::Api)?
-		.items
-		.drain(..)
-		.next())
+	let video = VIDEOS
+		.get_or_fetch(&cache_key(api, video_id), async {
+			REQWEST_CLIENT
+				.get(format!("{}/videos", api.api_url))
+				.query(&[
+					("key", api.key.as_ref()),
+					("part", "contentDetails,snippet"),
+					("id", video_id),
+				])
+				.send()
+				.await?
+				.json::<Response<List<Video>, _>>()
+				.await?
+				.into_result()
+				.map_err(Error::Api)?
+				.items
+				.drain(..)
+				.next()
+				.ok_or(Error::NotFound)
+		})
+		.await;
+
+	found(video)
+}
+
+/// Turn [`Error::NotFound`] from a lookup back into `None`, which is not
+/// cached, so that it is looked up again next time.
+fn found<T>(result: Result<T>) -> Result<Option<T>> {
+	match result {
+		Ok(value) => Ok(Some(value)),
+		Err(Error::NotFound) => Ok(None),
+		Err(e) => Err(e),
+	}
 }
 
 /// See <https://youtube.googleapis.com/youtube/v3/videos> for limitations
 ///
 /// For example, if `video_ids` is longer than 50, this will fail
+///
+/// Only videos which are not cached are requested, and the videos are returned
+/// in the order of `video_ids`.
 pub async fn videos(
 	api: &YoutubeApi,
 	video_ids: impl IntoIterator<Item = impl std::fmt::Display>,
 ) -> Result<Vec<Video>> {
-	let url = "https://youtube.googleapis.com/youtube/v3/videos";
+	let url = format!("{}/videos", api.api_url);
 	let query_base = [
 		("key", api.key.as_ref()),
 		("part", "contentDetails,snippet"),
 	];
 
-	REQWEST_CLIENT
-		.get(url)
-		.query(&query_base)
-		.query(&[("id", &video_ids.into_iter().join(","))])
-		.send()
-		.await?
-		.json::<Response<List<Video>, _>>()
+	let ids = video_ids.into_iter().map(|id| id.to_string()).collect_vec();
+
+	let mut found = HashMap::new();
+	let mut missing = Vec::new();
+
+	for id in &ids {
+		match VIDEOS.get(&cache_key(api, id)) {
+			Some(video) => {
+				found.insert(id.clone(), video);
+			}
+			None => missing.push(id.as_str()),
+		}
+	}
+
+	if !missing.is_empty() {
+		let fetched = REQWEST_CLIENT
+			.get(&url)
+			.query(&query_base)
+			.query(&[("id", &missing.into_iter().unique().join(","))])
+			.send()
+			.await?
+			.json::<Response<List<Video>, _>>()
+			.await?
+			.into_result()
+			.map_err(Error::Api)?
+			.items;
+
+		for video in fetched {
+			VIDEOS.insert(cache_key(api, &video.id), video.clone());
+			found.insert(video.id.clone(), video);
+		}
+	}
+
+	// playlists can have a video more than once
+	Ok(ids.iter().filter_map(|id| found.get(id).cloned()).collect())
+}
+
+/// Search for videos, returning at most `count` of the best matches in order,
+/// up to 50.
+///
+/// See <https://developers.google.com/youtube/v3/docs/search/list>
+pub async fn search(api: &YoutubeApi, query: &str, count: usize) -> Result<Vec<Video>> {
+	let url = format!("{}/search", api.api_url);
+	let count = count.min(50).to_string();
+	let query = [
+		("key", api.key.as_ref()),
+		("part", "id"),
+		("type", "video"),
+		("q", query),
+		("maxResults", count.as_ref()),
+	];
+
+	// the first page already has every result needed
+	let results: Vec<SearchResult> = std::pin::pin!(stream_paged(url, query))
+		.try_next()
 		.await?
-		.into_result()
-		.map_err(Error::Api)
-		.map(|v| v.items)
+		.unwrap_or_default();
+
+	if results.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	videos(api, results.into_iter().map(|result| result.id.video_id)).await
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	use crate::util::mock::MockServer;
+
+	async fn mock_api() -> YoutubeApi {
+		let base_url = MockServer::new()
+			.fixture_with_query(
+				"/youtube/v3/playlistItems",
+				"pageToken=mockpage2",
+				"youtube/playlist-items-2.json",
+			)
+			.fixture("/youtube/v3/playlistItems", "youtube/playlist-items-1.json")
+			.fixture("/youtube/v3/videos", "youtube/videos.json")
+			.start()
+			.await;
+
+		YoutubeApi {
+			key: "mock".to_owned(),
+			api_url: format!("{}/youtube/v3", base_url),
+		}
+	}
+
+	#[tokio::test]
+	async fn playlist_items_pages() {
+		let api = mock_api().await;
+
+		let pages: Vec<_> = playlist_items(&api, "PLmockplaylist")
+			.try_collect()
+			.await
+			.unwrap();
+
+		let ids = pages
+			.iter()
+			.map(|page| {
+				page.iter()
+					.map(|item| item.content_details.video_id.as_str())
+					.collect_vec()
+			})
+			.collect_vec();
+
+		assert_eq!(
+			ids,
+			[vec!["mockvideo01", "mockvideo02"], vec!["mockvideo03"]]
+		);
+	}
+
+	#[tokio::test]
+	async fn cached_by_api_url() {
+		let api = mock_api().await;
+
+		let cached = video(&api, "mockvideo01").await.unwrap();
+		assert_eq!(cached.map(|video| video.id), Some("mockvideo01".to_owned()));
+
+		// another server, which has no videos, doesn't see the cached video
+		let base_url = MockServer::new().start().await;
+
+		let other = YoutubeApi {
+			key: "mock".to_owned(),
+			api_url: format!("{}/youtube/v3", base_url),
+		};
+
+		assert!(video(&other, "mockvideo01").await.is_err());
+	}
+
+	#[tokio::test]
+	async fn videos_in_order() {
+		let api = mock_api().await;
+
+		let ids = ["mockvideo03", "mockvideo01", "mockvideo03"];
+
+		let found = videos(&api, ids).await.unwrap();
+
+		assert_eq!(
+			found.iter().map(|video| video.id.as_str()).collect_vec(),
+			ids
+		);
+	}
 }
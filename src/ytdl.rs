@@ -0,0 +1,227 @@
+//! Read tracks and playlists with yt-dlp, for sites like SoundCloud and
+//! Bandcamp which are streamed through it without an API of their own.
+
+use futures::StreamExt;
+
+use serde::Deserialize;
+
+use songbird::input::{AuxMetadata, YoutubeDl};
+
+use thiserror::Error;
+
+use tracing::error;
+
+use tokio::process::Command;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::audio::ComposeWithMetadata;
+use crate::youtube::compose_yt_url;
+
+/// Program run to read pages, which songbird also streams with.
+const PROGRAM: &str = "yt-dlp";
+
+/// Most tracks of a playlist read fully by a single run of [`PROGRAM`].
+const RESOLVE_BATCH: usize = 25;
+
+#[derive(Debug, Error)]
+pub enum Error {
+	#[error("failed to run {PROGRAM}: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("{PROGRAM} failed: {0}")]
+	Failed(String),
+}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Details of a single track, as printed by yt-dlp.
+#[derive(Debug, Deserialize)]
+pub struct Entry {
+	/// Page of the track, which entries of a flat playlist only have as
+	/// [`Entry::url`].
+	#[serde(default)]
+	pub webpage_url: String,
+
+	/// Page of the track in a flat playlist, otherwise the url of the media.
+	#[serde(default)]
+	url: Option<String>,
+
+	/// Url the entry was read from, to match fully read tracks to their
+	/// entries in a flat playlist.
+	#[serde(default)]
+	original_url: Option<String>,
+
+	pub title: Option<String>,
+	pub artist: Option<String>,
+	pub uploader: Option<String>,
+	pub thumbnail: Option<String>,
+
+	/// Length in seconds.
+	pub duration: Option<f64>,
+
+	/// Title of the set or album the track was read from, if any.
+	pub playlist_title: Option<String>,
+}
+
+impl Entry {
+	/// Parse a line printed by yt-dlp, logging it if it can't be parsed or has
+	/// no page to stream the track from.
+	fn parse(line: &[u8]) -> Option<Self> {
+		let mut entry = serde_json::from_slice::<Self>(line)
+			.inspect_err(|e| error!("Unable to parse {PROGRAM} entry: {:?}", e))
+			.ok()?;
+
+		if entry.webpage_url.is_empty() {
+			let Some(url) = entry.url.take() else {
+				error!("{PROGRAM} entry has no url: {:?}", entry.title);
+				return None;
+			};
+
+			entry.webpage_url = url;
+		}
+
+		Some(entry)
+	}
+
+	pub fn duration(&self) -> Option<Duration> {
+		self.duration
+			.filter(|d| d.is_finite() && *d >= 0.0)
+			.map(Duration::from_secs_f64)
+	}
+}
+
+impl From<Entry> for ComposeWithMetadata<YoutubeDl<'_>> {
+	fn from(entry: Entry) -> Self {
+		let duration = entry.duration();
+
+		compose_yt_url(
+			entry.webpage_url.clone(),
+			AuxMetadata {
+				title: entry.title,
+				artist: entry.artist.or_else(|| entry.uploader.clone()),
+				channel: entry.uploader,
+				duration,
+				source_url: Some(entry.webpage_url),
+				thumbnail: entry.thumbnail,
+				..Default::default()
+			},
+		)
+	}
+}
+
+/// Read every track at `url`, in order. Unless `playlist` is set, only the
+/// track the url points to is read, even if it is part of a playlist.
+///
+/// The tracks of a playlist are listed first, and any listed without a length
+/// or title are then read fully, a batch at a time. Tracks of a playlist which
+/// cannot be read are skipped.
+pub async fn entries(url: &str, playlist: bool) -> Result<Vec<Entry>> {
+	match playlist {
+		true => {
+			let listed = read(&["--yes-playlist", "--flat-playlist"], &[url]).await?;
+			Ok(complete(listed).await)
+		}
+		false => read(&["--no-playlist"], &[url]).await,
+	}
+}
+
+/// Run [`PROGRAM`] with `args` to read `urls`, failing only if no tracks were
+/// read.
+async fn read(args: &[&str], urls: &[&str]) -> Result<Vec<Entry>> {
+	let output = Command::new(PROGRAM)
+		.args(["-j", "--ignore-errors"])
+		.args(args)
+		.args(urls)
+		.output()
+		.await?;
+
+	let entries = output
+		.stdout
+		.split(|b| *b == b'\n')
+		.filter(|line| !line.is_empty())
+		.filter_map(Entry::parse)
+		.collect::<Vec<_>>();
+
+	// skipped tracks also fail the command, so only fail if nothing was read
+	if entries.is_empty() && !output.status.success() {
+		return Err(Error::Failed(
+			String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+		));
+	}
+
+	Ok(entries)
+}
+
+/// Fully read the entries of a flat playlist which are missing their length or
+/// title. Entries which cannot be read are kept as they were listed.
+async fn complete(mut entries: Vec<Entry>) -> Vec<Entry> {
+	let incomplete = entries
+		.iter()
+		.filter(|entry| entry.duration().is_none() || entry.title.is_none())
+		.map(|entry| entry.webpage_url.clone())
+		.collect::<Vec<_>>();
+
+	// owned batches keep the stream from borrowing across await points
+	let batches = incomplete
+		.chunks(RESOLVE_BATCH)
+		.map(<[String]>::to_vec)
+		.collect::<Vec<_>>();
+
+	let mut full = futures::stream::iter(batches)
+		.map(|urls| async move {
+			let urls = urls.iter().map(String::as_str).collect::<Vec<_>>();
+
+			read(&["--no-playlist"], &urls)
+				.await
+				.inspect_err(|e| error!("Unable to read playlist tracks: {:?}", e))
+				.unwrap_or_default()
+		})
+		// same concurrency as fetching playlists
+		.buffered(4)
+		.flat_map(futures::stream::iter)
+		.filter_map(|entry| async move { Some((entry.original_url.clone()?, entry)) })
+		.collect::<HashMap<_, _>>()
+		.await;
+
+	for entry in &mut entries {
+		if let Some(mut read) = full.remove(&entry.webpage_url) {
+			read.playlist_title = read.playlist_title.or(entry.playlist_title.take());
+			*entry = read;
+		}
+	}
+
+	entries
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn parse_entries() {
+		let full = Entry::parse(
+			br#"{"webpage_url": "https://soundcloud.com/a/b", "url": "https://cf-media.sndcdn.com/b.mp3", "original_url": "https://soundcloud.com/a/b?in=a/sets/set", "title": "B", "duration": 30.5}"#,
+		)
+		.unwrap();
+
+		assert_eq!(full.webpage_url, "https://soundcloud.com/a/b");
+		assert_eq!(
+			full.original_url.as_deref(),
+			Some("https://soundcloud.com/a/b?in=a/sets/set")
+		);
+		assert_eq!(full.duration(), Some(Duration::from_secs_f64(30.5)));
+
+		let flat = Entry::parse(
+			br#"{"_type": "url", "url": "https://soundcloud.com/a/c", "title": "C", "playlist_title": "Set"}"#,
+		)
+		.unwrap();
+
+		assert_eq!(flat.webpage_url, "https://soundcloud.com/a/c");
+		assert_eq!(flat.playlist_title.as_deref(), Some("Set"));
+		assert_eq!(flat.duration(), None);
+
+		assert!(Entry::parse(br#"{"title": "No url"}"#).is_none());
+		assert!(Entry::parse(b"WARNING: not json").is_none());
+	}
+}